@@ -0,0 +1,386 @@
+//! Documentation pages, as generated by `adzec doc`.
+//!
+//! Each module (source file) gets a page listing its functions: their
+//! signatures, their doc comments, and the functions they call. A function
+//! named in backticks in a doc comment, or called, is linked to wherever it's
+//! documented, in the same module or another.
+//!
+//! Doc comments are taken as plain paragraphs separated by blank lines, with
+//! `code` in backticks; nothing more of Markdown is interpreted.
+
+use std::collections::{ BTreeSet, HashMap };
+
+use parse::Tree;
+use parse::syntax::{ self, downcast };
+
+#[derive (Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+/// A module to document: its name, and its parsed source.
+///
+pub struct Module<'a> {
+    pub name: &'a str,
+    pub tree: &'a Tree,
+}
+
+/// Generate a page for each of `modules`, as a file name and its contents.
+///
+pub fn document(modules: &[Module], format: Format) -> Vec<(String, String)> {
+    // Which module each function is documented in, for links. Where a name
+    // is in more than one, the first is as good a guess as any.
+    let mut index = HashMap::new();
+    for module in modules {
+        for function in &module.tree.functions {
+            index.entry(function.signature.name.string.as_str()).or_insert(module.name);
+        }
+    }
+
+    modules.iter().map(|module| {
+        let page = Page {
+            module: module.name,
+            index: &index,
+            format,
+        };
+        let name = format!("{}.{}", module.name, format.extension());
+        (name, page.render(module.tree))
+    }).collect()
+}
+
+/// The rendering of one module's page.
+///
+struct Page<'a> {
+    module: &'a str,
+    index: &'a HashMap<&'a str, &'a str>,
+    format: Format,
+}
+
+impl<'a> Page<'a> {
+    fn render(&self, tree: &Tree) -> String {
+        let mut out = String::new();
+
+        match self.format {
+            Format::Html => {
+                out.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
+                out.push_str("<meta charset=\"utf-8\">\n");
+                out.push_str(&format!("<title>{}</title>\n", escape(self.module)));
+                out.push_str("</head>\n<body>\n");
+                out.push_str(&format!("<h1>Module <code>{}</code></h1>\n", escape(self.module)));
+            },
+            Format::Markdown => {
+                out.push_str(&format!("# Module `{}`\n", self.module));
+            },
+        }
+
+        for function in &tree.functions {
+            self.function(&mut out, function);
+        }
+
+        if self.format == Format::Html {
+            out.push_str("</body>\n</html>\n");
+        }
+        out
+    }
+
+    fn function(&self, out: &mut String, function: &syntax::function::Syntax) {
+        let name = &function.signature.name.string;
+        let signature = signature(&function.signature);
+
+        match self.format {
+            Format::Html => {
+                out.push_str(&format!(
+                    "<h2 id=\"{}\"><code>{}</code></h2>\n", anchor(name), escape(&signature),
+                ));
+                for paragraph in paragraphs(&function.doc) {
+                    out.push_str(&format!("<p>{}</p>\n", self.text(&paragraph)));
+                }
+            },
+            Format::Markdown => {
+                out.push_str(&format!(
+                    "\n<a id=\"{}\"></a>\n## `{}`\n", anchor(name), signature,
+                ));
+                for paragraph in paragraphs(&function.doc) {
+                    out.push_str(&format!("\n{}\n", self.text(&paragraph)));
+                }
+            },
+        }
+
+        let calls = calls(function);
+        if !calls.is_empty() {
+            let links: Vec<String> = calls.iter()
+                .map(|callee| self.code(callee))
+                .collect();
+            match self.format {
+                Format::Html => out.push_str(&format!("<p>Calls: {}</p>\n", links.join(", "))),
+                Format::Markdown => out.push_str(&format!("\nCalls: {}\n", links.join(", "))),
+            }
+        }
+    }
+
+    /// Render a paragraph of doc comment, linking any code that names a
+    /// function.
+    ///
+    fn text(&self, paragraph: &str) -> String {
+        // Odd pieces are between backticks.
+        paragraph.split('`').enumerate().map(|(index, piece)| {
+            match (index % 2, self.format) {
+                (1, _) => self.code(piece),
+                (_, Format::Html) => escape(piece),
+                (_, Format::Markdown) => piece.to_string(),
+            }
+        }).collect()
+    }
+
+    /// Render `code`, as a link if it's the name of a documented function.
+    ///
+    fn code(&self, code: &str) -> String {
+        let target = self.index.get(code).map(|module| match *module == self.module {
+            true  => format!("#{}", anchor(code)),
+            false => format!("{}.{}#{}", module, self.format.extension(), anchor(code)),
+        });
+
+        match (self.format, target) {
+            (Format::Html, Some(target)) => {
+                format!("<a href=\"{}\"><code>{}</code></a>", escape(&target), escape(code))
+            },
+            (Format::Html, None) => format!("<code>{}</code>", escape(code)),
+            (Format::Markdown, Some(target)) => format!("[`{}`]({})", code, target),
+            (Format::Markdown, None) => format!("`{}`", code),
+        }
+    }
+}
+
+/// A function's signature, as it would be written.
+///
+fn signature(signature: &syntax::signature::Syntax) -> String {
+    let parameters: Vec<String> = signature.parameters.iter()
+        .filter_map(|parameter| downcast::<syntax::parameter::Syntax>(&**parameter))
+        .map(|parameter| match parameter.ty {
+            Some(ref ty) => format!("{}: {}", parameter.name.string, ty),
+            None => parameter.name.string.clone(),
+        })
+        .collect();
+    let mut out = format!("{}({})", signature.name.string, parameters.join(", "));
+    if let Some(ref ty) = signature.return_type {
+        out.push_str(&format!(" -> {}", ty));
+    }
+    out
+}
+
+fn anchor(name: &str) -> String {
+    format!("fn.{}", name)
+}
+
+/// Join doc comment lines into paragraphs, split at blank lines.
+///
+fn paragraphs(lines: &[String]) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in lines {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join(" "));
+                current.clear();
+            }
+        }
+        else {
+            current.push(line.trim());
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join(" "));
+    }
+    paragraphs
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The names of the functions `function` calls, in alphabetical order.
+///
+fn calls(function: &syntax::function::Syntax) -> BTreeSet<String> {
+    let mut calls = BTreeSet::new();
+    for statement in &function.block.statements {
+        walk(&**statement, &mut calls);
+    }
+    calls
+}
+
+fn walk(node: &dyn syntax::Syntax, calls: &mut BTreeSet<String>) {
+    if let Some(statement) = downcast::<syntax::statement::Syntax>(node) {
+        walk(&*statement.block, calls);
+    }
+    else if let Some(block) = downcast::<syntax::block::Syntax>(node) {
+        for statement in &block.statements {
+            walk(&**statement, calls);
+        }
+    }
+    else if let Some(init) = downcast::<syntax::init::Syntax>(node) {
+        walk(&*init.rhs, calls);
+    }
+    else if let Some(assign) = downcast::<syntax::assign::Syntax>(node) {
+        walk(&*assign.place, calls);
+        walk(&*assign.rhs, calls);
+    }
+    else if let Some(ret) = downcast::<syntax::ret::Syntax>(node) {
+        if let Some(ref value) = ret.value {
+            walk(value, calls);
+        }
+    }
+    else if let Some(conditional) = downcast::<syntax::conditional::Syntax>(node) {
+        walk(&conditional.condition, calls);
+        walk(&conditional.then_block, calls);
+        if let Some(ref else_block) = conditional.else_block {
+            walk(&**else_block, calls);
+        }
+    }
+    else if let Some(expression) = downcast::<syntax::expression::Syntax>(node) {
+        walk(&*expression.value, calls);
+    }
+    else if let Some(binary) = downcast::<syntax::binary::Syntax>(node) {
+        walk(&*binary.lhs, calls);
+        walk(&*binary.rhs, calls);
+    }
+    else if let Some(cast) = downcast::<syntax::cast::Syntax>(node) {
+        walk(&*cast.value, calls);
+    }
+    else if let Some(call) = downcast::<syntax::call::Syntax>(node) {
+        calls.insert(call.name.string.clone());
+        for argument in &call.arguments {
+            walk(&**argument, calls);
+        }
+    }
+    else if let Some(construct) = downcast::<syntax::construct::Syntax>(node) {
+        for (_, value) in &construct.fields {
+            walk(&**value, calls);
+        }
+    }
+    else if let Some(field) = downcast::<syntax::field::Syntax>(node) {
+        walk(&*field.value, calls);
+    }
+    else if let Some(variant) = downcast::<syntax::variant::Syntax>(node) {
+        for argument in &variant.arguments {
+            walk(&**argument, calls);
+        }
+    }
+    else if let Some(array) = downcast::<syntax::array::Syntax>(node) {
+        for element in &array.elements {
+            walk(&**element, calls);
+        }
+    }
+    else if let Some(tuple) = downcast::<syntax::tuple::Syntax>(node) {
+        for element in &tuple.elements {
+            walk(&**element, calls);
+        }
+    }
+    else if let Some(index) = downcast::<syntax::index::Syntax>(node) {
+        walk(&*index.value, calls);
+        walk(&*index.index, calls);
+    }
+    else if let Some(reference) = downcast::<syntax::reference::Syntax>(node) {
+        walk(&*reference.value, calls);
+    }
+    else if let Some(dereference) = downcast::<syntax::dereference::Syntax>(node) {
+        walk(&*dereference.value, calls);
+    }
+    else if let Some(matching) = downcast::<syntax::matching::Syntax>(node) {
+        walk(&matching.value, calls);
+        for arm in &matching.arms {
+            walk(&*arm.body, calls);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ document, Format, Module };
+    use lex::Lexer;
+    use parse::{ Parser, Tree };
+
+    fn tree(source: &str) -> Tree {
+        Parser::new().parse(Lexer::new().lex(source).unwrap()).unwrap()
+    }
+
+    const MATHS: &str = "\
+        ## Add one to `x`, if it's < 100.\n\
+        ##\n\
+        ## See `twice` and `shout`.\n\
+        inc(x: i32) -> i32 { return x + 1; }\n\
+        twice(x: i32) -> i32 { return inc(inc(x)); }\n";
+
+    const MAIN: &str = "\
+        ## Print `twice` of 2 & exit.\n\
+        main() -> i32 { print(twice(2)); return 0; }\n";
+
+    fn pages(format: Format) -> Vec<(String, String)> {
+        let (maths, main) = (tree(MATHS), tree(MAIN));
+        document(&[
+            Module { name: "maths", tree: &maths },
+            Module { name: "main", tree: &main },
+        ], format)
+    }
+
+    #[test]
+    fn html_pages_link_functions_in_and_across_modules() {
+        let pages = pages(Format::Html);
+        assert_eq!(pages[0].0, "maths.html");
+        assert_eq!(pages[0].1, "\
+            <!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>maths</title>\n</head>\n<body>\n\
+            <h1>Module <code>maths</code></h1>\n\
+            <h2 id=\"fn.inc\"><code>inc(x: i32) -&gt; i32</code></h2>\n\
+            <p>Add one to <code>x</code>, if it's &lt; 100.</p>\n\
+            <p>See <a href=\"#fn.twice\"><code>twice</code></a> and <code>shout</code>.</p>\n\
+            <h2 id=\"fn.twice\"><code>twice(x: i32) -&gt; i32</code></h2>\n\
+            <p>Calls: <a href=\"#fn.inc\"><code>inc</code></a></p>\n\
+            </body>\n</html>\n");
+
+        assert_eq!(pages[1].0, "main.html");
+        assert!(pages[1].1.contains(
+            "<p>Print <a href=\"maths.html#fn.twice\"><code>twice</code></a> of 2 &amp; \
+             exit.</p>\n<p>Calls: <code>print</code>, \
+             <a href=\"maths.html#fn.twice\"><code>twice</code></a></p>\n"
+        ), "{}", pages[1].1);
+    }
+
+    #[test]
+    fn markdown_pages_link_functions_in_and_across_modules() {
+        let pages = pages(Format::Markdown);
+        assert_eq!(pages[0].0, "maths.md");
+        assert_eq!(pages[0].1, "\
+            # Module `maths`\n\
+            \n<a id=\"fn.inc\"></a>\n## `inc(x: i32) -> i32`\n\
+            \nAdd one to `x`, if it's < 100.\n\
+            \nSee [`twice`](#fn.twice) and `shout`.\n\
+            \n<a id=\"fn.twice\"></a>\n## `twice(x: i32) -> i32`\n\
+            \nCalls: [`inc`](#fn.inc)\n");
+
+        assert_eq!(pages[1], ("main.md".to_string(), "\
+            # Module `main`\n\
+            \n<a id=\"fn.main\"></a>\n## `main() -> i32`\n\
+            \nPrint [`twice`](maths.md#fn.twice) of 2 & exit.\n\
+            \nCalls: `print`, [`twice`](maths.md#fn.twice)\n".to_string()));
+    }
+}
@@ -0,0 +1,760 @@
+//! Textual LLVM IR (`.ll`) back-end.
+//!
+//! Nothing here links to LLVM; the output is meant to be fed to a separately
+//! installed `llc` or `clang`, e.g. `adzec --emit=llvm-ir a.adze | llc`.
+//!
+//! The IR maps nearly one-to-one onto LLVM's: block parameters become `phi`
+//! nodes, and constants are folded into their uses.
+//!
+//! Signed and unsigned integers of the same width are the same LLVM type;
+//! only comparisons, conversions and printing tell them apart. Comparisons
+//! give an `i1`, which is extended to the IR's `i32`. Floats convert to
+//! integers with the saturating `llvm.fptosi.sat` and `llvm.fptoui.sat`
+//! intrinsics.
+//!
+//! Arithmetic that traps on overflow uses the `llvm.*.with.overflow`
+//! intrinsics, branching to a block that calls `fflush`, so what was printed
+//! isn't lost, and then `llvm.trap`; arithmetic that saturates uses
+//! `llvm.*.sat`, or `llvm.*mul.fix.sat` with a scale of 0.
+//!
+//! Chars are `i32`s. Integers convert to them through an `i64`, selecting
+//! U+FFFD if they aren't scalar values, and they're printed UTF-8 encoded by
+//! a private helper, `@.print_char`, which calls the C library's `putchar`.
+//!
+//! Strings become private global constants, and are printed with the C
+//! library's `printf`, as are numbers; a NaN has its sign cleared first, so
+//! it's printed as `nan` whatever it was.
+//!
+//! Slots become `alloca`s of bytes, all at the start of the entry block so
+//! LLVM can promote them to registers; loads and stores at an offset index
+//! the bytes with `getelementptr`, and copies call `llvm.memcpy`. An
+//! element's address is a `getelementptr` over an array of bytes the size of
+//! an element.
+//!
+//! A `Switch` is LLVM's `switch`, which LLVM makes a jump table of where the
+//! cases are dense. A `Trap` calls `llvm.trap` the same way.
+//!
+//! A function returning two values returns a struct of them, built with
+//! `insertvalue`; its caller takes them out with `extractvalue`.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use ir::{ self, BinaryOp, Block, InstructionKind, Overflow, Terminator, Type, Value };
+
+pub fn emit(module: &ir::Module) -> Result<String, String> {
+    let mut out = String::new();
+    let mut globals = String::new();
+
+    for (index, function) in module.functions.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let (code, strings) = Function::new(function).emit();
+        out.push_str(&code);
+        globals.push_str(&strings);
+    }
+
+    for declaration in declarations(module) {
+        globals.push_str(&declaration);
+        globals.push('\n');
+    }
+
+    if !globals.is_empty() {
+        out.push('\n');
+        out.push_str(&globals);
+    }
+    Ok(out)
+}
+
+/// The external functions and format strings `module` needs.
+///
+fn declarations(module: &ir::Module) -> BTreeSet<String> {
+    let mut declarations = BTreeSet::new();
+
+    for function in &module.functions {
+        for instruction in function.blocks.iter().flat_map(|data| &data.instructions) {
+            match instruction.kind {
+                InstructionKind::Print(value) if function.type_of(value) == Type::Char => {
+                    declarations.insert(PRINT_CHAR.to_string());
+                    declarations.insert("declare i32 @putchar(i32)".to_string());
+                },
+                InstructionKind::Print(_) => {
+                    for format in ["d", "u", "lld", "llu", "g", "s"] {
+                        declarations.insert(format!(
+                            "@.fmt.{} = private unnamed_addr constant [{} x i8] c\"%{}\\00\"",
+                            format, format.len() + 2, format,
+                        ));
+                    }
+                    declarations.insert("declare i32 @printf(ptr, ...)".to_string());
+                },
+                InstructionKind::Convert(value) => {
+                    let from = function.type_of(value);
+                    let to = function.type_of(instruction.result);
+                    if to.is_integer() && from.is_float() {
+                        declarations.insert(format!(
+                            "declare {} {}({})",
+                            llvm_type(to), saturating_conversion(from, to), llvm_type(from),
+                        ));
+                    }
+                },
+                InstructionKind::Binary(op, overflow, lhs, _) if overflow != Overflow::Wrap => {
+                    let ty = llvm_type(function.type_of(lhs));
+                    let intrinsic = checked_arithmetic(op, overflow, function.type_of(lhs));
+                    if overflow == Overflow::Trap {
+                        declarations.insert("declare void @llvm.trap()".to_string());
+                        declarations.insert("declare i32 @fflush(ptr)".to_string());
+                    }
+                    declarations.insert(match (overflow, op) {
+                        (Overflow::Trap, _) => {
+                            format!("declare {{{}, i1}} {}({}, {})", ty, intrinsic, ty, ty)
+                        },
+                        (_, BinaryOp::Mul) => {
+                            format!("declare {} {}({}, {}, i32)", ty, intrinsic, ty, ty)
+                        },
+                        _ => format!("declare {} {}({}, {})", ty, intrinsic, ty, ty),
+                    });
+                },
+                InstructionKind::Copy(..) => {
+                    declarations.insert(
+                        "declare void @llvm.memcpy.p0.p0.i64(ptr, ptr, i64, i1)".to_string()
+                    );
+                },
+                _ => (),
+            }
+        }
+        if function.blocks.iter().any(|data| data.terminator == Some(Terminator::Trap)) {
+            declarations.insert("declare void @llvm.trap()".to_string());
+            declarations.insert("declare i32 @fflush(ptr)".to_string());
+        }
+    }
+
+    declarations
+}
+
+/// Print the UTF-8 encoding of a char, a byte at a time.
+///
+const PRINT_CHAR: &str = "\
+define private void @.print_char(i32 %c) {
+entry:
+  %ascii = icmp ult i32 %c, 128
+  br i1 %ascii, label %one, label %not.one
+not.one:
+  %two.bytes = icmp ult i32 %c, 2048
+  br i1 %two.bytes, label %two, label %not.two
+not.two:
+  %three.bytes = icmp ult i32 %c, 65536
+  br i1 %three.bytes, label %three, label %four
+one:
+  %one.0 = call i32 @putchar(i32 %c)
+  ret void
+two:
+  %two.s = lshr i32 %c, 6
+  %two.l = or i32 %two.s, 192
+  %two.0 = call i32 @putchar(i32 %two.l)
+  br label %last
+three:
+  %three.s = lshr i32 %c, 12
+  %three.l = or i32 %three.s, 224
+  %three.0 = call i32 @putchar(i32 %three.l)
+  br label %second.last
+four:
+  %four.s = lshr i32 %c, 18
+  %four.l = or i32 %four.s, 240
+  %four.0 = call i32 @putchar(i32 %four.l)
+  %four.s1 = lshr i32 %c, 12
+  %four.m1 = and i32 %four.s1, 63
+  %four.c1 = or i32 %four.m1, 128
+  %four.1 = call i32 @putchar(i32 %four.c1)
+  br label %second.last
+second.last:
+  %second.s = lshr i32 %c, 6
+  %second.m = and i32 %second.s, 63
+  %second.c = or i32 %second.m, 128
+  %second.0 = call i32 @putchar(i32 %second.c)
+  br label %last
+last:
+  %last.m = and i32 %c, 63
+  %last.c = or i32 %last.m, 128
+  %last.0 = call i32 @putchar(i32 %last.c)
+  ret void
+}";
+
+/// A quiet NaN, with its sign clear.
+///
+const NAN: &str = "0x7FF8000000000000";
+
+/// Flush every C stream, so what was printed isn't lost, and trap.
+///
+const TRAP: &str = "  call i32 @fflush(ptr null)\n  call void @llvm.trap()\n  unreachable\n";
+
+/// The state of emitting a single function.
+///
+struct Function<'a> {
+    function: &'a ir::Function,
+
+    /// The operand text for each value: either a name, or a constant.
+    operands: Vec<String>,
+
+    out: String,
+    /// The definitions of the function's strings.
+    globals: String,
+}
+
+impl<'a> Function<'a> {
+    fn new(function: &'a ir::Function) -> Self {
+        let mut operands: Vec<String> = (0..function.values.len())
+            .map(|index| format!("%v{}", index))
+            .collect();
+
+        for data in &function.blocks {
+            for instruction in &data.instructions {
+                match instruction.kind {
+                    InstructionKind::Const(value) => {
+                        operands[instruction.result.0] = value.to_string();
+                    },
+                    // LLVM takes floats of either type as the hex of a double.
+                    InstructionKind::Float(value) => {
+                        operands[instruction.result.0] = format!("0x{:016X}", value.to_bits());
+                    },
+                    InstructionKind::Str(_) => {
+                        operands[instruction.result.0] = format!(
+                            "@.str.{}.{}", function.name, instruction.result.0,
+                        );
+                    },
+                    InstructionKind::Print(_) |
+                    InstructionKind::Store(..) |
+                    InstructionKind::Copy(..) => {
+                        operands[instruction.result.0] = "0".to_string();
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        Function {
+            function,
+            operands,
+            out: String::new(),
+            globals: String::new(),
+        }
+    }
+
+    /// Emit the function's definition, and separately its strings'.
+    ///
+    fn emit(mut self) -> (String, String) {
+        let function = self.function;
+
+        let parameters: Vec<String> = function.parameters().iter()
+            .map(|parameter| self.typed(*parameter))
+            .collect();
+        writeln!(
+            self.out, "define {} @{}({}) {{",
+            returns_type(&function.returns), function.name, parameters.join(", "),
+        ).unwrap();
+
+        let predecessors = function.predecessors();
+        let mut traps = false;
+
+        for (index, data) in function.blocks.iter().enumerate() {
+            let block = Block(index);
+            writeln!(self.out, "{}:", label(block)).unwrap();
+
+            if block == function.entry() {
+                let slots = function.blocks.iter().flat_map(|data| &data.instructions);
+                for instruction in slots {
+                    if let InstructionKind::Slot(size, align) = instruction.kind {
+                        writeln!(
+                            self.out, "  %v{} = alloca [{} x i8], align {}",
+                            instruction.result.0, size, align,
+                        ).unwrap();
+                    }
+                }
+            }
+
+            // The entry's parameters are the function's.
+            if block != function.entry() {
+                for (position, parameter) in data.parameters.iter().enumerate() {
+                    let incoming: Vec<String> = predecessors[index].iter().map(|predecessor| {
+                        let argument = Self::argument(
+                            function, *predecessor, block, position,
+                        );
+                        format!(
+                            "[ {}, %{} ]",
+                            self.operands[argument.0], exit_label(function, *predecessor),
+                        )
+                    }).collect();
+
+                    writeln!(
+                        self.out, "  %v{} = phi {} {}",
+                        parameter.0, llvm_type(function.type_of(*parameter)),
+                        incoming.join(", "),
+                    ).unwrap();
+                }
+            }
+
+            for instruction in &data.instructions {
+                let result = instruction.result;
+                let ty = llvm_type(function.type_of(result));
+
+                match instruction.kind {
+                    // Folded into uses, or allocated in the entry block.
+                    InstructionKind::Const(_) |
+                    InstructionKind::Float(_) |
+                    InstructionKind::Slot(..) => (),
+
+                    InstructionKind::Load(address, offset) => {
+                        let address = self.address("a", result, address, offset);
+                        writeln!(
+                            self.out, "  %v{} = load {}, ptr {}, align {}",
+                            result.0, ty, address, function.type_of(result).size(),
+                        ).unwrap();
+                    },
+                    // The result is always 0, so it's folded into uses.
+                    InstructionKind::Store(address, offset, value) => {
+                        let address = self.address("a", result, address, offset);
+                        writeln!(
+                            self.out, "  store {}, ptr {}, align {}",
+                            self.typed(value), address, function.type_of(value).size(),
+                        ).unwrap();
+                    },
+                    // Likewise.
+                    InstructionKind::Copy(to, to_offset, from, from_offset, size) => {
+                        let to = self.address("a", result, to, to_offset);
+                        let from = self.address("s", result, from, from_offset);
+                        writeln!(
+                            self.out,
+                            "  call void @llvm.memcpy.p0.p0.i64(ptr {}, ptr {}, i64 {}, i1 false)",
+                            to, from, size,
+                        ).unwrap();
+                    },
+                    InstructionKind::Element(address, index, size) => {
+                        writeln!(
+                            self.out, "  %v{} = getelementptr [{} x i8], {}, {}",
+                            result.0, size, self.typed(address), self.typed(index),
+                        ).unwrap();
+                    },
+
+                    InstructionKind::Str(ref string) => {
+                        let mut bytes = string.clone().into_bytes();
+                        bytes.push(0);
+                        writeln!(
+                            self.globals, "{} = private unnamed_addr constant [{} x i8] c\"{}\"",
+                            self.operands[result.0], bytes.len(), escape(&bytes),
+                        ).unwrap();
+                    },
+
+                    InstructionKind::Binary(op, overflow, lhs, rhs)
+                        if overflow != Overflow::Wrap => {
+                        let operand_type = function.type_of(lhs);
+                        let intrinsic = checked_arithmetic(op, overflow, operand_type);
+                        let arguments = format!("{}, {}", self.typed(lhs), self.typed(rhs));
+
+                        match (overflow, op) {
+                            (Overflow::Trap, _) => {
+                                let pair = format!("{{{}, i1}}", ty);
+                                writeln!(
+                                    self.out, "  %.o{} = call {} {}({})",
+                                    result.0, pair, intrinsic, arguments,
+                                ).unwrap();
+                                writeln!(
+                                    self.out, "  %v{} = extractvalue {} %.o{}, 0",
+                                    result.0, pair, result.0,
+                                ).unwrap();
+                                writeln!(
+                                    self.out, "  %.f{} = extractvalue {} %.o{}, 1",
+                                    result.0, pair, result.0,
+                                ).unwrap();
+                                writeln!(
+                                    self.out, "  br i1 %.f{}, label %overflow, label %{}",
+                                    result.0, checked_label(result),
+                                ).unwrap();
+                                writeln!(self.out, "{}:", checked_label(result)).unwrap();
+                                traps = true;
+                            },
+                            (_, BinaryOp::Mul) => writeln!(
+                                self.out, "  %v{} = call {} {}({}, i32 0)",
+                                result.0, ty, intrinsic, arguments,
+                            ).unwrap(),
+                            _ => writeln!(
+                                self.out, "  %v{} = call {} {}({})",
+                                result.0, ty, intrinsic, arguments,
+                            ).unwrap(),
+                        }
+                    },
+
+                    InstructionKind::Binary(op, _, lhs, rhs) => {
+                        let operand_type = function.type_of(lhs);
+                        let operands = format!(
+                            "{} {}, {}",
+                            llvm_type(operand_type), self.operands[lhs.0], self.operands[rhs.0],
+                        );
+
+                        if op.is_comparison() {
+                            let (instruction, predicate) = comparison(op, operand_type);
+                            writeln!(
+                                self.out, "  %.b{} = {} {} {}",
+                                result.0, instruction, predicate, operands,
+                            ).unwrap();
+                            writeln!(
+                                self.out, "  %v{} = zext i1 %.b{} to i32", result.0, result.0,
+                            ).unwrap();
+                        }
+                        else {
+                            let prefix = if operand_type.is_float() { "f" } else { "" };
+                            writeln!(
+                                self.out, "  %v{} = {}{} {}", result.0, prefix, op, operands,
+                            ).unwrap();
+                        }
+                    },
+
+                    InstructionKind::Convert(value) => {
+                        let from = function.type_of(value);
+                        let to = function.type_of(result);
+                        if to == Type::Char {
+                            self.convert_to_char(result, value);
+                            continue;
+                        }
+                        let conversion = match (from.is_float(), to.is_float()) {
+                            (true, false) => {
+                                writeln!(
+                                    self.out, "  %v{} = call {} {}({})",
+                                    result.0, ty, saturating_conversion(from, to),
+                                    self.typed(value),
+                                ).unwrap();
+                                continue;
+                            },
+                            (false, false) if to.bits() < from.bits() => "trunc",
+                            (false, false) if to.bits() > from.bits() => match from.is_signed() {
+                                true  => "sext",
+                                false => "zext",
+                            },
+                            // Only the signedness differs, which LLVM doesn't
+                            // distinguish.
+                            (false, false) => "bitcast",
+                            (false, true) => match from.is_signed() {
+                                true  => "sitofp",
+                                false => "uitofp",
+                            },
+                            (true, true) if from == Type::F32 => "fpext",
+                            (true, true) => "fptrunc",
+                        };
+                        writeln!(
+                            self.out, "  %v{} = {} {} to {}",
+                            result.0, conversion, self.typed(value), ty,
+                        ).unwrap();
+                    },
+
+                    InstructionKind::Call(ref name, ref arguments, ref more) => {
+                        let arguments: Vec<String> = arguments.iter()
+                            .map(|argument| self.typed(*argument))
+                            .collect();
+                        if more.is_empty() {
+                            writeln!(
+                                self.out, "  %v{} = call {} @{}({})",
+                                result.0, ty, name, arguments.join(", "),
+                            ).unwrap();
+                        }
+                        else {
+                            let results = instruction.results();
+                            let types: Vec<Type> = results.iter()
+                                .map(|result| function.type_of(*result))
+                                .collect();
+                            let ty = returns_type(&types);
+                            writeln!(
+                                self.out, "  %.r{} = call {} @{}({})",
+                                result.0, ty, name, arguments.join(", "),
+                            ).unwrap();
+                            for (index, value) in results.iter().enumerate() {
+                                writeln!(
+                                    self.out, "  %v{} = extractvalue {} %.r{}, {}",
+                                    value.0, ty, result.0, index,
+                                ).unwrap();
+                            }
+                        }
+                    },
+
+                    // The result is always 0, so it's folded into uses, and
+                    // `printf`'s is ignored.
+                    InstructionKind::Print(value) if function.type_of(value) == Type::Char => {
+                        writeln!(
+                            self.out, "  call void @.print_char({})", self.typed(value),
+                        ).unwrap();
+                    },
+                    InstructionKind::Print(value) => {
+                        let value_type = function.type_of(value);
+                        let mut argument = self.typed(value);
+
+                        // Variadic arguments are never narrower than `int`,
+                        // nor `float`s.
+                        let extension = match value_type {
+                            Type::I8 | Type::I16 => Some(("sext", "i32")),
+                            Type::U8 | Type::U16 => Some(("zext", "i32")),
+                            Type::F32 => Some(("fpext", "double")),
+                            _ => None,
+                        };
+                        if let Some((extension, extended)) = extension {
+                            writeln!(
+                                self.out, "  %.e{} = {} {} to {}",
+                                result.0, extension, argument, extended,
+                            ).unwrap();
+                            argument = format!("{} %.e{}", extended, result.0);
+                        }
+                        // `printf` prints a NaN whose sign is set as `-nan`;
+                        // print them all as `nan`, as the VM does.
+                        if value_type.is_float() {
+                            writeln!(
+                                self.out, "  %.n{} = fcmp uno {}, 0.0", result.0, argument,
+                            ).unwrap();
+                            writeln!(
+                                self.out, "  %.f{} = select i1 %.n{}, double {}, {}",
+                                result.0, result.0, NAN, argument,
+                            ).unwrap();
+                            argument = format!("double %.f{}", result.0);
+                        }
+
+                        let format = match value_type {
+                            Type::I8 | Type::I16 | Type::I32 | Type::U8 | Type::U16 => "@.fmt.d",
+                            Type::U32 => "@.fmt.u",
+                            Type::I64 => "@.fmt.lld",
+                            Type::U64 => "@.fmt.llu",
+                            Type::F32 | Type::F64 => "@.fmt.g",
+                            Type::Str => "@.fmt.s",
+                            Type::Char => unreachable!("chars are printed by @.print_char"),
+                            Type::Ptr => unreachable!("ptrs aren't printed"),
+                        };
+                        writeln!(
+                            self.out, "  %.p{} = call i32 (ptr, ...) @printf(ptr {}, {})",
+                            result.0, format, argument,
+                        ).unwrap();
+                    },
+                }
+            }
+
+            match *data.terminator.as_ref().unwrap() {
+                Terminator::Return(ref values) if values.len() == 1 => {
+                    writeln!(self.out, "  ret {}", self.typed(values[0])).unwrap()
+                },
+                Terminator::Return(ref values) => {
+                    let types: Vec<Type> = values.iter()
+                        .map(|value| function.type_of(*value))
+                        .collect();
+                    let ty = returns_type(&types);
+                    let mut aggregate = "undef".to_string();
+                    for (position, value) in values.iter().enumerate() {
+                        writeln!(
+                            self.out, "  %.ret{}.{} = insertvalue {} {}, {}, {}",
+                            index, position, ty, aggregate, self.typed(*value), position,
+                        ).unwrap();
+                        aggregate = format!("%.ret{}.{}", index, position);
+                    }
+                    writeln!(self.out, "  ret {} {}", ty, aggregate).unwrap();
+                },
+                Terminator::Branch(ref target) => {
+                    writeln!(self.out, "  br label %{}", label(target.block)).unwrap()
+                },
+                Terminator::CondBranch(condition, ref then_target, ref else_target) => {
+                    writeln!(
+                        self.out, "  %.c{} = icmp ne {}, 0",
+                        index, self.typed(condition),
+                    ).unwrap();
+                    writeln!(
+                        self.out, "  br i1 %.c{}, label %{}, label %{}",
+                        index, label(then_target.block), label(else_target.block),
+                    ).unwrap();
+                },
+                Terminator::Switch(value, ref cases, ref default) => {
+                    let ty = llvm_type(function.type_of(value));
+                    let cases: Vec<String> = cases.iter().map(|(case, target)| {
+                        format!("{} {}, label %{}", ty, case, label(target.block))
+                    }).collect();
+                    writeln!(
+                        self.out, "  switch {}, label %{} [ {} ]",
+                        self.typed(value), label(default.block), cases.join("  "),
+                    ).unwrap();
+                },
+                Terminator::Trap => {
+                    self.out.push_str(TRAP);
+                },
+            }
+        }
+
+        if traps {
+            self.out.push_str("overflow:\n");
+            self.out.push_str(TRAP);
+        }
+
+        self.out.push_str("}\n");
+        (self.out, self.globals)
+    }
+
+    /// The argument `predecessor` passes to parameter `position` of `block`.
+    ///
+    fn argument(
+        function: &ir::Function,
+        predecessor: Block,
+        block: Block,
+        position: usize,
+    ) -> Value {
+        let terminator = function.block(predecessor).terminator.as_ref().unwrap();
+        let target = terminator.targets().into_iter()
+            .find(|target| target.block == block)
+            .unwrap();
+        target.arguments[position]
+    }
+
+    /// Convert integer `value` to the char `result`: U+FFFD if, extended to
+    /// an `i64`, it isn't below 0x110000, or it's a surrogate.
+    ///
+    fn convert_to_char(&mut self, result: Value, value: Value) {
+        let from = self.function.type_of(value);
+        let wide = match (from.bits(), from.is_signed()) {
+            (64, _) => self.operands[value.0].clone(),
+            (_, signed) => {
+                writeln!(
+                    self.out, "  %.w{} = {} {} to i64",
+                    result.0, if signed { "sext" } else { "zext" }, self.typed(value),
+                ).unwrap();
+                format!("%.w{}", result.0)
+            },
+        };
+
+        let r = result.0;
+        writeln!(self.out, "  %.s{} = sub i64 {}, 55296", r, wide).unwrap();
+        writeln!(self.out, "  %.d{} = icmp ult i64 %.s{}, 2048", r, r).unwrap();
+        writeln!(self.out, "  %.h{} = icmp ugt i64 {}, 1114111", r, wide).unwrap();
+        writeln!(self.out, "  %.i{} = or i1 %.d{}, %.h{}", r, r, r).unwrap();
+        writeln!(self.out, "  %.t{} = trunc i64 {} to i32", r, wide).unwrap();
+        writeln!(self.out, "  %v{} = select i1 %.i{}, i32 65533, i32 %.t{}", r, r, r).unwrap();
+    }
+
+    /// The address `offset` bytes past `address`, for the instruction giving
+    /// `result`, named with `prefix` if it has to be computed.
+    ///
+    fn address(&mut self, prefix: &str, result: Value, address: Value, offset: u32) -> String {
+        if offset == 0 {
+            return self.operands[address.0].clone();
+        }
+        writeln!(
+            self.out, "  %.{}{} = getelementptr i8, ptr {}, i64 {}",
+            prefix, result.0, self.operands[address.0], offset,
+        ).unwrap();
+        format!("%.{}{}", prefix, result.0)
+    }
+
+    fn typed(&self, value: Value) -> String {
+        format!("{} {}", llvm_type(self.function.type_of(value)), self.operands[value.0])
+    }
+}
+
+/// The type of what a function returns: a struct, if it's two values.
+///
+fn returns_type(types: &[Type]) -> String {
+    match *types {
+        [ty] => llvm_type(ty).to_string(),
+        _ => {
+            let types: Vec<&str> = types.iter().map(|ty| llvm_type(*ty)).collect();
+            format!("{{ {} }}", types.join(", "))
+        },
+    }
+}
+
+fn llvm_type(ty: Type) -> &'static str {
+    match ty {
+        Type::I8 | Type::U8 => "i8",
+        Type::I16 | Type::U16 => "i16",
+        Type::I32 | Type::U32 | Type::Char => "i32",
+        Type::I64 | Type::U64 => "i64",
+        Type::F32 => "float",
+        Type::F64 => "double",
+        Type::Str | Type::Ptr => "ptr",
+    }
+}
+
+/// The instruction and predicate for a comparison of `ty`s. Chars compare as
+/// unsigned integers. Float comparisons are ordered (false if either is NaN),
+/// but for `!=`, which is true.
+///
+fn comparison(op: BinaryOp, ty: Type) -> (&'static str, &'static str) {
+    if !ty.is_float() {
+        return ("icmp", match (ty.is_signed(), op) {
+            (_, BinaryOp::Eq) => "eq",
+            (_, BinaryOp::Ne) => "ne",
+            (true, BinaryOp::Lt) => "slt",
+            (true, BinaryOp::Le) => "sle",
+            (true, BinaryOp::Gt) => "sgt",
+            (true, BinaryOp::Ge) => "sge",
+            (false, BinaryOp::Lt) => "ult",
+            (false, BinaryOp::Le) => "ule",
+            (false, BinaryOp::Gt) => "ugt",
+            (false, BinaryOp::Ge) => "uge",
+            (_, op) => unreachable!("{} is not a comparison", op),
+        });
+    }
+
+    ("fcmp", match op {
+        BinaryOp::Eq => "oeq",
+        BinaryOp::Ne => "une",
+        BinaryOp::Lt => "olt",
+        BinaryOp::Le => "ole",
+        BinaryOp::Gt => "ogt",
+        BinaryOp::Ge => "oge",
+        op => unreachable!("{} is not a comparison", op),
+    })
+}
+
+/// The intrinsic converting a float of type `from` to an integer of type
+/// `to`, saturating.
+///
+fn saturating_conversion(from: Type, to: Type) -> String {
+    let sign = if to.is_signed() { 's' } else { 'u' };
+    format!("@llvm.fpto{}i.sat.{}.{}", sign, llvm_type(to), from)
+}
+
+/// The intrinsic for arithmetic that traps or saturates on overflow. One that
+/// traps gives the wrapped result along with whether it overflowed.
+///
+fn checked_arithmetic(op: BinaryOp, overflow: Overflow, ty: Type) -> String {
+    let sign = if ty.is_signed() { 's' } else { 'u' };
+    match (overflow, op) {
+        (Overflow::Trap, _) => format!("@llvm.{}{}.with.overflow.{}", sign, op, llvm_type(ty)),
+        (_, BinaryOp::Mul) => format!("@llvm.{}mul.fix.sat.{}", sign, llvm_type(ty)),
+        _ => format!("@llvm.{}{}.sat.{}", sign, op, llvm_type(ty)),
+    }
+}
+
+/// Escape `bytes` for a `c"..."` constant.
+///
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &byte in bytes {
+        match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => escaped.push(byte as char),
+            _ => write!(escaped, "\\{:02X}", byte).unwrap(),
+        }
+    }
+    escaped
+}
+
+fn label(block: Block) -> String {
+    format!("b{}", block.0)
+}
+
+/// The label of the code after arithmetic that traps, giving `result`.
+///
+fn checked_label(result: Value) -> String {
+    format!("v{}.ok", result.0)
+}
+
+/// The label `block`'s terminator is under, which is after any arithmetic in
+/// it that traps.
+///
+fn exit_label(function: &ir::Function, block: Block) -> String {
+    let last_trap = function.block(block).instructions.iter().rev().find(|instruction| {
+        matches!(instruction.kind, InstructionKind::Binary(_, Overflow::Trap, _, _))
+    });
+    match last_trap {
+        Some(instruction) => checked_label(instruction.result),
+        None => label(block),
+    }
+}
@@ -0,0 +1,28 @@
+//! Back-ends: each transforms an `ir::Module` into some textual output.
+
+pub mod llvm;
+pub mod wat;
+pub mod x86_64;
+pub mod regalloc;
+
+use ir::{ Target, Type };
+
+/// The cases of a `Switch` on a value of type `ty`, laid out as a jump table,
+/// if they're worth one: there are at least 3, the value fits 32 bits, and at
+/// least half of the table's entries are cases. Returns the smallest case,
+/// and for each value from it up to the largest, the index of its case.
+///
+pub fn jump_table(ty: Type, cases: &[(i128, Target)]) -> Option<(i128, Vec<Option<usize>>)> {
+    let first = cases.iter().map(|&(case, _)| case).min()?;
+    let last = cases.iter().map(|&(case, _)| case).max()?;
+    let span = last - first + 1;
+    if cases.len() < 3 || ty.bits() > 32 || span > 2 * cases.len() as i128 {
+        return None;
+    }
+
+    let mut table = vec![None; span as usize];
+    for (index, &(case, _)) in cases.iter().enumerate() {
+        table[(case - first) as usize] = Some(index);
+    }
+    Some((first, table))
+}
@@ -0,0 +1,450 @@
+//! Register allocation by linear scan over live intervals.
+//!
+//! This is Poletto and Sarkar's algorithm: every value gets a single interval,
+//! from its definition to its last use, widened to cover every block it's
+//! live through. Intervals are visited in order of start; when no register is
+//! free, whichever of the current and active intervals ends last is spilled to
+//! a stack slot.
+//!
+//! Registers are split into caller-saved and callee-saved. Intervals live
+//! across a call prefer callee-saved registers, so nothing need be saved
+//! around the call; others prefer caller-saved registers, so nothing need be
+//! saved in the prologue. Either way, saving is left to the code generator.
+//!
+//! The register set is the back-end's: registers are just indices into its
+//! lists.
+
+use std::collections::HashSet;
+
+use ir::{ self, Block, InstructionKind, Value };
+
+#[derive (Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Location {
+    Register(usize),
+    /// A slot in the stack frame, by index.
+    Stack(usize),
+}
+
+/// The registers available to allocate.
+///
+pub struct Registers<'a> {
+    pub caller_saved: &'a [usize],
+    pub callee_saved: &'a [usize],
+}
+
+/// The position of every block and instruction in a linear order (that of
+/// the blocks' indices).
+///
+/// A block's parameters are defined at its start, and its terminator is at
+/// its end.
+///
+pub struct Positions {
+    pub block_start: Vec<usize>,
+    pub instructions: Vec<Vec<usize>>,
+    pub block_end: Vec<usize>,
+    /// The position of every call, including prints (which call `printf`).
+    pub calls: Vec<usize>,
+}
+
+impl Positions {
+    pub fn new(function: &ir::Function) -> Self {
+        let mut positions = Positions {
+            block_start: Vec::new(),
+            instructions: Vec::new(),
+            block_end: Vec::new(),
+            calls: Vec::new(),
+        };
+
+        let mut position = 0;
+        for data in &function.blocks {
+            positions.block_start.push(position);
+            position += 1;
+
+            let mut instructions = Vec::new();
+            for instruction in &data.instructions {
+                if let InstructionKind::Call(..) | InstructionKind::Print(_) = instruction.kind {
+                    positions.calls.push(position);
+                }
+                instructions.push(position);
+                position += 1;
+            }
+            positions.instructions.push(instructions);
+
+            positions.block_end.push(position);
+            position += 1;
+        }
+
+        positions
+    }
+}
+
+/// An interval a value is live in: from its definition to its last use,
+/// inclusive.
+///
+#[derive (Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Interval {
+    /// Whether a call at `position` happens while this value must be kept.
+    ///
+    /// A value used by the call, but not after, needn't be; nor need the
+    /// call's own result.
+    ///
+    pub fn spans(&self, position: usize) -> bool {
+        self.start < position && position < self.end
+    }
+}
+
+pub struct Allocation {
+    /// The location of each value, by index; `None` for undefined values.
+    pub locations: Vec<Option<Location>>,
+    pub intervals: Vec<Option<Interval>>,
+    pub stack_slots: usize,
+}
+
+/// Give every value its own stack slot. This is slow, but simple to debug.
+///
+pub fn allocate_stack(function: &ir::Function) -> Allocation {
+    let intervals = intervals(function, &Positions::new(function));
+
+    let mut locations = vec![None; function.values.len()];
+    let mut stack_slots = 0;
+    for (index, interval) in intervals.iter().enumerate() {
+        if interval.is_some() {
+            locations[index] = Some(Location::Stack(stack_slots));
+            stack_slots += 1;
+        }
+    }
+
+    Allocation {
+        locations,
+        intervals,
+        stack_slots,
+    }
+}
+
+pub fn allocate_linear_scan(
+    function: &ir::Function,
+    registers: &Registers,
+) -> Allocation {
+    let positions = Positions::new(function);
+    let intervals = intervals(function, &positions);
+
+    let mut order: Vec<Value> = (0..intervals.len())
+        .filter(|index| intervals[*index].is_some())
+        .map(Value)
+        .collect();
+    order.sort_by_key(|value| (intervals[value.0].unwrap().start, value.0));
+
+    let mut locations = vec![None; function.values.len()];
+    let mut stack_slots = 0;
+
+    let mut free: Vec<usize> = registers.caller_saved.iter()
+        .chain(registers.callee_saved.iter())
+        .cloned()
+        .collect();
+    // Values currently in a register, with that register.
+    let mut active: Vec<(Value, usize)> = Vec::new();
+
+    for value in order {
+        let interval = intervals[value.0].unwrap();
+
+        // Expire intervals that ended before this one starts. A value last
+        // used where another is defined can share its register, since
+        // operands are always read before results are written; values
+        // defined together, such as a call's, can't, even if one's unused.
+        active.retain(|&(other, register)| {
+            let other = intervals[other.0].unwrap();
+            if other.end <= interval.start && other.start < interval.start {
+                free.push(register);
+                false
+            }
+            else {
+                true
+            }
+        });
+
+        let across_call = positions.calls.iter().any(|call| interval.spans(*call));
+        let preferred = match across_call {
+            true  => registers.callee_saved,
+            false => registers.caller_saved,
+        };
+
+        let choice = free.iter().position(|register| preferred.contains(register))
+            .or(if free.is_empty() { None } else { Some(0) });
+
+        match choice {
+            Some(index) => {
+                let register = free.remove(index);
+                locations[value.0] = Some(Location::Register(register));
+                active.push((value, register));
+            },
+            None => {
+                // Spill whichever interval ends last.
+                let (position, &(furthest, register)) = active.iter().enumerate()
+                    .max_by_key(|&(_, &(other, _))| intervals[other.0].unwrap().end)
+                    .unwrap();
+
+                if intervals[furthest.0].unwrap().end > interval.end {
+                    locations[furthest.0] = Some(Location::Stack(stack_slots));
+                    locations[value.0] = Some(Location::Register(register));
+                    active[position] = (value, register);
+                }
+                else {
+                    locations[value.0] = Some(Location::Stack(stack_slots));
+                }
+                stack_slots += 1;
+            },
+        }
+    }
+
+    Allocation {
+        locations,
+        intervals,
+        stack_slots,
+    }
+}
+
+/// Compute the live interval of every defined value.
+///
+fn intervals(
+    function: &ir::Function,
+    positions: &Positions,
+) -> Vec<Option<Interval>> {
+    let (live_in, live_out) = liveness(function);
+
+    let mut intervals: Vec<Option<Interval>> = vec![None; function.values.len()];
+    let mut extend = |value: Value, position: usize| {
+        let interval = &mut intervals[value.0];
+        *interval = Some(match *interval {
+            None => Interval { start: position, end: position },
+            Some(Interval { start, end }) => Interval {
+                start: start.min(position),
+                end: end.max(position),
+            },
+        });
+    };
+
+    for (index, data) in function.blocks.iter().enumerate() {
+        for parameter in &data.parameters {
+            extend(*parameter, positions.block_start[index]);
+        }
+        for (instruction, position) in data.instructions.iter()
+            .zip(&positions.instructions[index]) {
+            for result in instruction.results() {
+                extend(result, *position);
+            }
+            for operand in instruction.operands() {
+                extend(operand, *position);
+            }
+        }
+        for operand in data.terminator.as_ref().unwrap().operands() {
+            extend(operand, positions.block_end[index]);
+        }
+
+        for value in &live_in[index] {
+            extend(*value, positions.block_start[index]);
+        }
+        for value in &live_out[index] {
+            extend(*value, positions.block_end[index]);
+        }
+    }
+
+    intervals
+}
+
+/// The values live into and out of each block, by index.
+///
+fn liveness(function: &ir::Function) -> (Vec<HashSet<Value>>, Vec<HashSet<Value>>) {
+    let count = function.blocks.len();
+
+    // What each block uses before defining, and defines.
+    let mut uses = vec![HashSet::new(); count];
+    let mut defs = vec![HashSet::new(); count];
+    for (index, data) in function.blocks.iter().enumerate() {
+        defs[index].extend(data.parameters.iter().cloned());
+        for instruction in &data.instructions {
+            for operand in instruction.operands() {
+                if !defs[index].contains(&operand) {
+                    uses[index].insert(operand);
+                }
+            }
+            defs[index].extend(instruction.results());
+        }
+        for operand in data.terminator.as_ref().unwrap().operands() {
+            if !defs[index].contains(&operand) {
+                uses[index].insert(operand);
+            }
+        }
+    }
+
+    let successors = function.successors();
+    let mut live_in: Vec<HashSet<Value>> = vec![HashSet::new(); count];
+    let mut live_out: Vec<HashSet<Value>> = vec![HashSet::new(); count];
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for index in (0..count).rev() {
+            let mut out = HashSet::new();
+            for &Block(successor) in &successors[index] {
+                out.extend(live_in[successor].iter().cloned());
+            }
+
+            let mut new_in: HashSet<Value> = out.difference(&defs[index]).cloned().collect();
+            new_in.extend(uses[index].iter().cloned());
+
+            if new_in != live_in[index] || out != live_out[index] {
+                live_in[index] = new_in;
+                live_out[index] = out;
+                changed = true;
+            }
+        }
+    }
+
+    (live_in, live_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ allocate_linear_scan, allocate_stack, Allocation, Location, Registers };
+    use ir::{ BinaryOp, Function, InstructionKind, Overflow, Terminator, Type, Value };
+
+    const CALLER_SAVED: &[usize] = &[0, 1];
+    const CALLEE_SAVED: &[usize] = &[2, 3];
+
+    fn add(lhs: Value, rhs: Value) -> InstructionKind {
+        InstructionKind::Binary(BinaryOp::Add, Overflow::Wrap, lhs, rhs)
+    }
+
+    /// `f() -> i32`, which defines `count` constants and then adds them up in
+    /// the reverse order, so the first defined is live longest.
+    ///
+    fn pressure(count: usize) -> (Function, Vec<Value>) {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let constants: Vec<Value> = (0..count)
+            .map(|index| function.push(entry, Type::I32, InstructionKind::Const(index as i128)))
+            .collect();
+        let mut sum = constants[count - 1];
+        for constant in constants[..count - 1].iter().rev() {
+            sum = function.push(entry, Type::I32, add(sum, *constant));
+        }
+        function.terminate(entry, Terminator::Return(vec![sum]));
+        (function, constants)
+    }
+
+    /// Check no two values whose intervals overlap share a location, other
+    /// than a value last used where the other is defined.
+    ///
+    fn check(allocation: &Allocation) {
+        let allocated: Vec<(usize, Location)> = allocation.locations.iter().enumerate()
+            .filter_map(|(index, location)| location.map(|location| (index, location)))
+            .collect();
+        for &(a, location) in &allocated {
+            for &(b, other) in &allocated {
+                let (first, second) = (
+                    allocation.intervals[a].unwrap(), allocation.intervals[b].unwrap(),
+                );
+                let overlap = first.start < second.start && second.start < first.end;
+                assert!(
+                    !(overlap && location == other),
+                    "v{} and v{} are both in {:?}", a, b, location,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spills_whichever_interval_ends_last() {
+        let (function, constants) = pressure(4);
+        let registers = Registers { caller_saved: CALLER_SAVED, callee_saved: &[] };
+        let allocation = allocate_linear_scan(&function, &registers);
+        check(&allocation);
+
+        // The first two constants are spilled, as the others come and end
+        // sooner; each has its own slot.
+        let locations: Vec<Location> = constants.iter()
+            .map(|constant| allocation.locations[constant.0].unwrap())
+            .collect();
+        assert_eq!(&locations[..2], &[Location::Stack(0), Location::Stack(1)]);
+        for location in &locations[2..] {
+            assert!(matches!(location, Location::Register(_)), "{:?}", location);
+        }
+        assert_eq!(allocation.stack_slots, 2);
+    }
+
+    #[test]
+    fn needs_no_stack_without_register_pressure() {
+        let (function, _) = pressure(4);
+        let registers = Registers { caller_saved: CALLER_SAVED, callee_saved: CALLEE_SAVED };
+        let allocation = allocate_linear_scan(&function, &registers);
+        check(&allocation);
+        assert_eq!(allocation.stack_slots, 0);
+    }
+
+    /// `f(x: i32) -> i32`, which prints `x` between defining a constant and
+    /// adding the two, so both are live across the print's call.
+    ///
+    fn across_call() -> (Function, Value, Value, Value) {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let x = function.add_parameter(entry, Type::I32);
+        let one = function.push(entry, Type::I32, InstructionKind::Const(1));
+        function.push(entry, Type::I32, InstructionKind::Print(x));
+        let sum = function.push(entry, Type::I32, add(x, one));
+        function.terminate(entry, Terminator::Return(vec![sum]));
+        (function, x, one, sum)
+    }
+
+    #[test]
+    fn prefers_callee_saved_registers_across_calls() {
+        let (function, x, one, sum) = across_call();
+        let registers = Registers { caller_saved: CALLER_SAVED, callee_saved: CALLEE_SAVED };
+        let allocation = allocate_linear_scan(&function, &registers);
+        check(&allocation);
+
+        for &(value, registers) in &[(x, CALLEE_SAVED), (one, CALLEE_SAVED), (sum, CALLER_SAVED)] {
+            match allocation.locations[value.0] {
+                Some(Location::Register(register)) => assert!(
+                    registers.contains(&register), "{} is in register {}", value, register,
+                ),
+                location => panic!("{} is in {:?}", value, location),
+            }
+        }
+    }
+
+    /// Once the callee-saved registers run out, a caller-saved one is better
+    /// than the stack, and the code generator saves it around the call.
+    ///
+    #[test]
+    fn falls_back_to_caller_saved_registers_across_calls() {
+        let (function, x, one, _) = across_call();
+        let registers = Registers { caller_saved: CALLER_SAVED, callee_saved: &[2] };
+        let allocation = allocate_linear_scan(&function, &registers);
+        check(&allocation);
+        assert_eq!(allocation.locations[x.0], Some(Location::Register(2)));
+        assert!(matches!(
+            allocation.locations[one.0], Some(Location::Register(register))
+                if CALLER_SAVED.contains(&register)
+        ));
+        assert_eq!(allocation.stack_slots, 0);
+    }
+
+    #[test]
+    fn the_stack_allocator_gives_every_value_its_own_slot() {
+        let (mut function, _) = pressure(3);
+        // A value left undefined, as by a pass removing its instruction.
+        let undefined = function.new_value(Type::I32);
+        let allocation = allocate_stack(&function);
+
+        assert_eq!(allocation.locations[undefined.0], None);
+        let slots: Vec<Option<Location>> = (0..5).map(Location::Stack).map(Some).collect();
+        assert_eq!(&allocation.locations[..5], &slots[..]);
+        assert_eq!(allocation.stack_slots, 5);
+    }
+}
@@ -0,0 +1,903 @@
+//! WebAssembly text format (`.wat`) back-end.
+//!
+//! Output is a single module with every adze function exported under its own
+//! name. Every IR value becomes a wasm local.
+//!
+//! Wasm only has structured control flow, so a function of more than one
+//! block is laid out as a `loop` around a `br_table` on a `$block` local:
+//! branching sets `$block` and jumps back to the dispatch. This handles any
+//! control-flow graph, at the cost of an indirect jump per branch.
+//!
+//! Integers of 32 bits or fewer are `i32`s, and wider ones `i64`s, whether
+//! signed or not. An 8 or 16-bit integer is always kept sign-extended if
+//! it's signed and zero-extended if not, so arithmetic on one is followed by
+//! extending its low bits again.
+//!
+//! Arithmetic that traps on overflow executes `unreachable`. On integers of
+//! 32 bits or fewer, arithmetic that traps or saturates is done in `i64`, in
+//! a `$wide` local, and the result checked it's in range; on 64-bit ones, the
+//! operands and wrapped result tell whether it overflowed.
+//!
+//! A char is an `i32` of its scalar value. An integer converts to one in
+//! `$wide`, giving U+FFFD if it isn't a scalar value.
+//!
+//! Strings are NUL-terminated data in an exported `memory`, and a `str` is
+//! the address of one. Printing calls one of the host's `env.print_i32`
+//! (for any integer of 32 bits or fewer but `u32`), `env.print_u32`,
+//! `env.print_i64`, `env.print_u64`, `env.print_f64` (for either float type),
+//! `env.print_char` or `env.print_str`, which takes such an address.
+//!
+//! The memory of `Slot`s is on a stack at the top of `memory`, growing down
+//! from `$sp`, a global. A function with any takes its frame off `$sp` on
+//! entry, into a `$frame` local, and gives it back before returning; if the
+//! frame would reach down into the strings, below `$data_end`, it executes
+//! `unreachable` instead. A `ptr` is an address, like a `str`. Copies are
+//! `memory.copy`. An element's index is wrapped to an `i32` to find its
+//! address, since an index in bounds fits.
+//!
+//! A `Switch` whose cases are dense is a `br_table` out of nested `block`s,
+//! one per case, to the branch to that case's target; otherwise, it compares
+//! the value with each case in turn. A `Trap` is `unreachable`.
+//!
+//! A function returning two values has two results, as multi-value allows.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ir::{ self, BinaryOp, Block, InstructionKind, Overflow, Target, Terminator, Type, Value };
+use gen::jump_table;
+
+pub fn emit(module: &ir::Module) -> Result<String, String> {
+    let mut data = Data::default();
+    let mut functions = String::new();
+    let slots = module.functions.iter()
+        .any(|function| function.slots().1 > 0);
+    for function in &module.functions {
+        functions.push_str(&Function::new(function, &mut data).emit());
+    }
+
+    let mut out = String::from("(module\n");
+
+    let prints = module.functions.iter()
+        .flat_map(|function| &function.blocks)
+        .flat_map(|data| &data.instructions)
+        .any(|instruction| matches!(instruction.kind, InstructionKind::Print(_)));
+    if prints {
+        for (name, ty) in PRINTS {
+            writeln!(
+                out, "  (import \"env\" \"{}\" (func ${} (param {})))", name, name, ty,
+            ).unwrap();
+        }
+    }
+    if !data.segments.is_empty() || slots {
+        out.push_str("  (memory (export \"memory\") 1)\n");
+    }
+    if slots {
+        out.push_str("  (global $sp (mut i32) (i32.const 65536))\n");
+        writeln!(out, "  (global $data_end i32 (i32.const {}))", data.next).unwrap();
+    }
+
+    out.push_str(&functions);
+    out.push_str(&data.segments);
+
+    out.push_str(")\n");
+    Ok(out)
+}
+
+/// The host's printing functions, and the type each takes.
+///
+const PRINTS: [(&str, &str); 7] = [
+    ("print_i32", "i32"),
+    ("print_u32", "i32"),
+    ("print_i64", "i64"),
+    ("print_u64", "i64"),
+    ("print_f64", "f64"),
+    ("print_char", "i32"),
+    ("print_str", "i32"),
+];
+
+/// The strings laid out in memory so far.
+///
+#[derive (Default)]
+struct Data {
+    /// Their `data` segments.
+    segments: String,
+    /// The address of the next.
+    next: u32,
+}
+
+impl Data {
+    /// Add `string`; return its address.
+    ///
+    fn add(&mut self, string: &str) -> u32 {
+        let address = self.next;
+        let mut escaped = String::new();
+        for &byte in string.as_bytes().iter().chain(&[0]) {
+            match byte {
+                b' '..=b'~' if byte != b'"' && byte != b'\\' => escaped.push(byte as char),
+                _ => write!(escaped, "\\{:02x}", byte).unwrap(),
+            }
+        }
+        writeln!(self.segments, "  (data (i32.const {}) \"{}\")", address, escaped).unwrap();
+
+        self.next += string.len() as u32 + 1;
+        address
+    }
+}
+
+/// The state of emitting a single function.
+///
+struct Function<'a> {
+    function: &'a ir::Function,
+    data: &'a mut Data,
+    /// The offset of each `Slot`'s memory in the frame.
+    memory: HashMap<Value, u32>,
+    /// The size of the frame; 0 if there's none.
+    frame: u32,
+    body: String,
+    /// The current indentation of `body`, in levels.
+    depth: usize,
+}
+
+impl<'a> Function<'a> {
+    fn new(function: &'a ir::Function, data: &'a mut Data) -> Self {
+        let (memory, frame) = function.slots();
+        Function {
+            function,
+            data,
+            memory,
+            frame,
+            body: String::new(),
+            depth: 2,
+        }
+    }
+
+    fn emit(mut self) -> String {
+        let function = self.function;
+        let name = &function.name;
+
+        let mut out = format!("  (func ${} (export \"{}\")", name, name);
+        for parameter in function.parameters() {
+            write!(out, " (param ${} {})", parameter, wasm_type(function.type_of(*parameter)))
+                .unwrap();
+        }
+        let returns: Vec<&str> = function.returns.iter().map(|ty| wasm_type(*ty)).collect();
+        writeln!(out, " (result {})", returns.join(" ")).unwrap();
+
+        // Everything else is a local.
+        let parameters = function.parameters();
+        for (index, ty) in function.values.iter().enumerate() {
+            if !parameters.contains(&Value(index)) {
+                writeln!(out, "    (local ${} {})", Value(index), wasm_type(*ty)).unwrap();
+            }
+        }
+
+        let wide = function.blocks.iter()
+            .flat_map(|data| &data.instructions)
+            .any(|instruction| match instruction.kind {
+                InstructionKind::Binary(_, overflow, _, _) => overflow != Overflow::Wrap,
+                InstructionKind::Convert(_) => function.type_of(instruction.result) == Type::Char,
+                _ => false,
+            });
+        if wide {
+            out.push_str("    (local $wide i64)\n");
+        }
+
+        if self.frame > 0 {
+            out.push_str("    (local $frame i32)\n");
+            self.instruction("global.get $sp");
+            self.instruction(&format!("i32.const {}", self.frame));
+            self.instruction("i32.sub");
+            self.instruction("local.tee $frame");
+            // Signed, so a frame taken off past 0 is below the data too.
+            self.instruction("global.get $data_end");
+            self.instruction("i32.lt_s");
+            self.instruction("if");
+            self.instruction("unreachable");
+            self.instruction("end");
+            self.instruction("local.get $frame");
+            self.instruction("global.set $sp");
+        }
+
+        if function.blocks.len() == 1 {
+            self.block(Block(0));
+        }
+        else {
+            out.push_str("    (local $block i32)\n");
+            self.dispatch();
+        }
+
+        out.push_str(&self.body);
+        out.push_str("  )\n");
+        out
+    }
+
+    fn dispatch(&mut self) {
+        let count = self.function.blocks.len();
+
+        self.instruction("loop $dispatch");
+        self.depth += 1;
+
+        // Block N is ended by the N+1th `end`, so the innermost `block` is
+        // the first IR block.
+        for index in (0..count).rev() {
+            self.instruction(&format!("block $b{}", index));
+            self.depth += 1;
+        }
+
+        let labels: Vec<String> = (0..count).map(|index| format!("$b{}", index)).collect();
+        self.instruction("local.get $block");
+        self.instruction(&format!("br_table {}", labels.join(" ")));
+
+        for index in 0..count {
+            self.depth -= 1;
+            self.instruction("end");
+            self.block(Block(index));
+        }
+
+        self.depth -= 1;
+        self.instruction("end");
+
+        // Every block ends by returning or branching back to the dispatch.
+        self.instruction("unreachable");
+    }
+
+    fn block(&mut self, block: Block) {
+        let function = self.function;
+        let data = function.block(block);
+
+        for instruction in &data.instructions {
+            let ty = wasm_type(function.type_of(instruction.result));
+
+            match instruction.kind {
+                InstructionKind::Const(value) => {
+                    self.instruction(&format!("{}.const {}", ty, value));
+                },
+                // Both are written exactly.
+                InstructionKind::Float(value) => match function.type_of(instruction.result) {
+                    Type::F32 => self.instruction(&format!("f32.const {:?}", value as f32)),
+                    _ => self.instruction(&format!("f64.const {:?}", value)),
+                },
+                InstructionKind::Str(ref string) => {
+                    let address = self.data.add(string);
+                    self.instruction(&format!("i32.const {}", address));
+                },
+                // This sets the result itself.
+                InstructionKind::Binary(op, overflow, lhs, rhs) if overflow != Overflow::Wrap => {
+                    self.checked(op, overflow, lhs, rhs, instruction.result);
+                    continue;
+                },
+                InstructionKind::Binary(op, _, lhs, rhs) => {
+                    let operand_type = function.type_of(lhs);
+                    let signed = match operand_type {
+                        _ if operand_type.is_float() => "",
+                        _ if operand_type.is_signed() => "_s",
+                        _ => "_u",
+                    };
+
+                    self.get(lhs);
+                    self.get(rhs);
+                    self.instruction(&format!("{}.{}", wasm_type(operand_type), match op {
+                        BinaryOp::Add => "add".to_string(),
+                        BinaryOp::Sub => "sub".to_string(),
+                        BinaryOp::Mul => "mul".to_string(),
+                        BinaryOp::Eq => "eq".to_string(),
+                        BinaryOp::Ne => "ne".to_string(),
+                        BinaryOp::Lt => format!("lt{}", signed),
+                        BinaryOp::Le => format!("le{}", signed),
+                        BinaryOp::Gt => format!("gt{}", signed),
+                        BinaryOp::Ge => format!("ge{}", signed),
+                    }));
+                    if !op.is_comparison() {
+                        self.extend(operand_type);
+                    }
+                },
+                InstructionKind::Convert(value) => {
+                    self.get(value);
+                    self.convert(function.type_of(value), instruction.result);
+                },
+                InstructionKind::Call(ref name, ref arguments, ref more) => {
+                    for argument in arguments {
+                        self.get(*argument);
+                    }
+                    self.instruction(&format!("call ${}", name));
+                    // The last value returned is on top.
+                    for value in more.iter().rev() {
+                        self.instruction(&format!("local.set ${}", value));
+                    }
+                },
+                InstructionKind::Print(value) => {
+                    self.get(value);
+                    match function.type_of(value) {
+                        Type::U32 => self.instruction("call $print_u32"),
+                        Type::I64 => self.instruction("call $print_i64"),
+                        Type::U64 => self.instruction("call $print_u64"),
+                        Type::F32 => {
+                            self.instruction("f64.promote_f32");
+                            self.instruction("call $print_f64");
+                        },
+                        Type::F64 => self.instruction("call $print_f64"),
+                        Type::Char => self.instruction("call $print_char"),
+                        Type::Str => self.instruction("call $print_str"),
+                        _ => self.instruction("call $print_i32"),
+                    }
+                    self.instruction("i32.const 0");
+                },
+                InstructionKind::Slot(..) => {
+                    let offset = self.memory[&instruction.result];
+                    self.instruction("local.get $frame");
+                    self.instruction(&format!("i32.const {}", offset));
+                    self.instruction("i32.add");
+                },
+                InstructionKind::Load(address, offset) => {
+                    let ty = function.type_of(instruction.result);
+                    let width = match (ty.bits(), ty.is_signed()) {
+                        (8, true) => "8_s",
+                        (8, false) => "8_u",
+                        (16, true) => "16_s",
+                        (16, false) => "16_u",
+                        _ => "",
+                    };
+                    self.get(address);
+                    self.instruction(&format!(
+                        "{}.load{} offset={}", wasm_type(ty), width, offset,
+                    ));
+                },
+                InstructionKind::Store(address, offset, value) => {
+                    let ty = function.type_of(value);
+                    let width = match ty.bits() {
+                        8 => "8",
+                        16 => "16",
+                        _ => "",
+                    };
+                    self.get(address);
+                    self.get(value);
+                    self.instruction(&format!(
+                        "{}.store{} offset={}", wasm_type(ty), width, offset,
+                    ));
+                    self.instruction("i32.const 0");
+                },
+                InstructionKind::Copy(to, to_offset, from, from_offset, size) => {
+                    self.address(to, to_offset);
+                    self.address(from, from_offset);
+                    self.instruction(&format!("i32.const {}", size));
+                    self.instruction("memory.copy");
+                    self.instruction("i32.const 0");
+                },
+                InstructionKind::Element(address, index, size) => {
+                    self.get(address);
+                    self.get(index);
+                    self.instruction("i32.wrap_i64");
+                    self.instruction(&format!("i32.const {}", size));
+                    self.instruction("i32.mul");
+                    self.instruction("i32.add");
+                },
+            }
+            self.instruction(&format!("local.set ${}", instruction.result));
+        }
+
+        match *data.terminator.as_ref().unwrap() {
+            Terminator::Return(ref values) => {
+                for value in values {
+                    self.get(*value);
+                }
+                if self.frame > 0 {
+                    self.instruction("local.get $frame");
+                    self.instruction(&format!("i32.const {}", self.frame));
+                    self.instruction("i32.add");
+                    self.instruction("global.set $sp");
+                }
+                self.instruction("return");
+            },
+            Terminator::Branch(ref target) => self.branch(target),
+            Terminator::CondBranch(condition, ref then_target, ref else_target) => {
+                self.get(condition);
+                self.instruction("if");
+                self.depth += 1;
+                self.branch(then_target);
+                self.depth -= 1;
+                self.instruction("else");
+                self.depth += 1;
+                self.branch(else_target);
+                self.depth -= 1;
+                self.instruction("end");
+            },
+            Terminator::Switch(value, ref cases, ref default) => {
+                self.switch(value, cases, default)
+            },
+            Terminator::Trap => self.instruction("unreachable"),
+        }
+    }
+
+    fn switch(&mut self, value: Value, cases: &[(i128, Target)], default: &Target) {
+        let ty = self.function.type_of(value);
+
+        let (first, table) = match jump_table(ty, cases) {
+            Some(table) => table,
+            None => {
+                for (case, target) in cases {
+                    self.get(value);
+                    self.instruction(&format!("{}.const {}", wasm_type(ty), case));
+                    self.instruction(&format!("{}.eq", wasm_type(ty)));
+                    self.instruction("if");
+                    self.depth += 1;
+                    self.branch(target);
+                    self.depth -= 1;
+                    self.instruction("end");
+                }
+                self.branch(default);
+                return;
+            },
+        };
+
+        // Case N is after the N+1th `end`, and the default after the last;
+        // `br_table` takes anything out of range to the default.
+        for _ in 0..=cases.len() {
+            self.instruction("block");
+            self.depth += 1;
+        }
+        let labels: Vec<String> = table.iter()
+            .map(|index| index.unwrap_or(cases.len()).to_string())
+            .collect();
+        self.get(value);
+        if first != 0 {
+            self.instruction(&format!("i32.const {}", first));
+            self.instruction("i32.sub");
+        }
+        self.instruction(&format!("br_table {} {}", labels.join(" "), cases.len()));
+
+        for (_, target) in cases {
+            self.depth -= 1;
+            self.instruction("end");
+            self.branch(target);
+        }
+        self.depth -= 1;
+        self.instruction("end");
+        self.branch(default);
+    }
+
+    /// Set `result` to the result of integer arithmetic that traps or
+    /// saturates on overflow: first to the wrapped result, then, if it
+    /// overflowed, to the bound it saturates to.
+    ///
+    fn checked(
+        &mut self,
+        op: BinaryOp,
+        overflow: Overflow,
+        lhs: Value,
+        rhs: Value,
+        result: Value,
+    ) {
+        let ty = self.function.type_of(result);
+        let wasm = wasm_type(ty);
+        let (min, max) = ty.range();
+        let instruction = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            _ => unreachable!("{} is a comparison", op),
+        };
+
+        // Push whether it overflowed.
+        if ty.bits() < 64 {
+            let sign = if ty.is_signed() { "_s" } else { "_u" };
+            self.get(lhs);
+            self.instruction(&format!("i64.extend_i32{}", sign));
+            self.get(rhs);
+            self.instruction(&format!("i64.extend_i32{}", sign));
+            self.instruction(&format!("i64.{}", instruction));
+            self.instruction("local.tee $wide");
+            self.instruction("i32.wrap_i64");
+            self.instruction(&format!("local.set ${}", result));
+
+            // Only the product of two `u32`s can be out of range for `i64`.
+            let compare = match (op, ty.is_signed()) {
+                (BinaryOp::Mul, false) => "_u",
+                _ => "_s",
+            };
+            self.instruction("local.get $wide");
+            self.instruction(&format!("i64.const {}", min));
+            self.instruction(&format!("i64.lt{}", compare));
+            self.instruction("local.get $wide");
+            self.instruction(&format!("i64.const {}", max));
+            self.instruction(&format!("i64.gt{}", compare));
+            self.instruction("i32.or");
+        }
+        else {
+            self.get(lhs);
+            self.get(rhs);
+            self.instruction(&format!("i64.{}", instruction));
+            self.instruction(&format!("local.set ${}", result));
+
+            match (op, ty.is_signed()) {
+                // The result's sign differs from both operands'.
+                (BinaryOp::Add, true) => {
+                    self.get(lhs);
+                    self.get(result);
+                    self.instruction("i64.xor");
+                    self.get(rhs);
+                    self.get(result);
+                    self.instruction("i64.xor");
+                    self.instruction("i64.and");
+                    self.instruction("i64.const 0");
+                    self.instruction("i64.lt_s");
+                },
+                // The operands' signs differ, and the result's from the
+                // left-hand one's.
+                (BinaryOp::Sub, true) => {
+                    self.get(lhs);
+                    self.get(rhs);
+                    self.instruction("i64.xor");
+                    self.get(lhs);
+                    self.get(result);
+                    self.instruction("i64.xor");
+                    self.instruction("i64.and");
+                    self.instruction("i64.const 0");
+                    self.instruction("i64.lt_s");
+                },
+                (BinaryOp::Add, false) => {
+                    self.get(result);
+                    self.get(lhs);
+                    self.instruction("i64.lt_u");
+                },
+                (BinaryOp::Sub, false) => {
+                    self.get(lhs);
+                    self.get(rhs);
+                    self.instruction("i64.lt_u");
+                },
+                // Dividing the result by the left-hand operand doesn't give
+                // the right-hand one back; `-1` is taken apart, since
+                // dividing the smallest `i64` by it traps.
+                _ => {
+                    let sign = if ty.is_signed() { "_s" } else { "_u" };
+                    self.get(lhs);
+                    self.instruction("i64.eqz");
+                    self.instruction("if (result i32)");
+                    self.depth += 1;
+                    self.instruction("i32.const 0");
+                    self.depth -= 1;
+                    self.instruction("else");
+                    self.depth += 1;
+                    if ty.is_signed() {
+                        self.get(lhs);
+                        self.instruction("i64.const -1");
+                        self.instruction("i64.eq");
+                        self.instruction("if (result i32)");
+                        self.depth += 1;
+                        self.get(rhs);
+                        self.instruction(&format!("i64.const {}", min));
+                        self.instruction("i64.eq");
+                        self.depth -= 1;
+                        self.instruction("else");
+                        self.depth += 1;
+                    }
+                    self.get(result);
+                    self.get(lhs);
+                    self.instruction(&format!("i64.div{}", sign));
+                    self.get(rhs);
+                    self.instruction("i64.ne");
+                    if ty.is_signed() {
+                        self.depth -= 1;
+                        self.instruction("end");
+                    }
+                    self.depth -= 1;
+                    self.instruction("end");
+                },
+            }
+        }
+
+        if overflow == Overflow::Trap {
+            self.instruction("if");
+            self.depth += 1;
+            self.instruction("unreachable");
+            self.depth -= 1;
+            self.instruction("end");
+            return;
+        }
+
+        // The bound it saturates to.
+        let constant = |value: i128| match ty.bits() {
+            64 => format!("i64.const {}", value as i64),
+            _ => format!("i32.const {}", value as i32),
+        };
+        self.instruction("if");
+        self.depth += 1;
+        match (op, ty.is_signed()) {
+            (BinaryOp::Sub, false) => self.instruction(&constant(min)),
+            (_, false) => self.instruction(&constant(max)),
+            // Which way it overflowed follows from the operands' signs: the
+            // right-hand one's for `+` and `-`, and whether they differ for
+            // `*`.
+            (_, true) => {
+                let (positive, negative) = match op {
+                    BinaryOp::Sub => (min, max),
+                    _ => (max, min),
+                };
+                self.instruction(&constant(negative));
+                self.instruction(&constant(positive));
+                self.get(rhs);
+                if op == BinaryOp::Mul {
+                    self.get(lhs);
+                    self.instruction(&format!("{}.xor", wasm));
+                }
+                self.instruction(&format!("{}.const 0", wasm));
+                self.instruction(&format!("{}.lt_s", wasm));
+                self.instruction("select");
+            },
+        }
+        self.instruction(&format!("local.set ${}", result));
+        self.depth -= 1;
+        self.instruction("end");
+    }
+
+    /// Convert the value on the stack, of type `from`, to the type of
+    /// `result`.
+    ///
+    fn convert(&mut self, from: Type, result: Value) {
+        let to = self.function.type_of(result);
+        let sign = |ty: Type| if ty.is_signed() { "_s" } else { "_u" };
+
+        match (from.is_float(), to.is_float()) {
+            (false, false) if to == Type::Char => {
+                if from.bits() < 64 {
+                    self.instruction(&format!("i64.extend_i32{}", sign(from)));
+                }
+                self.instruction("local.set $wide");
+                self.instruction("i32.const 0xFFFD");
+                self.instruction("local.get $wide");
+                self.instruction("i32.wrap_i64");
+                // A surrogate, or anything too big, including negative
+                // numbers as unsigned.
+                self.instruction("local.get $wide");
+                self.instruction("i64.const 0xD800");
+                self.instruction("i64.sub");
+                self.instruction("i64.const 0x800");
+                self.instruction("i64.lt_u");
+                self.instruction("local.get $wide");
+                self.instruction("i64.const 0x10FFFF");
+                self.instruction("i64.gt_u");
+                self.instruction("i32.or");
+                self.instruction("select");
+            },
+            (false, false) => {
+                match (from.bits(), to.bits()) {
+                    (64, 64) => (),
+                    (64, _) => self.instruction("i32.wrap_i64"),
+                    (_, 64) => self.instruction(&format!("i64.extend_i32{}", sign(from))),
+                    _ => (),
+                }
+                self.extend(to);
+            },
+            // An 8 or 16-bit integer's range is within `i32`'s, so it's
+            // converted to that, then clamped.
+            (true, false) if to.bits() < 32 => {
+                let (min, max) = to.range();
+                self.instruction(&format!("i32.trunc_sat_{}_s", wasm_type(from)));
+                self.instruction(&format!("local.set ${}", result));
+                self.bound(result, max, "lt_s");
+                self.instruction(&format!("local.set ${}", result));
+                self.bound(result, min, "gt_s");
+            },
+            // These saturate, as in the IR.
+            (true, false) => self.instruction(&format!(
+                "{}.trunc_sat_{}{}", wasm_type(to), wasm_type(from), sign(to),
+            )),
+            (false, true) => self.instruction(&format!(
+                "{}.convert_{}{}", wasm_type(to), wasm_type(from), sign(from),
+            )),
+            (true, true) if from == Type::F32 => self.instruction("f64.promote_f32"),
+            (true, true) => self.instruction("f32.demote_f64"),
+        }
+    }
+
+    /// Push `value` if `value <comparison> bound`, otherwise `bound`.
+    ///
+    fn bound(&mut self, value: Value, bound: i128, comparison: &str) {
+        self.get(value);
+        self.instruction(&format!("i32.const {}", bound));
+        self.get(value);
+        self.instruction(&format!("i32.const {}", bound));
+        self.instruction(&format!("i32.{}", comparison));
+        self.instruction("select");
+    }
+
+    /// Re-extend the low bits of the `i32` on the stack, if it's of an 8 or
+    /// 16-bit integer type `ty`.
+    ///
+    fn extend(&mut self, ty: Type) {
+        match ty {
+            Type::I8 => self.instruction("i32.extend8_s"),
+            Type::I16 => self.instruction("i32.extend16_s"),
+            Type::U8 | Type::U16 => {
+                self.instruction(&format!("i32.const {}", ty.range().1));
+                self.instruction("i32.and");
+            },
+            _ => (),
+        }
+    }
+
+    fn branch(&mut self, target: &ir::Target) {
+        let parameters = &self.function.block(target.block).parameters;
+
+        // Push every argument before setting any parameter, since a parameter
+        // may also be an argument.
+        for argument in &target.arguments {
+            self.get(*argument);
+        }
+        for parameter in parameters.iter().rev() {
+            self.instruction(&format!("local.set ${}", parameter));
+        }
+
+        self.instruction(&format!("i32.const {}", target.block.0));
+        self.instruction("local.set $block");
+        self.instruction("br $dispatch");
+    }
+
+    /// Push `value`, an address, plus `offset`.
+    ///
+    fn address(&mut self, value: Value, offset: u32) {
+        self.get(value);
+        if offset != 0 {
+            self.instruction(&format!("i32.const {}", offset));
+            self.instruction("i32.add");
+        }
+    }
+
+    fn get(&mut self, value: Value) {
+        self.instruction(&format!("local.get ${}", value));
+    }
+
+    fn instruction(&mut self, instruction: &str) {
+        for _ in 0..self.depth {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(instruction);
+        self.body.push('\n');
+    }
+}
+
+fn wasm_type(ty: Type) -> &'static str {
+    match ty {
+        Type::I8 | Type::I16 | Type::I32 |
+        Type::U8 | Type::U16 | Type::U32 |
+        Type::Char | Type::Str | Type::Ptr => "i32",
+        Type::I64 | Type::U64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::emit;
+    use ir::{ self, Overflow };
+    use lex::Lexer;
+    use parse::Parser;
+
+    /// The module `source` compiles to, unoptimised.
+    ///
+    fn wat(source: &str, overflow: Overflow) -> String {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let tree = Parser::new().parse(tokens).unwrap();
+        let module = ir::lower::lower(&tree, overflow).unwrap();
+        ir::verify::verify(&module).unwrap();
+        emit(&module).unwrap()
+    }
+
+    /// The lines of the function `name` in `module`, trimmed, from its
+    /// `func` to its closing parenthesis.
+    ///
+    fn function<'a>(module: &'a str, name: &str) -> Vec<&'a str> {
+        let start = format!("  (func ${} ", name);
+        module.lines()
+            .skip_while(|line| !line.starts_with(&start))
+            .take_while(|line| *line != "  )")
+            .map(str::trim)
+            .collect()
+    }
+
+    #[test]
+    fn module_exports_each_function() {
+        let module = wat(
+            "add(a: i32, b: i32) -> i32 { return a + b; }\n\
+             main() -> i32 { return add(1, 2); }\n",
+            Overflow::Wrap,
+        );
+
+        assert!(module.starts_with("(module\n"));
+        assert!(module.ends_with(")\n"));
+        assert_eq!(
+            function(&module, "add")[0],
+            "(func $add (export \"add\") (param $v0 i32) (param $v1 i32) (result i32)",
+        );
+        assert_eq!(function(&module, "main")[0], "(func $main (export \"main\") (result i32)");
+        assert!(function(&module, "main").contains(&"call $add"));
+
+        // Nothing needs memory.
+        assert!(!module.contains("(memory"));
+        assert!(!module.contains("$sp"));
+    }
+
+    #[test]
+    fn wide_types_are_i64() {
+        let module = wat("id(x: i64) -> u64 { return x as u64; }\n", Overflow::Wrap);
+        assert_eq!(
+            function(&module, "id")[0],
+            "(func $id (export \"id\") (param $v0 i64) (result i64)",
+        );
+    }
+
+    #[test]
+    fn single_block_has_no_dispatch() {
+        let module = wat("main() -> i32 { return 7; }\n", Overflow::Wrap);
+        let main = function(&module, "main");
+
+        assert!(!main.contains(&"loop $dispatch"));
+        assert!(!main.contains(&"(local $block i32)"));
+        assert_eq!(&main[main.len() - 2..], ["local.get $v0", "return"]);
+    }
+
+    #[test]
+    fn blocks_are_dispatched_by_br_table() {
+        let module = wat(
+            "max(a: i32, b: i32) -> i32 {\n\
+             \x20   if a > b {\n\
+             \x20       return a;\n\
+             \x20   }\n\
+             \x20   return b;\n\
+             }\n",
+            Overflow::Wrap,
+        );
+        let max = function(&module, "max");
+
+        assert!(max.contains(&"(local $block i32)"));
+        let dispatch = max.iter().position(|line| *line == "loop $dispatch").unwrap();
+        assert_eq!(
+            max[dispatch + 1..dispatch + 6],
+            ["block $b2", "block $b1", "block $b0", "local.get $block", "br_table $b0 $b1 $b2"],
+        );
+        // A branch sets the next block and goes back to the dispatch.
+        assert!(max.windows(3).any(|lines| {
+            lines == ["i32.const 1", "local.set $block", "br $dispatch"]
+        }));
+        assert_eq!(max.last(), Some(&"unreachable"));
+    }
+
+    #[test]
+    fn checked_arithmetic_sets_its_result_only_where_it_changes() {
+        let source = "main() -> i32 { return saturating_add(1, 2) + 3; }\n";
+        for overflow in [Overflow::Trap, Overflow::Wrap] {
+            let module = wat(source, overflow);
+            let main = function(&module, "main");
+
+            for lines in main.windows(2) {
+                if let Some(value) = lines[0].strip_prefix("local.get ") {
+                    assert_ne!(lines[1], format!("local.set {}", value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn frames_stop_above_the_strings() {
+        let module = wat(
+            "main() -> i32 {\n\
+             \x20   print(\"hi\");\n\
+             \x20   a = [1, 2, 3, 4];\n\
+             \x20   return a[0];\n\
+             }\n",
+            Overflow::Wrap,
+        );
+        let main = function(&module, "main");
+
+        assert!(module.contains("  (global $data_end i32 (i32.const 3))\n"));
+        let entry = main.iter().position(|line| *line == "global.get $sp").unwrap();
+        assert_eq!(
+            main[entry + 3..entry + 11],
+            [
+                "local.tee $frame", "global.get $data_end", "i32.lt_s",
+                "if", "unreachable", "end", "local.get $frame", "global.set $sp",
+            ],
+        );
+    }
+}
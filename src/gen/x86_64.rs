@@ -0,0 +1,1258 @@
+//! x86-64 assembly back-end, for the System V ABI.
+//!
+//! Output is GNU `as` syntax (Intel flavour), ready for e.g.
+//! `adzec --emit=asm a.adze > a.s && cc a.s`. Every function is global, so
+//! an adze `main` is a C `main`.
+//!
+//! Values are placed by `regalloc`, either by linear scan or, with
+//! `RegAlloc::Stack`, all on the stack. `rax` and `r11` are kept back as
+//! scratch registers.
+//!
+//! The frame looks like this, from `rbp` down:
+//!
+//! - the callee-saved registers the function uses, pushed in the prologue;
+//! - a slot for each caller-saved register, to keep values live across calls;
+//! - a slot for each spilled value;
+//! - the memory of the function's `Slot`s, 16-byte aligned.
+//!
+//! A `Switch` with dense cases jumps through a table of 32-bit offsets, kept
+//! in the code after it; otherwise it compares the value with each case.
+//!
+//! Every slot is 8 bytes; an integer of 32 bits or fewer or an `f32` uses the
+//! low 4, and a 64-bit integer, an `f64` or a `str` (the address of a
+//! NUL-terminated string in `.rodata`) or a `ptr` all 8. Registers are the
+//! same. An 8 or 16-bit integer is kept sign-extended to 32 bits if it's
+//! signed, and zero-extended if not, so arithmetic on one is followed by
+//! extending its low bits again.
+//!
+//! Floats are kept in general-purpose registers like everything else, as their
+//! bits, and only moved into `xmm0` and `xmm1` to be worked on. So unlike in
+//! the System V ABI, they're passed and returned in general-purpose registers
+//! too, and a function taking or returning one can't be called from C.
+//!
+//! A function returns its value in `rax`, and a second one, if any, in `rdx`,
+//! as C returns a struct of two integers.
+//!
+//! Arithmetic that traps on overflow jumps to a `ud2`, so the program dies of
+//! `SIGILL`; a `Trap` is a `ud2` of its own. Either first calls `fflush` on
+//! every stream, so what the program printed isn't lost with it.
+//!
+//! Printing calls the C library's `printf`, but for chars, which a local
+//! routine, `.Lprint_char`, UTF-8 encodes and passes a byte at a time to
+//! `putchar`. A NaN has its sign cleared first, so it's always `nan`. An
+//! integer converted to a char that isn't a scalar value becomes U+FFFD.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ir::{ self, BinaryOp, Block, InstructionKind, Overflow, Target, Terminator, Type, Value };
+use gen::jump_table;
+use gen::regalloc::{ self, Allocation, Location, Positions, Registers };
+
+/// How to place values, as chosen by `--regalloc=`.
+///
+#[derive (Debug, Copy, Clone, PartialEq)]
+pub enum RegAlloc {
+    LinearScan,
+    /// Keep every value in its own stack slot, for debugging.
+    Stack,
+}
+
+/// 64-bit and 32-bit names of the general-purpose registers, indexed as in
+/// `regalloc`.
+const REGISTERS: [(&str, &str); 14] = [
+    ("rcx", "ecx"),
+    ("rdx", "edx"),
+    ("rsi", "esi"),
+    ("rdi", "edi"),
+    ("r8",  "r8d"),
+    ("r9",  "r9d"),
+    ("r10", "r10d"),
+    ("rbx", "ebx"),
+    ("r12", "r12d"),
+    ("r13", "r13d"),
+    ("r14", "r14d"),
+    ("r15", "r15d"),
+    // Scratch; never allocated.
+    ("rax", "eax"),
+    ("r11", "r11d"),
+];
+
+const CALLER_SAVED: [usize; 7] = [0, 1, 2, 3, 4, 5, 6];
+const CALLEE_SAVED: [usize; 5] = [7, 8, 9, 10, 11];
+
+/// Where the System V ABI passes the first six integer arguments.
+const ARGUMENTS: [usize; 6] = [3, 2, 1, 0, 4, 5];
+
+const RAX: usize = 12;
+const R11: usize = 13;
+
+/// Where functions return their values.
+const RETURNS: [usize; 2] = [RAX, 1];
+
+pub fn emit(module: &ir::Module, regalloc: RegAlloc) -> Result<String, String> {
+    let mut out = String::from("\t.intel_syntax noprefix\n\t.text\n");
+    let mut rodata = String::new();
+
+    for function in &module.functions {
+        let allocation = match regalloc {
+            RegAlloc::LinearScan => regalloc::allocate_linear_scan(function, &Registers {
+                caller_saved: &CALLER_SAVED,
+                callee_saved: &CALLEE_SAVED,
+            }),
+            RegAlloc::Stack => regalloc::allocate_stack(function),
+        };
+
+        out.push('\n');
+        let (code, strings) = Function::new(function, allocation).emit();
+        out.push_str(&code);
+        rodata.push_str(&strings);
+    }
+
+    let prints = module.functions.iter()
+        .flat_map(|function| &function.blocks)
+        .flat_map(|data| &data.instructions)
+        .any(|instruction| matches!(instruction.kind, InstructionKind::Print(_)));
+    let prints_chars = module.functions.iter().any(|function| {
+        function.blocks.iter().flat_map(|data| &data.instructions).any(|instruction| {
+            match instruction.kind {
+                InstructionKind::Print(value) => function.type_of(value) == Type::Char,
+                _ => false,
+            }
+        })
+    });
+    if prints_chars {
+        out.push('\n');
+        out.push_str(PRINT_CHAR);
+    }
+
+    if prints {
+        for format in ["d", "u", "lld", "llu", "g", "s"] {
+            rodata.push_str(&string(&format!(".Lfmt.{}", format), &format!("%{}", format)));
+        }
+    }
+
+    if !rodata.is_empty() {
+        out.push_str("\n\t.section .rodata\n");
+        out.push_str(&rodata);
+    }
+
+    // Nothing needs an executable stack.
+    out.push_str("\n\t.section .note.GNU-stack,\"\",@progbits\n");
+    Ok(out)
+}
+
+/// Print the char in `edi`, UTF-8 encoded. The bytes are packed into `ebx`,
+/// first byte lowest: continuation bytes are taken from the bottom of the
+/// char while what's left doesn't fit in the lead byte, whose marker (`ecx`)
+/// grows as its limit (`edx`) shrinks.
+///
+const PRINT_CHAR: &str = "\
+.Lprint_char:
+	push rbx
+	mov eax, edi
+	mov ebx, edi
+	cmp eax, 0x80
+	jb .Lprint_char.output
+	xor ebx, ebx
+	mov ecx, 0xC0
+	mov edx, 0x20
+.Lprint_char.encode:
+	shl ebx, 8
+	mov r11d, eax
+	and r11d, 0x3F
+	or r11d, 0x80
+	or ebx, r11d
+	shr eax, 6
+	cmp eax, edx
+	jb .Lprint_char.lead
+	shr ecx, 1
+	or ecx, 0x80
+	shr edx, 1
+	jmp .Lprint_char.encode
+.Lprint_char.lead:
+	shl ebx, 8
+	or eax, ecx
+	or ebx, eax
+.Lprint_char.output:
+	movzx edi, bl
+	call putchar@PLT
+	shr ebx, 8
+	jnz .Lprint_char.output
+	pop rbx
+	ret
+";
+
+/// The state of emitting a single function.
+///
+struct Function<'a> {
+    function: &'a ir::Function,
+    allocation: Allocation,
+    positions: Positions,
+
+    /// The callee-saved registers used, in the order they're pushed.
+    callee_saved: Vec<usize>,
+    /// Whether any arithmetic traps on overflow, jumping to a `ud2`.
+    traps: bool,
+    /// The offset of each `Slot`'s memory from the bottom of it all.
+    memory: HashMap<Value, u32>,
+    /// The offset from `rbp` of the bottom of the `Slot`s' memory.
+    memory_base: usize,
+
+    out: String,
+    /// The function's strings, for `.rodata`.
+    strings: String,
+}
+
+impl<'a> Function<'a> {
+    fn new(function: &'a ir::Function, allocation: Allocation) -> Self {
+        let callee_saved = CALLEE_SAVED.iter().cloned().filter(|register| {
+            allocation.locations.contains(&Some(Location::Register(*register)))
+        }).collect::<Vec<_>>();
+
+        let (memory, memory_size) = function.slots();
+        let slots = CALLER_SAVED.len() + allocation.stack_slots;
+        let slots_end = callee_saved.len() * 8 + slots * 8;
+        let memory_base = slots_end.next_multiple_of(16) + memory_size as usize;
+
+        Function {
+            function,
+            positions: Positions::new(function),
+            allocation,
+            callee_saved,
+            traps: false,
+            memory,
+            memory_base,
+            out: String::new(),
+            strings: String::new(),
+        }
+    }
+
+    /// Emit the function's code, and separately its strings.
+    ///
+    fn emit(mut self) -> (String, String) {
+        let function = self.function;
+        let name = &function.name;
+
+        writeln!(self.out, "\t.globl {}", name).unwrap();
+        writeln!(self.out, "\t.type {}, @function", name).unwrap();
+        writeln!(self.out, "{}:", name).unwrap();
+
+        // Prologue
+        self.line("push rbp");
+        self.line("mov rbp, rsp");
+        for register in self.callee_saved.clone() {
+            self.line(&format!("push {}", REGISTERS[register].0));
+        }
+
+        // Keep rsp 16-byte aligned, as calls require; the memory is at the
+        // bottom, so it is too.
+        let frame = self.memory_base - self.callee_saved.len() * 8;
+        if frame > 0 {
+            self.line(&format!("sub rsp, {}", frame));
+        }
+
+        self.parameters();
+
+        for index in 0..function.blocks.len() {
+            writeln!(self.out, "{}:", self.label(Block(index))).unwrap();
+            self.block(Block(index));
+        }
+
+        // Epilogue; returns jump here with the result in eax, and any second
+        // one in rdx.
+        writeln!(self.out, ".L{}.return:", name).unwrap();
+        self.line(&format!("lea rsp, [rbp - {}]", self.callee_saved.len() * 8));
+        for register in self.callee_saved.clone().iter().rev() {
+            self.line(&format!("pop {}", REGISTERS[*register].0));
+        }
+        self.line("pop rbp");
+        self.line("ret");
+
+        if self.traps {
+            writeln!(self.out, ".L{}.overflow:", name).unwrap();
+            self.trap();
+        }
+
+        writeln!(self.out, "\t.size {}, .-{}", name, name).unwrap();
+        (self.out, self.strings)
+    }
+
+    /// Move the parameters from where the ABI puts them to their locations.
+    ///
+    fn parameters(&mut self) {
+        let parameters = self.function.parameters().to_vec();
+
+        // Push every register argument before popping any into place, since
+        // one parameter's location may be another's argument register.
+        let in_registers = parameters.len().min(ARGUMENTS.len());
+        for register in &ARGUMENTS[..in_registers] {
+            self.line(&format!("push {}", REGISTERS[*register].0));
+        }
+        for parameter in parameters[..in_registers].iter().rev() {
+            self.line("pop r11");
+            if self.location(*parameter).is_some() {
+                let destination = self.operand(*parameter);
+                let scratch = self.register(R11, *parameter);
+                self.line(&format!("mov {}, {}", destination, scratch));
+            }
+        }
+
+        // The rest were pushed by the caller, above the return address.
+        for (index, parameter) in parameters.iter().enumerate().skip(in_registers) {
+            if self.location(*parameter).is_none() {
+                continue;
+            }
+            let offset = 16 + 8 * (index - in_registers);
+            let scratch = self.register(R11, *parameter);
+            let size = size(self.function.type_of(*parameter));
+            self.line(&format!("mov {}, {} ptr [rbp + {}]", scratch, size, offset));
+            let destination = self.operand(*parameter);
+            self.line(&format!("mov {}, {}", destination, scratch));
+        }
+    }
+
+    fn block(&mut self, block: Block) {
+        let function = self.function;
+        let data = function.block(block);
+
+        for (instruction, position) in data.instructions.iter()
+            .zip(self.positions.instructions[block.0].clone()) {
+            let result = instruction.result;
+
+            match instruction.kind {
+                InstructionKind::Const(value) => {
+                    let destination = self.operand(result);
+                    // Written as signed, whatever the type, so as to fit the
+                    // immediate; 64 bits only fit by way of a register.
+                    match function.type_of(result).bits() {
+                        64 if value as i64 != value as i32 as i64 => {
+                            self.line(&format!("mov rax, {}", value as i64));
+                            self.line(&format!("mov {}, rax", destination));
+                        },
+                        64 => self.line(&format!("mov {}, {}", destination, value as i64)),
+                        _ => self.line(&format!("mov {}, {}", destination, value as i32)),
+                    }
+                },
+                InstructionKind::Float(value) => {
+                    let destination = self.operand(result);
+                    match function.type_of(result) {
+                        Type::F32 => {
+                            let bits = (value as f32).to_bits();
+                            self.line(&format!("mov {}, {:#x}", destination, bits));
+                        },
+                        // There's no 64-bit immediate store to memory.
+                        _ => {
+                            self.line(&format!("mov rax, {:#x}", value.to_bits()));
+                            self.line(&format!("mov {}, rax", destination));
+                        },
+                    }
+                },
+                InstructionKind::Str(ref contents) => {
+                    let label = format!(".L{}.str{}", function.name, result.0);
+                    self.strings.push_str(&string(&label, contents));
+
+                    // `lea` can only load into a register.
+                    let register = match self.location(result) {
+                        Some(Location::Register(register)) => register,
+                        _ => RAX,
+                    };
+                    self.line(&format!("lea {}, [rip + {}]", REGISTERS[register].0, label));
+                    if register == RAX {
+                        let destination = self.operand(result);
+                        self.line(&format!("mov {}, rax", destination));
+                    }
+                },
+                InstructionKind::Binary(op, overflow, lhs, rhs) => {
+                    match (function.type_of(lhs).is_float(), op.is_comparison()) {
+                        (true, false) => self.float_arithmetic(op, lhs, rhs, result),
+                        (true, true) => self.float_comparison(op, lhs, rhs, result),
+                        (false, true) => self.comparison(op, lhs, rhs, result),
+                        (false, false) if overflow == Overflow::Wrap => {
+                            self.binary(op, lhs, rhs, result)
+                        },
+                        (false, false) => self.checked(op, overflow, lhs, rhs, result),
+                    }
+                },
+                InstructionKind::Convert(value) => self.convert(value, result),
+                InstructionKind::Call(ref name, ref arguments, ref more) => {
+                    self.call(name, arguments, position, result, more);
+                },
+                InstructionKind::Print(value) => self.print(value, position, result),
+                InstructionKind::Slot(..) => {
+                    let offset = self.memory_base - self.memory[&result] as usize;
+                    // As for strings, `lea` can only load into a register.
+                    let register = match self.location(result) {
+                        Some(Location::Register(register)) => register,
+                        _ => RAX,
+                    };
+                    self.line(&format!("lea {}, [rbp - {}]", REGISTERS[register].0, offset));
+                    if register == RAX {
+                        let destination = self.operand(result);
+                        self.line(&format!("mov {}, rax", destination));
+                    }
+                },
+                InstructionKind::Load(address, offset) => self.load(address, offset, result),
+                InstructionKind::Store(address, offset, value) => {
+                    self.store(address, offset, value, result)
+                },
+                InstructionKind::Copy(to, to_offset, from, from_offset, size) => {
+                    self.copy(to, to_offset, from, from_offset, size, result)
+                },
+                InstructionKind::Element(address, index, size) => {
+                    let index = self.operand(index);
+                    let address = self.operand(address);
+                    self.line(&format!("mov rax, {}", index));
+                    self.line(&format!("imul rax, rax, {}", size));
+                    self.line(&format!("add rax, {}", address));
+                    let destination = self.operand(result);
+                    self.line(&format!("mov {}, rax", destination));
+                },
+            }
+        }
+
+        match *data.terminator.as_ref().unwrap() {
+            Terminator::Return(ref values) => {
+                // `rax` isn't allocated, so filling it first can't overwrite
+                // the second value.
+                for (&value, &register) in values.iter().zip(&RETURNS) {
+                    let source = self.operand(value);
+                    let register = self.register(register, value);
+                    self.line(&format!("mov {}, {}", register, source));
+                }
+                self.line(&format!("jmp .L{}.return", function.name));
+            },
+            Terminator::Branch(ref target) => self.branch(target),
+            Terminator::CondBranch(condition, ref then_target, ref else_target) => {
+                let source = self.operand(condition);
+                self.line(&format!("cmp {}, 0", source));
+                let else_label = format!(".L{}.else{}", function.name, block.0);
+                self.line(&format!("je {}", else_label));
+                self.branch(then_target);
+                writeln!(self.out, "{}:", else_label).unwrap();
+                self.branch(else_target);
+            },
+            Terminator::Switch(value, ref cases, ref default) => {
+                self.switch(block, value, cases, default)
+            },
+            Terminator::Trap => self.trap(),
+        }
+    }
+
+    /// Jump to the target of the case equal to `value`, through a table of
+    /// offsets if `jump_table()` gives one, or else by comparing with each
+    /// case in turn.
+    ///
+    fn switch(&mut self, block: Block, value: Value, cases: &[(i128, Target)], default: &Target) {
+        let name = self.function.name.clone();
+        let ty = self.function.type_of(value);
+
+        // A target with arguments is reached through a branch of its own, to
+        // pass them.
+        let labels: Vec<String> = cases.iter().map(|(_, target)| target)
+            .chain(Some(default))
+            .enumerate()
+            .map(|(index, target)| match target.arguments.is_empty() {
+                true  => self.label(target.block),
+                false => format!(".L{}.case{}.{}", name, block.0, index),
+            })
+            .collect();
+
+        let source = self.operand(value);
+        let scratch = self.register(RAX, value);
+        self.line(&format!("mov {}, {}", scratch, source));
+
+        match jump_table(ty, cases) {
+            Some((first, table)) => {
+                // Anything out of range wraps round to above it, unsigned.
+                if first != 0 {
+                    self.line(&format!("sub eax, {}", first as i32));
+                }
+                self.line(&format!("cmp eax, {}", table.len()));
+                self.line(&format!("jae {}", labels[cases.len()]));
+
+                let table_label = format!(".L{}.table{}", name, block.0);
+                self.line(&format!("lea r11, [rip + {}]", table_label));
+                self.line("movsxd rax, dword ptr [r11 + rax * 4]");
+                self.line("add rax, r11");
+                self.line("jmp rax");
+
+                self.line(".p2align 2");
+                writeln!(self.out, "{}:", table_label).unwrap();
+                for index in table {
+                    let label = &labels[index.unwrap_or(cases.len())];
+                    self.line(&format!(".long {} - {}", label, table_label));
+                }
+            },
+            None => {
+                for (index, &(case, _)) in cases.iter().enumerate() {
+                    // Immediates are 32 bits, sign-extended.
+                    match ty.bits() {
+                        64 if case as i64 != case as i64 as i32 as i64 => {
+                            self.line(&format!("mov r11, {}", case as i64));
+                            self.line("cmp rax, r11");
+                        },
+                        64 => self.line(&format!("cmp rax, {}", case as i64)),
+                        _ => self.line(&format!("cmp eax, {}", case as i32)),
+                    }
+                    self.line(&format!("je {}", labels[index]));
+                }
+                self.line(&format!("jmp {}", labels[cases.len()]));
+            },
+        }
+
+        for (index, target) in cases.iter().map(|(_, target)| target).chain(Some(default))
+            .enumerate() {
+            if !target.arguments.is_empty() {
+                writeln!(self.out, "{}:", labels[index]).unwrap();
+                self.branch(target);
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: Value, rhs: Value, result: Value) {
+        let instruction = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "imul",
+            _ => unreachable!("{} is a comparison", op),
+        };
+
+        let ty = self.function.type_of(result);
+        let scratch = self.register(RAX, result);
+        let lhs = self.operand(lhs);
+        let rhs_location = self.location(rhs);
+        let rhs = self.operand(rhs);
+        let destination = self.operand(result);
+
+        // Work in the destination register directly where that won't clobber
+        // the right-hand side before it's read, and there's nothing to extend
+        // after. (`imul` can't take a memory destination anyway.)
+        match self.location(result) {
+            Some(Location::Register(_))
+                if self.location(result) != rhs_location && ty.bits() >= 32 => {
+                if destination != lhs {
+                    self.line(&format!("mov {}, {}", destination, lhs));
+                }
+                self.line(&format!("{} {}, {}", instruction, destination, rhs));
+            },
+            _ => {
+                self.line(&format!("mov {}, {}", scratch, lhs));
+                self.line(&format!("{} {}, {}", instruction, scratch, rhs));
+                self.extend_rax(ty);
+                self.line(&format!("mov {}, {}", destination, scratch));
+            },
+        }
+    }
+
+    /// Integer arithmetic that traps or saturates on overflow. A 64-bit
+    /// result is checked by the flags; narrower ones are worked out in 64
+    /// bits, where they can't overflow, and then checked they're in range.
+    ///
+    fn checked(
+        &mut self,
+        op: BinaryOp,
+        overflow: Overflow,
+        lhs: Value,
+        rhs: Value,
+        result: Value,
+    ) {
+        let ty = self.function.type_of(result);
+        let name = self.function.name.clone();
+        let lhs_operand = self.operand(lhs);
+        let rhs_operand = self.operand(rhs);
+
+        // The condition the result overflowed under.
+        let condition = match ty.bits() {
+            64 => {
+                self.line(&format!("mov rax, {}", lhs_operand));
+                match (op, ty.is_signed()) {
+                    (BinaryOp::Add, _) => self.line(&format!("add rax, {}", rhs_operand)),
+                    (BinaryOp::Sub, _) => self.line(&format!("sub rax, {}", rhs_operand)),
+                    (BinaryOp::Mul, true) => self.line(&format!("imul rax, {}", rhs_operand)),
+                    // `mul` also writes the high half to `rdx`, which may be
+                    // in use; neither `push` nor `pop` touch the flags.
+                    (BinaryOp::Mul, false) => {
+                        self.line("push rdx");
+                        self.line(&format!("mul {}", rhs_operand));
+                        self.line("pop rdx");
+                    },
+                    _ => unreachable!("{} is a comparison", op),
+                }
+                match (op, ty.is_signed()) {
+                    (BinaryOp::Add, false) | (BinaryOp::Sub, false) => "c",
+                    _ => "o",
+                }
+            },
+            _ => {
+                self.load_extended(RAX, lhs);
+                self.load_extended(R11, rhs);
+                match op {
+                    BinaryOp::Add => self.line("add rax, r11"),
+                    BinaryOp::Sub => self.line("sub rax, r11"),
+                    BinaryOp::Mul => self.line("imul rax, r11"),
+                    _ => unreachable!("{} is a comparison", op),
+                }
+                // It's in range if extending its low bits gives it back.
+                self.line(match ty {
+                    Type::I8 => "movsx r11, al",
+                    Type::I16 => "movsx r11, ax",
+                    Type::I32 => "movsxd r11, eax",
+                    Type::U8 => "movzx r11d, al",
+                    Type::U16 => "movzx r11d, ax",
+                    _ => "mov r11d, eax",
+                });
+                self.line("cmp rax, r11");
+                "ne"
+            },
+        };
+
+        match overflow {
+            Overflow::Trap => {
+                self.line(&format!("j{} .L{}.overflow", condition, name));
+                self.traps = true;
+            },
+            _ => {
+                let done = format!(".L{}.saturated{}", name, result.0);
+                let (min, max) = ty.range();
+                let (min, max) = (min as i64, max as i64);
+                self.line(&format!("j{} {}", negate(condition), done));
+
+                match (op, ty.is_signed()) {
+                    (BinaryOp::Sub, false) => self.line("xor eax, eax"),
+                    (_, false) => self.line(&format!("mov rax, {}", max)),
+                    // Which way it overflowed follows from the operands'
+                    // signs: the right-hand one's for `+` and `-`, and
+                    // whether they differ for `*`.
+                    (_, true) => {
+                        let scratch = self.register(R11, rhs);
+                        self.line(&format!("mov {}, {}", scratch, rhs_operand));
+                        if op == BinaryOp::Mul {
+                            self.line(&format!("xor {}, {}", scratch, lhs_operand));
+                        }
+                        self.line(&format!("test {}, {}", scratch, scratch));
+                        let (positive, negative) = match op {
+                            BinaryOp::Sub => (min, max),
+                            _ => (max, min),
+                        };
+                        self.line(&format!("mov rax, {}", positive));
+                        self.line(&format!("jns {}", done));
+                        self.line(&format!("mov rax, {}", negative));
+                    },
+                }
+                writeln!(self.out, "{}:", done).unwrap();
+            },
+        }
+
+        let destination = self.operand(result);
+        let scratch = self.register(RAX, result);
+        self.line(&format!("mov {}, {}", destination, scratch));
+    }
+
+    fn comparison(&mut self, op: BinaryOp, lhs: Value, rhs: Value, result: Value) {
+        let condition = match (self.function.type_of(lhs).is_signed(), op) {
+            (_, BinaryOp::Eq) => "e",
+            (_, BinaryOp::Ne) => "ne",
+            (true, BinaryOp::Lt) => "l",
+            (true, BinaryOp::Le) => "le",
+            (true, BinaryOp::Gt) => "g",
+            (true, BinaryOp::Ge) => "ge",
+            (false, BinaryOp::Lt) => "b",
+            (false, BinaryOp::Le) => "be",
+            (false, BinaryOp::Gt) => "a",
+            (false, BinaryOp::Ge) => "ae",
+            _ => unreachable!("{} is not a comparison", op),
+        };
+
+        let scratch = self.register(RAX, lhs);
+        let lhs = self.operand(lhs);
+        let rhs = self.operand(rhs);
+        self.line(&format!("mov {}, {}", scratch, lhs));
+        self.line(&format!("cmp {}, {}", scratch, rhs));
+        self.line(&format!("set{} al", condition));
+        self.set_result(result);
+    }
+
+    /// Re-extend the low bits of `eax`, if it holds an 8 or 16-bit integer of
+    /// type `ty`.
+    ///
+    fn extend_rax(&mut self, ty: Type) {
+        match ty {
+            Type::I8 => self.line("movsx eax, al"),
+            Type::I16 => self.line("movsx eax, ax"),
+            Type::U8 => self.line("movzx eax, al"),
+            Type::U16 => self.line("movzx eax, ax"),
+            _ => (),
+        }
+    }
+
+    /// Load an integer into `register`, extended to 64 bits.
+    ///
+    fn load_extended(&mut self, register: usize, value: Value) {
+        let ty = self.function.type_of(value);
+        let source = self.operand(value);
+        let (wide, narrow) = REGISTERS[register];
+        match (ty.bits(), ty.is_signed()) {
+            (64, _) => self.line(&format!("mov {}, {}", wide, source)),
+            (_, true) => self.line(&format!("movsxd {}, {}", wide, source)),
+            // Writing the low half clears the top.
+            (_, false) => self.line(&format!("mov {}, {}", narrow, source)),
+        }
+    }
+
+    fn float_arithmetic(&mut self, op: BinaryOp, lhs: Value, rhs: Value, result: Value) {
+        let suffix = float_suffix(self.function.type_of(lhs));
+        let instruction = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            _ => unreachable!("{} is a comparison", op),
+        };
+
+        self.load_xmm(0, lhs);
+        self.load_xmm(1, rhs);
+        self.line(&format!("{}{} xmm0, xmm1", instruction, suffix));
+        self.store_xmm(0, result);
+    }
+
+    /// Compare floats. `ucomis*` sets the flags as for an unsigned compare,
+    /// and sets PF as well if either is NaN, when only `!=` is true.
+    ///
+    fn float_comparison(&mut self, op: BinaryOp, lhs: Value, rhs: Value, result: Value) {
+        let suffix = float_suffix(self.function.type_of(lhs));
+        self.load_xmm(0, lhs);
+        self.load_xmm(1, rhs);
+
+        // NaN clears CF, so `<` and `<=` are done as `>` and `>=` swapped.
+        let compare = |swapped: bool| match swapped {
+            false => format!("ucomi{} xmm0, xmm1", suffix),
+            true  => format!("ucomi{} xmm1, xmm0", suffix),
+        };
+        match op {
+            BinaryOp::Eq => {
+                self.line(&compare(false));
+                self.line("sete al");
+                self.line("setnp r11b");
+                self.line("and al, r11b");
+            },
+            BinaryOp::Ne => {
+                self.line(&compare(false));
+                self.line("setne al");
+                self.line("setp r11b");
+                self.line("or al, r11b");
+            },
+            BinaryOp::Gt | BinaryOp::Lt => {
+                self.line(&compare(op == BinaryOp::Lt));
+                self.line("seta al");
+            },
+            BinaryOp::Ge | BinaryOp::Le => {
+                self.line(&compare(op == BinaryOp::Le));
+                self.line("setae al");
+            },
+            _ => unreachable!("{} is not a comparison", op),
+        }
+        self.set_result(result);
+    }
+
+    /// Store `al`, as an `i32`, to `result`.
+    ///
+    fn set_result(&mut self, result: Value) {
+        self.line("movzx eax, al");
+        let destination = self.operand(result);
+        self.line(&format!("mov {}, eax", destination));
+    }
+
+    fn convert(&mut self, value: Value, result: Value) {
+        let from = self.function.type_of(value);
+        let to = self.function.type_of(result);
+
+        match (from.is_float(), to.is_float()) {
+            (false, false) => {
+                // Extending to 64 bits first gives every wider type its
+                // value; the low bits are every narrower one's.
+                self.load_extended(RAX, value);
+                self.extend_rax(to);
+                if to == Type::Char {
+                    // U+FFFD for a surrogate, or anything too big, which
+                    // includes negative numbers as unsigned.
+                    self.line("lea r11, [rax - 0xD800]");
+                    self.line("cmp r11, 0x800");
+                    self.line("mov r11d, 0xFFFD");
+                    self.line("cmovb rax, r11");
+                    self.line("cmp rax, 0x10FFFF");
+                    self.line("cmova rax, r11");
+                }
+                let destination = self.operand(result);
+                let scratch = self.register(RAX, result);
+                self.line(&format!("mov {}, {}", destination, scratch));
+            },
+            (false, true) => self.integer_to_float(value, result),
+            (true, false) => self.float_to_integer(value, result),
+            (true, true) if from == Type::F32 => {
+                self.load_xmm(0, value);
+                self.line("cvtss2sd xmm0, xmm0");
+                self.store_xmm(0, result);
+            },
+            (true, true) => {
+                self.load_xmm(0, value);
+                self.line("cvtsd2ss xmm0, xmm0");
+                self.store_xmm(0, result);
+            },
+        }
+    }
+
+    fn integer_to_float(&mut self, value: Value, result: Value) {
+        let instruction = format!("cvtsi2{}", float_suffix(self.function.type_of(result)));
+
+        // Every integer but a `u64` fits an `i64`, which `cvtsi2*` takes.
+        self.load_extended(RAX, value);
+        if self.function.type_of(value) != Type::U64 {
+            self.line(&format!("{} xmm0, rax", instruction));
+            self.store_xmm(0, result);
+            return;
+        }
+
+        // A `u64` with its top bit set is halved, keeping its lowest bit so
+        // as to round the same, and doubled after.
+        let label = format!(".L{}.convert{}", self.function.name, result.0);
+        let done = format!("{}.done", label);
+        self.line("test rax, rax");
+        self.line(&format!("js {}", label));
+        self.line(&format!("{} xmm0, rax", instruction));
+        self.line(&format!("jmp {}", done));
+        writeln!(self.out, "{}:", label).unwrap();
+        self.line("mov r11, rax");
+        self.line("shr r11, 1");
+        self.line("and eax, 1");
+        self.line("or r11, rax");
+        self.line(&format!("{} xmm0, r11", instruction));
+        self.line(&format!("add{} xmm0, xmm0", float_suffix(self.function.type_of(result))));
+        writeln!(self.out, "{}:", done).unwrap();
+        self.store_xmm(0, result);
+    }
+
+    /// Convert a float to an integer, saturating, with NaN as 0. `cvttsd2si`
+    /// alone gives `i64::MIN` for anything out of its range.
+    ///
+    fn float_to_integer(&mut self, value: Value, result: Value) {
+        let to = self.function.type_of(result);
+        let (min, max) = to.range();
+        let done = format!(".L{}.convert{}", self.function.name, result.0);
+
+        self.load_xmm(0, value);
+        if self.function.type_of(value) == Type::F32 {
+            self.line("cvtss2sd xmm0, xmm0");
+        }
+
+        self.line("xor eax, eax");
+        self.line("ucomisd xmm0, xmm0");
+        self.line(&format!("jp {}", done));
+
+        // Both `min` and `max + 1` are powers of two, or 0, so exact.
+        self.line(&format!("mov rax, {:#x}", (min as f64).to_bits()));
+        self.line("movq xmm1, rax");
+        self.line("maxsd xmm0, xmm1");
+        self.line(&format!("mov rax, {:#x}", ((max + 1) as f64).to_bits()));
+        self.line("movq xmm1, rax");
+        self.line(&format!("mov rax, {}", max as i64));
+        self.line("ucomisd xmm0, xmm1");
+        self.line(&format!("jae {}", done));
+
+        if to == Type::U64 {
+            // At or above 2^63, take that off first, and put it back after.
+            let small = format!("{}.small", done);
+            self.line(&format!("mov rax, {:#x}", 9223372036854775808f64.to_bits()));
+            self.line("movq xmm1, rax");
+            self.line("ucomisd xmm0, xmm1");
+            self.line(&format!("jb {}", small));
+            self.line("subsd xmm0, xmm1");
+            self.line("cvttsd2si rax, xmm0");
+            self.line("btc rax, 63");
+            self.line(&format!("jmp {}", done));
+            writeln!(self.out, "{}:", small).unwrap();
+        }
+        self.line("cvttsd2si rax, xmm0");
+        writeln!(self.out, "{}:", done).unwrap();
+
+        let destination = self.operand(result);
+        let scratch = self.register(RAX, result);
+        self.line(&format!("mov {}, {}", destination, scratch));
+    }
+
+    /// Move a float into `xmm<index>`.
+    ///
+    fn load_xmm(&mut self, index: usize, value: Value) {
+        let instruction = match self.function.type_of(value) {
+            Type::F32 => "movd",
+            _ => "movq",
+        };
+        let source = self.operand(value);
+        self.line(&format!("{} xmm{}, {}", instruction, index, source));
+    }
+
+    /// Move a float from `xmm<index>`.
+    ///
+    fn store_xmm(&mut self, index: usize, value: Value) {
+        let instruction = match self.function.type_of(value) {
+            Type::F32 => "movd",
+            _ => "movq",
+        };
+        let destination = self.operand(value);
+        self.line(&format!("{} {}, xmm{}", instruction, destination, index));
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        arguments: &[Value],
+        position: usize,
+        result: Value,
+        more: &[Value],
+    ) {
+        let saved = self.save_caller_saved(position);
+
+        let in_registers = arguments.len().min(ARGUMENTS.len());
+        let on_stack = arguments.len() - in_registers;
+
+        // Stack arguments go in reverse, with padding first if need be to
+        // keep rsp aligned.
+        let padding = if on_stack % 2 == 1 { 8 } else { 0 };
+        if padding > 0 {
+            self.line("sub rsp, 8");
+        }
+        for argument in arguments[in_registers..].iter().rev() {
+            self.push(*argument);
+        }
+
+        // As with parameters, push everything before popping any of it into
+        // place.
+        for argument in &arguments[..in_registers] {
+            self.push(*argument);
+        }
+        for register in ARGUMENTS[..in_registers].iter().rev() {
+            self.line(&format!("pop {}", REGISTERS[*register].0));
+        }
+
+        self.line(&format!("call {}", name));
+
+        let cleanup = on_stack * 8 + padding;
+        if cleanup > 0 {
+            self.line(&format!("add rsp, {}", cleanup));
+        }
+
+        // Take the second value first, in case the first's location is
+        // `rdx`; no other value's location can be `rax`.
+        for (&value, &register) in more.iter().zip(&RETURNS[1..]) {
+            let destination = self.operand(value);
+            let register = self.register(register, value);
+            self.line(&format!("mov {}, {}", destination, register));
+        }
+        let destination = self.operand(result);
+        let register = self.register(RAX, result);
+        self.line(&format!("mov {}, {}", destination, register));
+
+        self.restore_caller_saved(&saved);
+    }
+
+    fn print(&mut self, value: Value, position: usize, result: Value) {
+        let saved = self.save_caller_saved(position);
+
+        let ty = self.function.type_of(value);
+        if ty == Type::Char {
+            let source = self.operand(value);
+            self.line(&format!("mov edi, {}", source));
+            self.line("call .Lprint_char");
+
+            let destination = self.operand(result);
+            self.line(&format!("mov {}, 0", destination));
+            self.restore_caller_saved(&saved);
+            return;
+        }
+
+        // The value goes in its argument register before the format string
+        // goes in the first, since the value may be in the first.
+        let format = match ty {
+            Type::I8 | Type::I16 | Type::I32 | Type::U8 | Type::U16 => ".Lfmt.d",
+            Type::U32 => ".Lfmt.u",
+            Type::I64 => ".Lfmt.lld",
+            Type::U64 => ".Lfmt.llu",
+            Type::F32 | Type::F64 => ".Lfmt.g",
+            Type::Str => ".Lfmt.s",
+            Type::Char => unreachable!("chars are printed by .Lprint_char"),
+            Type::Ptr => unreachable!("ptrs aren't printed"),
+        };
+        if ty.is_float() {
+            // Variadic floats are always passed as doubles.
+            self.load_xmm(0, value);
+            if ty == Type::F32 {
+                self.line("cvtss2sd xmm0, xmm0");
+            }
+            // `printf` prints a NaN whose sign is set as `-nan`; clear it, so
+            // every NaN is `nan`, as in the VM.
+            self.line("movq rax, xmm0");
+            self.line("mov r11, rax");
+            self.line("btr r11, 63");
+            self.line("ucomisd xmm0, xmm0");
+            self.line("cmovp rax, r11");
+            self.line("movq xmm0, rax");
+        }
+        else {
+            let source = self.operand(value);
+            let argument = self.register(ARGUMENTS[1], value);
+            if source != argument {
+                self.line(&format!("mov {}, {}", argument, source));
+            }
+        }
+        self.line(&format!("lea rdi, [rip + {}]", format));
+        // A variadic call takes the number of vector registers used in `al`.
+        let vector_registers = if ty.is_float() { 1 } else { 0 };
+        self.line(&format!("mov eax, {}", vector_registers));
+        self.line("call printf@PLT");
+
+        let destination = self.operand(result);
+        self.line(&format!("mov {}, 0", destination));
+
+        self.restore_caller_saved(&saved);
+    }
+
+    /// Flush the C library's output and die of `SIGILL`. Nothing is restored
+    /// after, so `rsp` is just aligned down for the call.
+    fn trap(&mut self) {
+        self.line("and rsp, -16");
+        self.line("xor edi, edi");
+        self.line("call fflush@PLT");
+        self.line("ud2");
+    }
+
+    /// Load `result` from `offset` bytes past `address`, extending an 8 or
+    /// 16-bit integer as it's kept in registers.
+    ///
+    fn load(&mut self, address: Value, offset: u32, result: Value) {
+        let source = self.operand(address);
+        self.line(&format!("mov r11, {}", source));
+
+        let ty = self.function.type_of(result);
+        let memory = format!("[r11 + {}]", offset);
+        self.line(&match ty {
+            Type::I8 => format!("movsx eax, byte ptr {}", memory),
+            Type::I16 => format!("movsx eax, word ptr {}", memory),
+            Type::U8 => format!("movzx eax, byte ptr {}", memory),
+            Type::U16 => format!("movzx eax, word ptr {}", memory),
+            _ => format!("mov {}, {} ptr {}", self.register(RAX, result), size(ty), memory),
+        });
+
+        let destination = self.operand(result);
+        let scratch = self.register(RAX, result);
+        self.line(&format!("mov {}, {}", destination, scratch));
+    }
+
+    /// Store `value` at `offset` bytes past `address`; `result` is 0.
+    ///
+    fn store(&mut self, address: Value, offset: u32, value: Value, result: Value) {
+        let source = self.operand(address);
+        self.line(&format!("mov r11, {}", source));
+        let source = self.operand(value);
+        let scratch = self.register(RAX, value);
+        self.line(&format!("mov {}, {}", scratch, source));
+
+        let (width, register) = match self.function.type_of(value).bits() {
+            8 => ("byte", "al"),
+            16 => ("word", "ax"),
+            32 => ("dword", "eax"),
+            _ => ("qword", "rax"),
+        };
+        self.line(&format!("mov {} ptr [r11 + {}], {}", width, offset, register));
+
+        let destination = self.operand(result);
+        self.line(&format!("mov {}, 0", destination));
+    }
+
+    /// Copy `size` bytes from `from_offset` bytes past `from` to `to_offset`
+    /// bytes past `to`, with `rep movsb`; `result` is 0. That takes `rdi`,
+    /// `rsi` and `rcx`, which are kept on the stack meanwhile.
+    ///
+    fn copy(
+        &mut self,
+        to: Value,
+        to_offset: u32,
+        from: Value,
+        from_offset: u32,
+        size: u32,
+        result: Value,
+    ) {
+        let source = self.operand(to);
+        self.line(&format!("mov r11, {}", source));
+        let source = self.operand(from);
+        self.line(&format!("mov rax, {}", source));
+
+        self.line("push rsi");
+        self.line("push rdi");
+        self.line("push rcx");
+        self.line(&format!("lea rdi, [r11 + {}]", to_offset));
+        self.line(&format!("lea rsi, [rax + {}]", from_offset));
+        self.line(&format!("mov ecx, {}", size));
+        self.line("rep movsb");
+        self.line("pop rcx");
+        self.line("pop rdi");
+        self.line("pop rsi");
+
+        let destination = self.operand(result);
+        self.line(&format!("mov {}, 0", destination));
+    }
+
+    /// Keep caller-saved registers whose values outlive the call at
+    /// `position`; return them, for `restore_caller_saved()`.
+    ///
+    fn save_caller_saved(&mut self, position: usize) -> Vec<usize> {
+        let mut saved = Vec::new();
+        for (index, location) in self.allocation.locations.iter().enumerate() {
+            if let Some(Location::Register(register)) = *location {
+                let interval = self.allocation.intervals[index].unwrap();
+                if CALLER_SAVED.contains(&register) && interval.spans(position) {
+                    saved.push(register);
+                }
+            }
+        }
+        for register in &saved {
+            let slot = self.caller_saved_slot(*register);
+            self.line(&format!("mov qword ptr {}, {}", slot, REGISTERS[*register].0));
+        }
+        saved
+    }
+
+    fn restore_caller_saved(&mut self, saved: &[usize]) {
+        for register in saved {
+            let slot = self.caller_saved_slot(*register);
+            self.line(&format!("mov {}, qword ptr {}", REGISTERS[*register].0, slot));
+        }
+    }
+
+    /// Pass arguments to the target's parameters, and jump to it.
+    ///
+    fn branch(&mut self, target: &ir::Target) {
+        let parameters = self.function.block(target.block).parameters.clone();
+
+        // The moves must happen in parallel, since a parameter's location may
+        // hold another's argument; so go through the stack.
+        let moves: Vec<(Value, Value)> = target.arguments.iter().cloned()
+            .zip(parameters)
+            .filter(|&(argument, parameter)| {
+                self.location(parameter).is_some() &&
+                self.location(argument) != self.location(parameter)
+            })
+            .collect();
+
+        for &(argument, _) in &moves {
+            self.push(argument);
+        }
+        for &(_, parameter) in moves.iter().rev() {
+            self.line("pop r11");
+            let destination = self.operand(parameter);
+            let scratch = self.register(R11, parameter);
+            self.line(&format!("mov {}, {}", destination, scratch));
+        }
+
+        let label = self.label(target.block);
+        self.line(&format!("jmp {}", label));
+    }
+
+    /// Push a value, as 8 bytes, on to the stack.
+    ///
+    fn push(&mut self, value: Value) {
+        match self.location(value) {
+            Some(Location::Register(register)) => {
+                self.line(&format!("push {}", REGISTERS[register].0));
+            },
+            _ => {
+                let source = self.operand(value);
+                let scratch = self.register(R11, value);
+                self.line(&format!("mov {}, {}", scratch, source));
+                self.line("push r11");
+            },
+        }
+    }
+
+    fn location(&self, value: Value) -> Option<Location> {
+        self.allocation.locations[value.0]
+    }
+
+    /// The assembly operand for a value's location, as wide as its type.
+    ///
+    fn operand(&self, value: Value) -> String {
+        match self.location(value) {
+            Some(Location::Register(register)) => self.register(register, value).to_string(),
+            Some(Location::Stack(slot)) => {
+                let offset = self.slots_base() + (CALLER_SAVED.len() + slot + 1) * 8;
+                format!("{} ptr [rbp - {}]", size(self.function.type_of(value)), offset)
+            },
+            None => panic!("{} has no location", value),
+        }
+    }
+
+    /// The name of `register`, as wide as `value`'s type.
+    ///
+    fn register(&self, register: usize, value: Value) -> &'static str {
+        match size(self.function.type_of(value)) {
+            "qword" => REGISTERS[register].0,
+            _ => REGISTERS[register].1,
+        }
+    }
+
+    fn caller_saved_slot(&self, register: usize) -> String {
+        let index = CALLER_SAVED.iter().position(|r| *r == register).unwrap();
+        format!("[rbp - {}]", self.slots_base() + (index + 1) * 8)
+    }
+
+    /// The offset from `rbp` of the top of the slots; below the pushed
+    /// callee-saved registers.
+    ///
+    fn slots_base(&self) -> usize {
+        self.callee_saved.len() * 8
+    }
+
+    fn label(&self, block: Block) -> String {
+        format!(".L{}.{}", self.function.name, block.0)
+    }
+
+    fn line(&mut self, line: &str) {
+        self.out.push('\t');
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+}
+
+/// A NUL-terminated string, for `.rodata`.
+///
+fn string(label: &str, contents: &str) -> String {
+    let bytes: Vec<String> = contents.bytes().chain(Some(0))
+        .map(|byte| byte.to_string())
+        .collect();
+    format!("{}:\n\t.byte {}\n", label, bytes.join(","))
+}
+
+/// The size of a value of type `ty` in memory.
+///
+fn size(ty: Type) -> &'static str {
+    match ty.bits() {
+        64 => "qword",
+        _ => "dword",
+    }
+}
+
+/// The opposite of the condition code `condition`.
+///
+fn negate(condition: &str) -> String {
+    match condition.strip_prefix('n') {
+        Some(condition) => condition.to_string(),
+        None => format!("n{}", condition),
+    }
+}
+
+/// The suffix of SSE instructions on floats of type `ty`.
+///
+fn float_suffix(ty: Type) -> &'static str {
+    match ty {
+        Type::F32 => "ss",
+        _ => "sd",
+    }
+}
@@ -0,0 +1,2820 @@
+//! Lowering of a `parse::Tree` to IR.
+//!
+//! Locals are put into SSA form as they are lowered: each assignment just
+//! rebinds the name to a new `Value`, and where control flow joins, names
+//! that differ between the incoming edges become block parameters.
+//!
+//! A name assigned on only some of the paths into a join is out of scope
+//! after it.
+//!
+//! Parameters are `i32`s unless their types are given, and a local may be
+//! given a type where it's assigned. A function returns a value only if its
+//! type is given, after `->`, and then must `return` one on every path; one
+//! without can't, and a call to it is only a statement. In the IR, it returns
+//! an `i32` 0, so `main` without a type exits with 0. Every other type is
+//! inferred: each operator needs operands of the same type, and nothing
+//! converts implicitly, but `as` converts explicitly, as do the built-ins
+//! named after each type (`i64()`, `f32()`, ...). `isize` and `usize` are
+//! other names for `i64` and `u64`.
+//!
+//! A literal with a suffix has that type. One without takes the type expected
+//! where it's used, if that's an integer or float type as it's an integer or
+//! float literal; otherwise it's an `i32` or an `f64`.
+//!
+//! String literals are `str`s, which can only be bound to a name and passed
+//! to the built-in `print`.
+//!
+//! Character literals are `char`s, which can be compared but not added, and
+//! converted to and from integers. An integer that isn't a Unicode scalar
+//! value converts to U+FFFD, the replacement character.
+//!
+//! Integer `+`, `-` and `*` wrap or trap on overflow, as `lower` is told.
+//! Either way, the built-ins `wrapping_add`, `wrapping_sub`, `wrapping_mul`,
+//! `saturating_add`, `saturating_sub` and `saturating_mul` do what they say.
+//!
+//! Structs are laid out as in C: fields in the order declared, each aligned
+//! to its size (a struct's alignment being its largest field's), and the
+//! whole padded to its alignment. A struct is kept in a `Slot`, and its
+//! `Value` is the slot's address. Structs are values, so binding a local to
+//! another, or passing one, copies it; after that, assigning to a field
+//! changes the local's own memory. A struct argument is passed as the
+//! address of the caller's copy, and a struct is returned by copying it to
+//! memory the caller passes the address of, as a hidden first parameter.
+//!
+//! An array, `[T; N]`, is `N` `T`s one after another, aligned as a `T`.
+//! It's made with a literal, `[a, b, ...]`, whose elements take their type
+//! from the first unless one is expected, and otherwise handled like a
+//! struct. An element is `a[i]`, where the index is an integer of any type;
+//! one out of bounds is an error if it's a constant, and traps otherwise. The
+//! built-in `len` gives an array's length, as a `usize`.
+//!
+//! A reference, `&T` or `&mut T`, is the address of a `T`, taken with `&a`
+//! or `&mut a` and followed with `*r`; a field or element is reached through
+//! one without the `*`. A local whose address is taken is kept in a slot. Only
+//! a `&mut` reference can be assigned through, and one converts to a `&`
+//! where that's expected. A slice, `&[T]` or `&mut [T]`, is the address of
+//! its first element then its length, a `usize`, kept in memory like a
+//! struct; a reference to an array converts to one where one is expected, and
+//! every index into one is checked when the program runs.
+//!
+//! A reference mustn't outlive what it refers to. A function returning one
+//! must take one, for it to refer to what that does, and can't return a
+//! reference into its own frame: to a local, or to the caller's copy of an
+//! argument. Nor can it store one through a reference, or pass one to a
+//! function along with a `&mut` reference it could be stored through. What's
+//! in memory is tracked loosely: once a reference into the frame is stored
+//! anywhere in it, any reference read from it might be one.
+//!
+//! An enum is laid out as a `u32` tag, the index of its variant, then the
+//! variant's fields, placed as a struct's would be after the tag, aligned for
+//! any variant's; it's as big as the biggest variant needs. It's made with
+//! `Name::Variant(value, ...)`, and otherwise handled like a struct.
+//!
+//! A `match` runs the first arm whose pattern matches: `_`, a literal, for an
+//! integer or char, or a variant, binding names to copies of its fields. It
+//! must be exhaustive, with a `_` arm or an arm for every variant (or every
+//! value). Arms that can't be reached are `lint`'s to warn about; they are
+//! still checked, but never run. It's lowered to a `Switch` on the tag, or on
+//! the integer or char itself.
+//!
+//! A tuple, `(T, U, ...)`, is laid out as a struct whose fields are named
+//! `0`, `1`, ..., and reached as `t.0`. It's made with a literal, `(a, b)`,
+//! and taken apart where it's assigned, as in `(q, r) := divmod(a, b);`,
+//! with `_` for an element that isn't wanted. A tuple of two elements that
+//! aren't kept in memory is returned as the function's two values, rather
+//! than through memory, and taken apart without ever being stored.
+
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+
+use lex::{ Float, FloatType, Integer, IntegerType, TokenKind };
+use parse::Tree;
+use parse::syntax::{ self, downcast, literal };
+use ir::{
+    BinaryOp, Block, Function, InstructionKind, Module, Overflow, Target,
+    Terminator, Type, Value,
+};
+
+/// Whether `name` is a function provided by the compiler, which can't be
+/// defined: `print`, `len`, a conversion to a type, or arithmetic.
+///
+fn is_built_in(name: &str) -> bool {
+    name == "print" || name == "len" || named_type(name).is_some() ||
+        arithmetic_built_in(name).is_some()
+}
+
+/// The operation and overflow of an arithmetic built-in, such as
+/// `wrapping_add`.
+///
+fn arithmetic_built_in(name: &str) -> Option<(BinaryOp, Overflow)> {
+    let (overflow, op) = name.split_once('_')?;
+    let overflow = match overflow {
+        "wrapping" => Overflow::Wrap,
+        "saturating" => Overflow::Saturate,
+        _ => return None,
+    };
+    let op = match op {
+        "add" => BinaryOp::Add,
+        "sub" => BinaryOp::Sub,
+        "mul" => BinaryOp::Mul,
+        _ => return None,
+    };
+    Some((op, overflow))
+}
+
+/// The type of something in the source: a scalar, a struct or enum, named,
+/// an array of a type, a reference (`&mut` if the `bool` is set) to a type
+/// or to a slice of one, or a tuple.
+///
+#[derive (Debug, Clone, PartialEq)]
+enum Ty {
+    Scalar(Type),
+    Struct(String),
+    Enum(String),
+    Array(Box<Ty>, u32),
+    Ref(Box<Ty>, bool),
+    Slice(Box<Ty>, bool),
+    Tuple(Vec<Ty>),
+}
+
+impl Ty {
+    /// The type of the `Value` holding one: the address of a struct, enum,
+    /// array or slice, or what a reference refers to.
+    ///
+    fn ir_type(&self) -> Type {
+        match *self {
+            Ty::Scalar(ty) => ty,
+            _ => Type::Ptr,
+        }
+    }
+
+    /// The type, if it's a scalar; what's expected of a value of it.
+    ///
+    fn scalar(&self) -> Option<Type> {
+        match *self {
+            Ty::Scalar(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Whether a value of the type is kept in memory, its `Value` being the
+    /// address: a struct, enum, array, slice or tuple, but not a reference.
+    ///
+    fn in_memory(&self) -> bool {
+        !matches!(*self, Ty::Scalar(_) | Ty::Ref(..))
+    }
+
+    /// The type with what kind it is, for messages: `struct A`, say.
+    ///
+    fn describe(&self) -> String {
+        match *self {
+            Ty::Struct(ref name) => format!("struct {}", name),
+            Ty::Enum(ref name) => format!("enum {}", name),
+            Ty::Tuple(_) => format!("tuple {}", self),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The types of the `Value`s a function returning this returns, unless
+    /// it's returned to memory the caller reserves: one, or a tuple of two
+    /// that aren't kept in memory.
+    ///
+    fn returned(&self) -> Option<Vec<Type>> {
+        match *self {
+            Ty::Tuple(ref elements) => match elements.len() == 2 &&
+                elements.iter().all(|element| !element.in_memory()) {
+                true  => Some(elements.iter().map(Ty::ir_type).collect()),
+                false => None,
+            },
+            ref ty if ty.in_memory() => None,
+            ref ty => Some(vec![ty.ir_type()]),
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Ty::Scalar(ty) => write!(f, "{}", ty),
+            Ty::Struct(ref name) | Ty::Enum(ref name) => write!(f, "{}", name),
+            Ty::Array(ref element, length) => write!(f, "[{}; {}]", element, length),
+            Ty::Ref(ref ty, false) => write!(f, "&{}", ty),
+            Ty::Ref(ref ty, true) => write!(f, "&mut {}", ty),
+            Ty::Slice(ref element, false) => write!(f, "&[{}]", element),
+            Ty::Slice(ref element, true) => write!(f, "&mut [{}]", element),
+            Ty::Tuple(ref elements) => {
+                let elements: Vec<String> = elements.iter().map(Ty::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            },
+        }
+    }
+}
+
+/// The type of an enum's tag, at its start.
+///
+const TAG: Type = Type::U32;
+
+/// Every struct's and enum's layout, by name.
+///
+#[derive (Default)]
+struct Layouts {
+    structs: HashMap<String, Struct>,
+    enums: HashMap<String, Enum>,
+}
+
+impl Layouts {
+    /// The size and alignment of a value of type `ty`.
+    ///
+    fn size_align(&self, ty: &Ty) -> (u32, u32) {
+        match *ty {
+            Ty::Scalar(ty) => (ty.size(), ty.size()),
+            Ty::Struct(ref name) => (self.structs[name].size, self.structs[name].align),
+            Ty::Enum(ref name) => (self.enums[name].size, self.enums[name].align),
+            Ty::Array(ref element, length) => {
+                let (size, align) = self.size_align(element);
+                (size * length, align)
+            },
+            Ty::Ref(..) => (Type::Ptr.size(), Type::Ptr.size()),
+            // The address of the first element, then the length.
+            Ty::Slice(..) => (Type::Ptr.size() + Type::U64.size(), Type::Ptr.size()),
+            Ty::Tuple(ref elements) => {
+                let (_, size, align) = self.tuple(elements);
+                (size.next_multiple_of(align), align)
+            },
+        }
+    }
+
+    /// Lay out a tuple of `elements`, as `place()` does a struct's fields,
+    /// naming them by their index.
+    ///
+    fn tuple(&self, elements: &[Ty]) -> (Vec<Field>, u32, u32) {
+        place(self, elements.iter().cloned().enumerate()
+            .map(|(index, element)| (index.to_string(), element))
+            .collect())
+    }
+
+    /// Whether a value of type `ty` holds a reference anywhere, and so has no
+    /// zero value.
+    ///
+    fn has_reference(&self, ty: &Ty) -> bool {
+        match *ty {
+            Ty::Scalar(_) => false,
+            Ty::Struct(ref name) => self.structs[name].fields.iter()
+                .any(|field| self.has_reference(&field.ty)),
+            Ty::Enum(ref name) => self.enums[name].variants.iter()
+                .flat_map(|variant| &variant.fields)
+                .any(|field| self.has_reference(&field.ty)),
+            Ty::Array(ref element, _) => self.has_reference(element),
+            Ty::Ref(..) | Ty::Slice(..) => true,
+            Ty::Tuple(ref elements) => elements.iter().any(|element| self.has_reference(element)),
+        }
+    }
+
+    /// Whether a reference could be stored through a value of type `ty`: if
+    /// it holds a `&mut` reference to something holding a reference.
+    ///
+    fn writes_reference(&self, ty: &Ty) -> bool {
+        match *ty {
+            Ty::Scalar(_) => false,
+            Ty::Struct(ref name) => self.structs[name].fields.iter()
+                .any(|field| self.writes_reference(&field.ty)),
+            Ty::Enum(ref name) => self.enums[name].variants.iter()
+                .flat_map(|variant| &variant.fields)
+                .any(|field| self.writes_reference(&field.ty)),
+            Ty::Array(ref element, _) => self.writes_reference(element),
+            Ty::Ref(ref referent, mutable) | Ty::Slice(ref referent, mutable) => {
+                mutable && self.has_reference(referent) || self.writes_reference(referent)
+            },
+            Ty::Tuple(ref elements) => {
+                elements.iter().any(|element| self.writes_reference(element))
+            },
+        }
+    }
+
+    /// The type of an array of `length` `element`s, if it's small enough
+    /// that its size fits a `u32`.
+    ///
+    fn array(&self, element: Ty, length: u64) -> Result<Ty, String> {
+        let (size, _) = self.size_align(&element);
+        match size as u64 * length <= u32::MAX as u64 && length <= u32::MAX as u64 {
+            true  => Ok(Ty::Array(Box::new(element), length as u32)),
+            false => Err(format!("array type `[{}; {}]` is too big", element, length)),
+        }
+    }
+}
+
+/// A struct's layout.
+///
+struct Struct {
+    fields: Vec<Field>,
+    size: u32,
+    align: u32,
+}
+
+struct Field {
+    name: String,
+    ty: Ty,
+    /// From the start of the struct or enum, in bytes.
+    offset: u32,
+}
+
+/// An enum's layout.
+///
+struct Enum {
+    variants: Vec<Variant>,
+    size: u32,
+    align: u32,
+}
+
+/// A variant's fields are named by their index.
+///
+struct Variant {
+    name: String,
+    fields: Vec<Field>,
+}
+
+/// A struct or enum declaration, while laying them out.
+///
+#[derive (Clone, Copy)]
+enum Declaration<'t> {
+    Struct(&'t syntax::structure::Syntax),
+    Enum(&'t syntax::enumeration::Syntax),
+}
+
+/// A function's parameter types and return type, if it returns anything.
+///
+struct Signature {
+    parameters: Vec<Ty>,
+    return_type: Option<Ty>,
+}
+
+/// Lower `tree`, where `overflow` is what `+`, `-` and `*` do on integers
+/// that overflow: `Wrap` or `Trap`.
+///
+pub fn lower(tree: &Tree, overflow: Overflow) -> Result<Module, String> {
+    let layouts = layouts(tree)?;
+
+    // Functions can be called before they're defined, so first collect all
+    // their signatures.
+    let mut signatures = HashMap::new();
+    for function in &tree.functions {
+        let name = &function.signature.name.string;
+        if is_built_in(name) {
+            return Err(format!("`{}` is built in and can't be defined", name));
+        }
+
+        let signature = signature(&layouts, &function.signature).map_err(
+            |message| format!("in function `{}`: {}", name, message)
+        )?;
+        if name == "main" && signature.return_type.as_ref()
+            .is_some_and(|ty| *ty != Ty::Scalar(Type::I32)) {
+            return Err("`main` must return i32, if anything".to_string());
+        }
+        if signatures.insert(name.clone(), signature).is_some() {
+            return Err(format!("function `{}` defined twice", name));
+        }
+    }
+
+    let mut functions = Vec::new();
+    for function in &tree.functions {
+        functions.push(Lowerer::new(&layouts, &signatures, overflow).lower(function)?);
+    }
+
+    Ok(Module {
+        functions,
+    })
+}
+
+/// Lay out every struct and enum declared.
+///
+fn layouts(tree: &Tree) -> Result<Layouts, String> {
+    let declarations: Vec<(&str, Declaration)> = tree.structs.iter()
+        .map(|declaration| (declaration.name.string.as_str(), Declaration::Struct(declaration)))
+        .chain(tree.enums.iter().map(
+            |declaration| (declaration.name.string.as_str(), Declaration::Enum(declaration))
+        ))
+        .collect();
+
+    let mut declared = HashMap::new();
+    for &(name, declaration) in &declarations {
+        if named_type(name).is_some() {
+            return Err(format!("`{}` is a built-in type and can't be defined", name));
+        }
+        if declared.insert(name, declaration).is_some() {
+            return Err(format!("{} `{}` defined twice", kind(declaration), name));
+        }
+    }
+
+    let mut layouts = Layouts::default();
+    for &(name, _) in &declarations {
+        layout(name, &declared, &mut layouts, &mut Vec::new())?;
+    }
+    Ok(layouts)
+}
+
+fn kind(declaration: Declaration) -> &'static str {
+    match declaration {
+        Declaration::Struct(_) => "struct",
+        Declaration::Enum(_) => "enum",
+    }
+}
+
+/// Lay out the struct or enum `name`, after any it contains, unless that's
+/// been done already. `enclosing` are those whose layouts are waiting on this
+/// one, which can't contain any of them.
+///
+fn layout(
+    name: &str,
+    declared: &HashMap<&str, Declaration>,
+    layouts: &mut Layouts,
+    enclosing: &mut Vec<String>,
+) -> Result<(), String> {
+    if layouts.structs.contains_key(name) || layouts.enums.contains_key(name) {
+        return Ok(());
+    }
+    if enclosing.iter().any(|outer| outer == name) {
+        return Err(format!("{} `{}` contains itself", kind(declared[name]), name));
+    }
+    enclosing.push(name.to_string());
+
+    match declared[name] {
+        Declaration::Struct(declaration) => {
+            let mut types = Vec::new();
+            for field in &declaration.fields {
+                let field_name = &field.name.string;
+                if types.iter().any(|(earlier, _)| earlier == field_name) {
+                    return Err(format!(
+                        "struct `{}` has two fields named `{}`", name, field_name,
+                    ));
+                }
+
+                let ty = field_type(&field.ty, declared, layouts, enclosing)?.ok_or_else(
+                    || format!(
+                        "unknown type `{}` of field `{}` of struct `{}`",
+                        field.ty, field_name, name,
+                    )
+                )?;
+                types.push((field_name.clone(), ty));
+            }
+
+            let (fields, size, align) = place(layouts, types);
+            layouts.structs.insert(name.to_string(), Struct {
+                fields,
+                size: size.next_multiple_of(align),
+                align,
+            });
+        },
+        Declaration::Enum(declaration) => {
+            if declaration.variants.is_empty() {
+                return Err(format!("enum `{}` has no variants", name));
+            }
+
+            // Each variant is laid out from 0, then moved past the tag.
+            let mut variants: Vec<Variant> = Vec::new();
+            let mut payload_size = 0;
+            let mut payload_align = 1;
+            for variant in &declaration.variants {
+                let variant_name = &variant.name.string;
+                if variants.iter().any(|earlier| &earlier.name == variant_name) {
+                    return Err(format!(
+                        "enum `{}` has two variants named `{}`", name, variant_name,
+                    ));
+                }
+
+                let mut types = Vec::new();
+                for (index, field) in variant.fields.iter().enumerate() {
+                    let ty = field_type(field, declared, layouts, enclosing)?.ok_or_else(
+                        || format!(
+                            "unknown type `{}` in variant `{}` of enum `{}`",
+                            field, variant_name, name,
+                        )
+                    )?;
+                    types.push((index.to_string(), ty));
+                }
+
+                let (fields, size, align) = place(layouts, types);
+                payload_size = payload_size.max(size);
+                payload_align = payload_align.max(align);
+                variants.push(Variant {
+                    name: variant_name.clone(),
+                    fields,
+                });
+            }
+
+            let payload = TAG.size().next_multiple_of(payload_align);
+            for field in variants.iter_mut().flat_map(|variant| &mut variant.fields) {
+                field.offset += payload;
+            }
+            let align = payload_align.max(TAG.size());
+            layouts.enums.insert(name.to_string(), Enum {
+                variants,
+                size: (payload + payload_size).next_multiple_of(align),
+                align,
+            });
+        },
+    }
+
+    enclosing.pop();
+    Ok(())
+}
+
+/// The type of a field of type `ty`, laying out any struct or enum in it
+/// first; `None` if there's no such type.
+///
+fn field_type(
+    ty: &syntax::ty::Syntax,
+    declared: &HashMap<&str, Declaration>,
+    layouts: &mut Layouts,
+    enclosing: &mut Vec<String>,
+) -> Result<Option<Ty>, String> {
+    let type_name = match *ty {
+        syntax::ty::Syntax::Named(ref name) => &name.string,
+        syntax::ty::Syntax::Array(ref element, length) => {
+            return match field_type(element, declared, layouts, enclosing)? {
+                Some(element) => layouts.array(element, length).map(Some),
+                None => Ok(None),
+            };
+        },
+        // What a reference refers to needn't be laid out yet, so a struct can
+        // refer to another of its own type.
+        syntax::ty::Syntax::Reference(mutable, ref ty) => {
+            let (ty, slice) = match **ty {
+                syntax::ty::Syntax::Slice(ref element) => (&**element, true),
+                ref ty => (ty, false),
+            };
+            let referent = match *ty {
+                syntax::ty::Syntax::Named(ref name) => match declared.get(name.string.as_str()) {
+                    Some(Declaration::Struct(_)) => Some(Ty::Struct(name.string.clone())),
+                    Some(Declaration::Enum(_)) => Some(Ty::Enum(name.string.clone())),
+                    None => field_type(ty, declared, layouts, enclosing)?,
+                },
+                _ => field_type(ty, declared, layouts, enclosing)?,
+            };
+            return Ok(referent.map(|referent| match slice {
+                true  => Ty::Slice(Box::new(referent), mutable),
+                false => Ty::Ref(Box::new(referent), mutable),
+            }));
+        },
+        syntax::ty::Syntax::Slice(_) => return Err(format!(
+            "slice type `{}` must be behind a reference", ty,
+        )),
+        syntax::ty::Syntax::Tuple(ref elements) => {
+            let mut types = Vec::new();
+            for element in elements {
+                match field_type(element, declared, layouts, enclosing)? {
+                    Some(element) => types.push(element),
+                    None => return Ok(None),
+                }
+            }
+            return Ok(Some(Ty::Tuple(types)));
+        },
+    };
+    if let Some(ty) = named_type(type_name) {
+        return Ok(Some(Ty::Scalar(ty)));
+    }
+    Ok(match declared.get(type_name.as_str()) {
+        Some(declaration) => {
+            layout(type_name, declared, layouts, enclosing)?;
+            Some(match *declaration {
+                Declaration::Struct(_) => Ty::Struct(type_name.to_string()),
+                Declaration::Enum(_) => Ty::Enum(type_name.to_string()),
+            })
+        },
+        None => None,
+    })
+}
+
+/// Place fields of these names and types in order, each aligned to its
+/// size; return them with the end of the last, and the largest alignment.
+///
+fn place(layouts: &Layouts, types: Vec<(String, Ty)>) -> (Vec<Field>, u32, u32) {
+    let mut fields = Vec::new();
+    let mut size: u32 = 0;
+    let mut align = 1;
+    for (name, ty) in types {
+        let (field_size, field_align) = layouts.size_align(&ty);
+        let offset = size.next_multiple_of(field_align);
+        fields.push(Field {
+            name,
+            ty,
+            offset,
+        });
+        size = offset + field_size;
+        align = align.max(field_align);
+    }
+    (fields, size, align)
+}
+
+fn signature(
+    layouts: &Layouts,
+    signature: &syntax::signature::Syntax,
+) -> Result<Signature, String> {
+    let mut parameters = Vec::new();
+    for parameter in &signature.parameters {
+        let parameter = downcast::<syntax::parameter::Syntax>(&**parameter)
+            .ok_or("function parameter is not a parameter")?;
+        parameters.push(type_named(layouts, parameter.ty.as_ref())?);
+    }
+
+    let return_type = match signature.return_type {
+        Some(ref ty) => Some(type_named(layouts, Some(ty))?),
+        None => None,
+    };
+    if let Some(ref return_type) = return_type {
+        if layouts.has_reference(return_type) &&
+            !parameters.iter().any(|parameter| layouts.has_reference(parameter)) {
+            return Err(format!(
+                "returns {} but takes no reference for it to refer to",
+                return_type,
+            ));
+        }
+    }
+
+    Ok(Signature {
+        parameters,
+        return_type,
+    })
+}
+
+/// The type a parameter, return or local is given; `i32` if none.
+///
+fn type_named(
+    layouts: &Layouts,
+    ty: Option<&syntax::ty::Syntax>,
+) -> Result<Ty, String> {
+    let name = match ty {
+        Some(syntax::ty::Syntax::Named(name)) => &name.string,
+        Some(syntax::ty::Syntax::Array(element, length)) => {
+            let element = type_named(layouts, Some(element))?;
+            return layouts.array(element, *length);
+        },
+        Some(syntax::ty::Syntax::Reference(mutable, ty)) => return Ok(match **ty {
+            syntax::ty::Syntax::Slice(ref element) => {
+                Ty::Slice(Box::new(type_named(layouts, Some(element))?), *mutable)
+            },
+            ref ty => Ty::Ref(Box::new(type_named(layouts, Some(ty))?), *mutable),
+        }),
+        Some(ty @ syntax::ty::Syntax::Slice(_)) => return Err(format!(
+            "slice type `{}` must be behind a reference", ty,
+        )),
+        Some(syntax::ty::Syntax::Tuple(elements)) => return Ok(Ty::Tuple(
+            elements.iter().map(|element| type_named(layouts, Some(element)))
+                .collect::<Result<_, _>>()?,
+        )),
+        None => return Ok(Ty::Scalar(Type::I32)),
+    };
+    match named_type(name) {
+        Some(ty) => Ok(Ty::Scalar(ty)),
+        None if layouts.structs.contains_key(name) => Ok(Ty::Struct(name.clone())),
+        None if layouts.enums.contains_key(name) => Ok(Ty::Enum(name.clone())),
+        None => Err(format!("unknown type `{}`", name)),
+    }
+}
+
+fn named_type(name: &str) -> Option<Type> {
+    match name {
+        "isize" => Some(Type::I64),
+        "usize" => Some(Type::U64),
+        "char" => Some(Type::Char),
+        _ => Type::NUMBERS.iter().cloned().find(|ty| ty.to_string() == name),
+    }
+}
+
+fn integer_type(ty: IntegerType) -> Type {
+    match ty {
+        IntegerType::I8 => Type::I8,
+        IntegerType::I16 => Type::I16,
+        IntegerType::I32 => Type::I32,
+        IntegerType::I64 | IntegerType::ISize => Type::I64,
+        IntegerType::U8 => Type::U8,
+        IntegerType::U16 => Type::U16,
+        IntegerType::U32 => Type::U32,
+        IntegerType::U64 | IntegerType::USize => Type::U64,
+    }
+}
+
+/// The array literal `expression` is, if it is one.
+///
+fn array_literal(expression: &dyn syntax::Syntax) -> Option<&syntax::array::Syntax> {
+    if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+        return array_literal(&*expression.value);
+    }
+    downcast::<syntax::array::Syntax>(expression)
+}
+
+/// The tuple literal `expression` is, if it is one.
+///
+fn tuple_literal(expression: &dyn syntax::Syntax) -> Option<&syntax::tuple::Syntax> {
+    if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+        return tuple_literal(&*expression.value);
+    }
+    downcast::<syntax::tuple::Syntax>(expression)
+}
+
+/// The call `expression` is, if it is one.
+///
+fn call_of(expression: &dyn syntax::Syntax) -> Option<&syntax::call::Syntax> {
+    if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+        return call_of(&*expression.value);
+    }
+    downcast::<syntax::call::Syntax>(expression)
+}
+
+/// Add the name of every local in `node` whose address is taken, with `&a`
+/// or `&mut a`, to `names`.
+///
+fn addressed(node: &dyn syntax::Syntax, names: &mut HashSet<String>) {
+    if let Some(statement) = downcast::<syntax::statement::Syntax>(node) {
+        addressed(&*statement.block, names);
+    }
+    else if let Some(block) = downcast::<syntax::block::Syntax>(node) {
+        for statement in &block.statements {
+            addressed(&**statement, names);
+        }
+    }
+    else if let Some(init) = downcast::<syntax::init::Syntax>(node) {
+        addressed(&*init.rhs, names);
+    }
+    else if let Some(assign) = downcast::<syntax::assign::Syntax>(node) {
+        addressed(&*assign.place, names);
+        addressed(&*assign.rhs, names);
+    }
+    else if let Some(ret) = downcast::<syntax::ret::Syntax>(node) {
+        if let Some(ref value) = ret.value {
+            addressed(value, names);
+        }
+    }
+    else if let Some(conditional) = downcast::<syntax::conditional::Syntax>(node) {
+        addressed(&conditional.condition, names);
+        addressed(&conditional.then_block, names);
+        if let Some(ref else_block) = conditional.else_block {
+            addressed(&**else_block, names);
+        }
+    }
+    else if let Some(expression) = downcast::<syntax::expression::Syntax>(node) {
+        addressed(&*expression.value, names);
+    }
+    else if let Some(binary) = downcast::<syntax::binary::Syntax>(node) {
+        addressed(&*binary.lhs, names);
+        addressed(&*binary.rhs, names);
+    }
+    else if let Some(cast) = downcast::<syntax::cast::Syntax>(node) {
+        addressed(&*cast.value, names);
+    }
+    else if let Some(call) = downcast::<syntax::call::Syntax>(node) {
+        for argument in &call.arguments {
+            addressed(&**argument, names);
+        }
+    }
+    else if let Some(construct) = downcast::<syntax::construct::Syntax>(node) {
+        for (_, value) in &construct.fields {
+            addressed(&**value, names);
+        }
+    }
+    else if let Some(field) = downcast::<syntax::field::Syntax>(node) {
+        addressed(&*field.value, names);
+    }
+    else if let Some(variant) = downcast::<syntax::variant::Syntax>(node) {
+        for argument in &variant.arguments {
+            addressed(&**argument, names);
+        }
+    }
+    else if let Some(matching) = downcast::<syntax::matching::Syntax>(node) {
+        addressed(&matching.value, names);
+        for arm in &matching.arms {
+            addressed(&*arm.body, names);
+        }
+    }
+    else if let Some(array) = downcast::<syntax::array::Syntax>(node) {
+        for element in &array.elements {
+            addressed(&**element, names);
+        }
+    }
+    else if let Some(tuple) = downcast::<syntax::tuple::Syntax>(node) {
+        for element in &tuple.elements {
+            addressed(&**element, names);
+        }
+    }
+    else if let Some(index) = downcast::<syntax::index::Syntax>(node) {
+        addressed(&*index.value, names);
+        addressed(&*index.index, names);
+    }
+    else if let Some(reference) = downcast::<syntax::reference::Syntax>(node) {
+        match name_of(&*reference.value) {
+            Some(name) => { names.insert(name.to_string()); },
+            None => addressed(&*reference.value, names),
+        }
+    }
+    else if let Some(dereference) = downcast::<syntax::dereference::Syntax>(node) {
+        addressed(&*dereference.value, names);
+    }
+}
+
+/// The name `expression` is, if it's just a name.
+///
+fn name_of(expression: &dyn syntax::Syntax) -> Option<&str> {
+    if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+        return name_of(&*expression.value);
+    }
+    downcast::<syntax::name::Syntax>(expression).map(|name| name.string.as_str())
+}
+
+/// Whether `expression` is just a name.
+///
+fn is_name(expression: &dyn syntax::Syntax) -> bool {
+    name_of(expression).is_some()
+}
+
+/// Whether `expression` is a literal without a suffix, whose type depends on
+/// where it's used.
+///
+fn is_unsuffixed(expression: &dyn syntax::Syntax) -> bool {
+    if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+        return is_unsuffixed(&*expression.value);
+    }
+    matches!(
+        downcast::<literal::Syntax>(expression),
+        Some(literal::Syntax::Integer(Integer { ty: None, .. })) |
+        Some(literal::Syntax::Float(Float { ty: None, .. }))
+    )
+}
+
+/// `count` fields, in words.
+///
+fn field_count(count: usize) -> String {
+    match count {
+        1 => "1 field".to_string(),
+        _ => format!("{} fields", count),
+    }
+}
+
+type Variables = HashMap<String, Value>;
+
+/// The error on returning a reference into the function's own frame.
+///
+const RETURNS_LOCAL: &str = "can't return a reference to a local, which doesn't outlive the call";
+
+/// The state of lowering a single function.
+///
+struct Lowerer<'a> {
+    layouts: &'a Layouts,
+    signatures: &'a HashMap<String, Signature>,
+    /// What integer arithmetic operators do on overflow.
+    overflow: Overflow,
+
+    function: Function,
+    return_type: Option<Ty>,
+    /// Where to copy a returned struct or enum.
+    sret: Option<Value>,
+    /// The block being appended to.
+    current: Block,
+    /// The current `Value` of each local in scope.
+    variables: Variables,
+    /// The struct, enum, array or slice each `ptr` holds the address of, or
+    /// the type of each reference.
+    types: HashMap<Value, Ty>,
+    /// The locals whose addresses are taken somewhere in the function.
+    addressed: HashSet<String>,
+    /// The slot of each of those that isn't kept in memory anyway, once it's
+    /// assigned, and its type, which can't change.
+    boxes: HashMap<String, (Value, Ty)>,
+    /// The values holding addresses in the function's own frame, which
+    /// mustn't outlive it: slots, the caller's copies of arguments kept in
+    /// memory, and references into either.
+    frame: HashSet<Value>,
+    /// Whether a reference into the frame has been stored in its memory;
+    /// until one is, no reference read from memory refers into it.
+    frame_stored: bool,
+}
+
+/// Where something is in memory: `offset` bytes past `address`. It can be
+/// assigned to unless it's reached through a `&` reference.
+///
+struct Place {
+    address: Value,
+    offset: u32,
+    ty: Ty,
+    mutable: bool,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(
+        layouts: &'a Layouts,
+        signatures: &'a HashMap<String, Signature>,
+        overflow: Overflow,
+    ) -> Self {
+        Lowerer {
+            layouts,
+            signatures,
+            overflow,
+            function: Function::new(String::new(), vec![Type::I32]),
+            return_type: None,
+            sret: None,
+            current: Block(0),
+            variables: HashMap::new(),
+            types: HashMap::new(),
+            addressed: HashSet::new(),
+            boxes: HashMap::new(),
+            frame: HashSet::new(),
+            frame_stored: false,
+        }
+    }
+
+    fn lower(
+        mut self,
+        function: &syntax::function::Syntax,
+    ) -> Result<Function, String> {
+        let name = &function.signature.name.string;
+        let signature = &self.signatures[name];
+
+        addressed(&function.block, &mut self.addressed);
+
+        // A function that returns nothing returns 0 to the IR.
+        let returned = match signature.return_type {
+            Some(ref ty) => ty.returned(),
+            None => Some(vec![Type::I32]),
+        };
+        self.function = Function::new(name.clone(), returned.clone().unwrap_or(vec![Type::Ptr]));
+        self.return_type = signature.return_type.clone();
+        self.current = self.function.new_block();
+
+        if let (None, Some(ty)) = (returned, &signature.return_type) {
+            let sret = self.function.add_parameter(self.current, Type::Ptr);
+            self.typed(sret, ty);
+            self.sret = Some(sret);
+        }
+        for (parameter, ty) in function.signature.parameters.iter().zip(&signature.parameters) {
+            let parameter = &downcast::<syntax::parameter::Syntax>(&**parameter)
+                .ok_or("function parameter is not a parameter")?
+                .name.string;
+
+            let value = self.function.add_parameter(self.current, ty.ir_type());
+            self.typed(value, ty);
+            if ty.in_memory() {
+                self.frame.insert(value);
+            }
+            self.bind(parameter, value)?;
+        }
+
+        self.block(&function.block).map_err(
+            |message| format!("in function `{}`: {}", name, message)
+        )?;
+
+        // Only a function that returns nothing can fall off its end, but the
+        // end may be unreachable anyway.
+        if !self.function.is_terminated(self.current) {
+            let terminator = match self.return_type {
+                None => Terminator::Return(vec![self.constant(Type::I32, 0)]),
+                Some(_) if !self.function.reverse_post_order().contains(&self.current) => {
+                    Terminator::Trap
+                },
+                Some(_) => return Err(format!(
+                    "in function `{}`: missing return value at the end", name,
+                )),
+            };
+            self.function.terminate(self.current, terminator);
+        }
+
+        Ok(self.function)
+    }
+
+    fn block(
+        &mut self,
+        block: &syntax::block::Syntax,
+    ) -> Result<(), String> {
+        for statement in &block.statements {
+            self.statement(&**statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(
+        &mut self,
+        statement: &dyn syntax::Syntax,
+    ) -> Result<(), String> {
+        if let Some(statement) = downcast::<syntax::statement::Syntax>(statement) {
+            return self.statement(&*statement.block);
+        }
+
+        // Anything after a terminator is unreachable, but still has to go in
+        // some block.
+        if self.function.is_terminated(self.current) {
+            self.current = self.function.new_block();
+        }
+
+        if let Some(block) = downcast::<syntax::block::Syntax>(statement) {
+            self.block(block)
+        }
+        else if let Some(init) = downcast::<syntax::init::Syntax>(statement) {
+            let ty = match init.lhs_type {
+                Some(ref ty) => Some(type_named(self.layouts, Some(ty))?),
+                None => None,
+            };
+            let names = match init.lhs {
+                syntax::init::Lhs::Name(ref name) => name,
+                syntax::init::Lhs::Tuple(ref names) => {
+                    let mut bound = HashSet::new();
+                    for name in init.lhs.names() {
+                        if !bound.insert(&name.string) {
+                            return Err(format!(
+                                "`{}` is bound twice in taking a tuple apart", name.string,
+                            ));
+                        }
+                    }
+                    let values = self.elements(&*init.rhs, ty.as_ref(), "value")?;
+                    if values.len() != names.len() {
+                        return Err(format!(
+                            "can't take {} elements apart into {} names",
+                            values.len(), names.len(),
+                        ));
+                    }
+                    for (name, value) in names.iter().zip(values) {
+                        if let Some(ref name) = *name {
+                            self.bind(&name.string, value)?;
+                        }
+                    }
+                    return Ok(());
+                },
+            };
+            let value = self.owned(&*init.rhs, ty.as_ref())?;
+            if let Some(ref ty) = ty {
+                self.expect_ty(value, ty, &format!("value of `{}`", names.string))?;
+            }
+            self.bind(&names.string, value)?;
+            Ok(())
+        }
+        else if let Some(assign) = downcast::<syntax::assign::Syntax>(statement) {
+            let (place, what) = if let Some(field) =
+                downcast::<syntax::field::Syntax>(&*assign.place) {
+                (self.field(field)?, format!("value of `{}`", field.name.string))
+            }
+            else if let Some(index) = downcast::<syntax::index::Syntax>(&*assign.place) {
+                (self.index(index)?, "value of element".to_string())
+            }
+            else if let Some(dereference) =
+                downcast::<syntax::dereference::Syntax>(&*assign.place) {
+                (self.dereference(dereference)?, "value".to_string())
+            }
+            else {
+                return Err(
+                    "only a field, an element or a dereference can be assigned to".to_string()
+                );
+            };
+            if !place.mutable {
+                return Err("can't assign through a `&` reference".to_string());
+            }
+            let value = self.expression_as(&*assign.rhs, &place.ty)?;
+            self.expect_ty(value, &place.ty, &what)?;
+            if self.escapes(value) && !self.frame.contains(&place.address) {
+                return Err(
+                    "can't store a reference to a local where it would outlive it".to_string()
+                );
+            }
+            self.store(place.address, place.offset, value, &place.ty);
+            Ok(())
+        }
+        else if let Some(ret) = downcast::<syntax::ret::Syntax>(statement) {
+            let return_type = match (ret.value.as_ref(), self.return_type.clone()) {
+                (None, None) => {
+                    let zero = self.constant(Type::I32, 0);
+                    self.function.terminate(self.current, Terminator::Return(vec![zero]));
+                    return Ok(());
+                },
+                (Some(_), None) => {
+                    return Err("unexpected return value, as the function has no return type"
+                        .to_string());
+                },
+                (None, Some(ty)) => return Err(format!("missing return value, of type {}", ty)),
+                (Some(_), Some(ty)) => ty,
+            };
+            let values = match (ret.value.as_ref(), self.sret) {
+                // A tuple returned in registers is never stored.
+                (Some(value), None) if matches!(return_type, Ty::Tuple(_)) => {
+                    self.elements(value, Some(&return_type), "returned value")?
+                },
+                (Some(value), None) => {
+                    let value = self.expression_as(value, &return_type)?;
+                    self.expect_ty(value, &return_type, "returned value")?;
+                    vec![value]
+                },
+                (Some(value), Some(sret)) => {
+                    let value = self.expression_as(value, &return_type)?;
+                    self.expect_ty(value, &return_type, "returned value")?;
+                    if self.escapes(value) {
+                        return Err(RETURNS_LOCAL.to_string());
+                    }
+                    self.copy(sret, 0, value, 0, &return_type);
+                    vec![sret]
+                },
+                (None, _) => unreachable!("a value is returned"),
+            };
+            if values.iter().any(|&value| self.escapes(value)) {
+                return Err(RETURNS_LOCAL.to_string());
+            }
+            self.function.terminate(self.current, Terminator::Return(values));
+            Ok(())
+        }
+        else if let Some(conditional) = downcast::<syntax::conditional::Syntax>(statement) {
+            self.conditional(conditional)
+        }
+        else if let Some(call) = downcast::<syntax::call::Syntax>(statement) {
+            self.call(call, None).map(|_| ())
+        }
+        else if let Some(matching) = downcast::<syntax::matching::Syntax>(statement) {
+            self.matching(matching, None, false).map(|_| ())
+        }
+        else {
+            Err(format!("unsupported statement: {:?}", statement))
+        }
+    }
+
+    fn conditional(
+        &mut self,
+        conditional: &syntax::conditional::Syntax,
+    ) -> Result<(), String> {
+        let condition = self.expression(&conditional.condition, Some(Type::I32))?;
+        self.expect(condition, Type::I32, "condition")?;
+
+        let start = self.current;
+        let before = self.variables.clone();
+
+        // Each block that will branch to the join, and the locals along that
+        // edge.
+        let mut incoming = Vec::new();
+
+        // The join block is made last, so blocks are numbered in source
+        // order; until then, an `if` without `else` targets `then_block`
+        // twice, as a placeholder.
+        let then_block = self.function.new_block();
+        let else_block = match conditional.else_block {
+            Some(_) => self.function.new_block(),
+            None => then_block,
+        };
+        self.function.terminate(start, Terminator::CondBranch(
+            condition, Target::new(then_block), Target::new(else_block),
+        ));
+
+        self.current = then_block;
+        self.block(&conditional.then_block)?;
+        if !self.function.is_terminated(self.current) {
+            incoming.push((self.current, self.variables.clone()));
+        }
+
+        if let Some(ref else_syntax) = conditional.else_block {
+            self.variables = before.clone();
+            self.current = else_block;
+            self.statement(&**else_syntax)?;
+            if !self.function.is_terminated(self.current) {
+                incoming.push((self.current, self.variables.clone()));
+            }
+        }
+
+        let join = self.function.new_block();
+        for &(predecessor, _) in &incoming {
+            self.function.terminate(predecessor, Terminator::Branch(Target::new(join)));
+        }
+        if conditional.else_block.is_none() {
+            let terminator = self.function.block_mut(start).terminator.as_mut().unwrap();
+            terminator.targets_mut()[1].block = join;
+            incoming.push((start, before.clone()));
+        }
+
+        self.current = join;
+        self.join(join, incoming, before)
+    }
+
+    /// Set up the locals at `join`, given the locals along each edge into it;
+    /// add block parameters, and branch arguments to match, where they differ.
+    ///
+    fn join(
+        &mut self,
+        join: Block,
+        incoming: Vec<(Block, Variables)>,
+        before: Variables,
+    ) -> Result<(), String> {
+        // If nothing reaches the join, what follows is unreachable anyway;
+        // keep the locals from before so it can still be lowered.
+        if incoming.is_empty() {
+            self.variables = before;
+            return Ok(());
+        }
+
+        // Sort for a deterministic order of parameters.
+        let mut names: Vec<&String> = incoming[0].1.keys().filter(|name| {
+            incoming.iter().all(|(_, variables)| variables.contains_key(*name))
+        }).collect();
+        names.sort();
+
+        let mut variables = HashMap::new();
+        for name in names {
+            let first = incoming[0].1[name];
+            if incoming.iter().all(|(_, variables)| variables[name] == first) {
+                variables.insert(name.clone(), first);
+                continue;
+            }
+
+            let first_type = self.ty(first);
+            for (_, predecessor_variables) in &incoming {
+                let found = self.ty(predecessor_variables[name]);
+                if found != first_type {
+                    return Err(format!(
+                        "`{}` has type {} on one branch but {} on another",
+                        name, first_type, found,
+                    ));
+                }
+            }
+
+            let parameter = self.function.add_parameter(join, first_type.ir_type());
+            self.typed(parameter, &first_type);
+            if incoming.iter().any(|(_, variables)| self.frame.contains(&variables[name])) {
+                self.frame.insert(parameter);
+            }
+            for &(predecessor, ref predecessor_variables) in &incoming {
+                let terminator = self.function.block_mut(predecessor)
+                    .terminator.as_mut().unwrap();
+                for target in terminator.targets_mut() {
+                    if target.block == join {
+                        target.arguments.push(predecessor_variables[name]);
+                    }
+                }
+            }
+            variables.insert(name.clone(), parameter);
+        }
+
+        self.variables = variables;
+        Ok(())
+    }
+
+    /// Lower an expression, where a value of type `expected` is wanted if
+    /// that's known; return the `Value` holding its result.
+    ///
+    /// `expected` only gives unsuffixed literals their type; the caller still
+    /// has to check the result's.
+    ///
+    fn expression(
+        &mut self,
+        expression: &dyn syntax::Syntax,
+        expected: Option<Type>,
+    ) -> Result<Value, String> {
+        if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+            self.expression(&*expression.value, expected)
+        }
+        else if let Some(literal) = downcast::<literal::Syntax>(expression) {
+            Ok(match *literal {
+                literal::Syntax::Integer(Integer { ty, value }) => {
+                    let ty = match (ty, expected) {
+                        (Some(ty), _) => integer_type(ty),
+                        (None, Some(expected)) if expected.is_integer() => expected,
+                        (None, _) => Type::I32,
+                    };
+                    // The lexer has checked it fits a `u64`, or its suffix.
+                    if !ty.contains(value as i128) {
+                        return Err(format!(
+                            "integer literal {} is out of range for {}", value, ty,
+                        ));
+                    }
+                    self.constant(ty, value as i128)
+                },
+                literal::Syntax::Float(Float { ty, value }) => {
+                    let ty = match (ty, expected) {
+                        (Some(FloatType::F32), _) => Type::F32,
+                        (Some(FloatType::F64), _) => Type::F64,
+                        (None, Some(expected)) if expected.is_float() => expected,
+                        (None, _) => Type::F64,
+                    };
+                    match ty {
+                        Type::F32 => self.float(ty, value as f32 as f64),
+                        _ => self.float(ty, value),
+                    }
+                },
+                literal::Syntax::String(ref string) => self.function.push(
+                    self.current, Type::Str, InstructionKind::Str(string.clone()),
+                ),
+                literal::Syntax::Char(this_char) => self.constant(Type::Char, this_char as i128),
+            })
+        }
+        else if let Some(name) = downcast::<syntax::name::Syntax>(expression) {
+            if let Some((slot, ty)) = self.boxed(&name.string) {
+                return Ok(self.load(slot, 0, &ty));
+            }
+            self.variables.get(&name.string).cloned().ok_or_else(
+                || format!("use of undeclared variable `{}`", name.string)
+            )
+        }
+        else if let Some(binary) = downcast::<syntax::binary::Syntax>(expression) {
+            let op = match binary.op {
+                TokenKind::OpAdd => BinaryOp::Add,
+                TokenKind::OpSub => BinaryOp::Sub,
+                TokenKind::OpMul => BinaryOp::Mul,
+                TokenKind::OpEq => BinaryOp::Eq,
+                TokenKind::OpNe => BinaryOp::Ne,
+                TokenKind::OpLt => BinaryOp::Lt,
+                TokenKind::OpLe => BinaryOp::Le,
+                TokenKind::OpGt => BinaryOp::Gt,
+                TokenKind::OpGe => BinaryOp::Ge,
+                op => return Err(format!("unsupported binary operator: {:?}", op)),
+            };
+
+            // The operands are of the type of the result, unless this is a
+            // comparison.
+            let expected = match op.is_comparison() {
+                true  => None,
+                false => expected,
+            };
+            let (lhs, rhs) = self.operands(op, &*binary.lhs, &*binary.rhs, expected)?;
+
+            let ty = self.function.type_of(lhs);
+            let result_type = match op.is_comparison() {
+                true  => Type::I32,
+                false => ty,
+            };
+            let overflow = match op.is_comparison() || ty.is_float() {
+                true  => Overflow::Wrap,
+                false => self.overflow,
+            };
+            Ok(self.function.push(
+                self.current, result_type, InstructionKind::Binary(op, overflow, lhs, rhs),
+            ))
+        }
+        else if let Some(cast) = downcast::<syntax::cast::Syntax>(expression) {
+            let value = self.expression(&*cast.value, None)?;
+            let ty = match type_named(self.layouts, Some(&cast.ty))? {
+                Ty::Scalar(ty) => ty,
+                ty => return Err(format!("can't convert to {}", ty.describe())),
+            };
+            self.convert(value, ty, "cast operand")
+        }
+        else if let Some(call) = downcast::<syntax::call::Syntax>(expression) {
+            let name = &call.name.string;
+            if self.signatures.get(name).is_some_and(|signature| signature.return_type.is_none()) {
+                return Err(format!("`{}` returns nothing, so has no value", name));
+            }
+            self.call(call, expected)
+        }
+        else if let Some(construct) = downcast::<syntax::construct::Syntax>(expression) {
+            self.construct(construct)
+        }
+        else if let Some(variant) = downcast::<syntax::variant::Syntax>(expression) {
+            self.variant(variant)
+        }
+        else if let Some(array) = downcast::<syntax::array::Syntax>(expression) {
+            self.array(array, None)
+        }
+        else if let Some(tuple) = downcast::<syntax::tuple::Syntax>(expression) {
+            self.tuple(tuple, None)
+        }
+        else if let Some(index) = downcast::<syntax::index::Syntax>(expression) {
+            let place = self.index(index)?;
+            Ok(self.load(place.address, place.offset, &place.ty))
+        }
+        else if let Some(reference) = downcast::<syntax::reference::Syntax>(expression) {
+            let place = self.place(&*reference.value)?;
+            if reference.mutable && !place.mutable {
+                return Err("can't take a `&mut` reference through a `&` reference".to_string());
+            }
+            let ty = Ty::Ref(Box::new(place.ty), reference.mutable);
+            Ok(self.pointer(place.address, place.offset, &ty))
+        }
+        else if let Some(dereference) = downcast::<syntax::dereference::Syntax>(expression) {
+            let place = self.dereference(dereference)?;
+            Ok(self.load(place.address, place.offset, &place.ty))
+        }
+        else if let Some(matching) = downcast::<syntax::matching::Syntax>(expression) {
+            self.matching(matching, expected, true)?.ok_or_else(
+                || "a `match` that never finishes has no value".to_string()
+            )
+        }
+        else if let Some(field) = downcast::<syntax::field::Syntax>(expression) {
+            let place = self.field(field)?;
+            Ok(self.load(place.address, place.offset, &place.ty))
+        }
+        else {
+            Err(format!("unsupported expression: {:?}", expression))
+        }
+    }
+
+    /// Lower an expression where a value of type `ty` is wanted, as
+    /// `expression()` does, but giving the elements of an array or tuple
+    /// literal their types too, and converting to `ty` as `coerce()` does.
+    ///
+    fn expression_as(
+        &mut self,
+        expression: &dyn syntax::Syntax,
+        ty: &Ty,
+    ) -> Result<Value, String> {
+        if let (Some(array), Ty::Array(element, _)) = (array_literal(expression), ty) {
+            return self.array(array, Some(element));
+        }
+        let value = match (tuple_literal(expression), ty) {
+            (Some(tuple), Ty::Tuple(elements)) if tuple.elements.len() == elements.len() => {
+                return self.tuple(tuple, Some(elements));
+            },
+            _ => self.expression(expression, ty.scalar())?,
+        };
+        Ok(self.coerce(value, ty))
+    }
+
+    /// Convert `value` to `ty` where that's done implicitly: a `&mut`
+    /// reference or slice to a `&` one, or a reference to an array to a slice
+    /// of it. Anything else is left for the caller to check.
+    ///
+    fn coerce(&mut self, value: Value, ty: &Ty) -> Value {
+        match (self.ty(value), ty) {
+            (Ty::Ref(found, true), Ty::Ref(wanted, false)) if found == *wanted => {
+                self.pointer(value, 0, ty)
+            },
+            (Ty::Slice(found, true), Ty::Slice(wanted, false)) if found == *wanted => {
+                self.load(value, 0, ty)
+            },
+            (Ty::Ref(found, mutable), &Ty::Slice(ref wanted, wanted_mutable))
+                if mutable || !wanted_mutable => match *found {
+                Ty::Array(ref element, length) if element == wanted => {
+                    let slice = self.slot(ty);
+                    self.store(slice, 0, value, &Ty::Scalar(Type::Ptr));
+                    let length = self.constant(Type::U64, length as i128);
+                    self.store(slice, Type::Ptr.size(), length, &Ty::Scalar(Type::U64));
+                    slice
+                },
+                _ => value,
+            },
+            _ => value,
+        }
+    }
+
+    /// Lower an expression as `expression_as()` does, if a type is expected,
+    /// but if it's a local kept in memory, copy it, so that the result can be
+    /// changed without changing the local.
+    ///
+    fn owned(
+        &mut self,
+        expression: &dyn syntax::Syntax,
+        expected: Option<&Ty>,
+    ) -> Result<Value, String> {
+        let value = match expected {
+            Some(ty) => self.expression_as(expression, ty)?,
+            None => self.expression(expression, None)?,
+        };
+        let ty = self.ty(value);
+        match ty.in_memory() && is_name(expression) {
+            true => {
+                let copy = self.slot(&ty);
+                self.copy(copy, 0, value, 0, &ty);
+                Ok(copy)
+            },
+            false => Ok(value),
+        }
+    }
+
+    /// Lower a struct literal into a new slot.
+    ///
+    fn construct(&mut self, construct: &syntax::construct::Syntax) -> Result<Value, String> {
+        let name = &construct.name.string;
+        let layout = self.layouts.structs.get(name).ok_or_else(
+            || format!("unknown struct `{}`", name)
+        )?;
+        let address = self.slot(&Ty::Struct(name.clone()));
+
+        let mut given: Vec<&str> = Vec::new();
+        for (field_name, value) in &construct.fields {
+            let field_name = field_name.string.as_str();
+            let field = layout.fields.iter().find(|field| field.name == field_name).ok_or_else(
+                || format!("struct `{}` has no field `{}`", name, field_name)
+            )?;
+            if given.contains(&field_name) {
+                return Err(format!("field `{}` of `{}` given twice", field_name, name));
+            }
+            given.push(field_name);
+
+            let value = self.expression_as(&**value, &field.ty)?;
+            self.expect_ty(value, &field.ty, &format!("value of `{}`", field_name))?;
+            self.store(address, field.offset, value, &field.ty);
+        }
+
+        if let Some(missing) = layout.fields.iter().find(|field| !given.contains(&&*field.name)) {
+            return Err(format!("missing field `{}` of `{}`", missing.name, name));
+        }
+        Ok(address)
+    }
+
+    /// Lower an enum variant into a new slot.
+    ///
+    fn variant(&mut self, variant: &syntax::variant::Syntax) -> Result<Value, String> {
+        let name = &variant.name.string;
+        let (tag, found) = self.find_variant(name, &variant.variant.string)?;
+        if variant.arguments.len() != found.fields.len() {
+            return Err(format!(
+                "`{}::{}` has {} but was given {}",
+                name, found.name, field_count(found.fields.len()), variant.arguments.len(),
+            ));
+        }
+
+        let address = self.slot(&Ty::Enum(name.clone()));
+        let tag = self.constant(TAG, tag as i128);
+        self.store(address, 0, tag, &Ty::Scalar(TAG));
+        for (field, argument) in found.fields.iter().zip(&variant.arguments) {
+            let value = self.expression_as(&**argument, &field.ty)?;
+            self.expect_ty(value, &field.ty, &format!(
+                "field {} of `{}::{}`", field.name, name, found.name,
+            ))?;
+            self.store(address, field.offset, value, &field.ty);
+        }
+        Ok(address)
+    }
+
+    /// Find the variant `variant_name` of the enum `name`, and its tag.
+    ///
+    fn find_variant(
+        &self,
+        name: &str,
+        variant_name: &str,
+    ) -> Result<(usize, &'a Variant), String> {
+        let layouts = self.layouts;
+        let layout = layouts.enums.get(name).ok_or_else(
+            || format!("unknown enum `{}`", name)
+        )?;
+        layout.variants.iter().enumerate().find(|&(_, found)| found.name == variant_name)
+            .ok_or_else(|| format!("enum `{}` has no variant `{}`", name, variant_name))
+    }
+
+    /// Lower a `match`. If its value is `wanted`, each arm must be an
+    /// expression, and of the same type, and it's `None` only if no arm
+    /// finishes; `expected` is the type expected of it.
+    ///
+    fn matching(
+        &mut self,
+        matching: &syntax::matching::Syntax,
+        expected: Option<Type>,
+        wanted: bool,
+    ) -> Result<Option<Value>, String> {
+        let scrutinee = self.expression(&matching.value, None)?;
+        let ty = self.ty(scrutinee);
+        let count = match ty {
+            Ty::Scalar(Type::Char) => 0x110000 - 0x800,
+            Ty::Scalar(scalar) if scalar.is_integer() => 1 << scalar.bits(),
+            Ty::Enum(ref name) => self.layouts.enums[name].variants.len() as i128,
+            _ => return Err(format!("can't match on {}", ty.describe())),
+        };
+
+        // The arm each value goes to is the first whose pattern matches it;
+        // any after a `_`, or once every value has one, can't be reached.
+        let mut cases: Vec<(i128, usize)> = Vec::new();
+        let mut wildcard = None;
+        for (index, arm) in matching.arms.iter().enumerate() {
+            let key = self.pattern(&arm.pattern, &ty)?;
+            if wildcard.is_some() || cases.len() as i128 == count {
+                continue;
+            }
+            match key {
+                None => wildcard = Some(index),
+                Some(key) if cases.iter().all(|&(case, _)| case != key) => {
+                    cases.push((key, index));
+                },
+                Some(_) => (),
+            }
+        }
+
+        // Every value not in a case goes to the default: the `_` arm, or if
+        // there's none, the last case, which is then all that's left.
+        let default = match wildcard {
+            Some(arm) => arm,
+            None if cases.len() as i128 == count => cases.pop().unwrap().1,
+            None => return Err(match ty {
+                Ty::Enum(ref name) => {
+                    let missing = self.layouts.enums[name].variants.iter().enumerate()
+                        .find(|&(tag, _)| cases.iter().all(|&(case, _)| case != tag as i128))
+                        .unwrap().1;
+                    format!(
+                        "match on {} isn't exhaustive: `{}::{}` isn't covered",
+                        name, name, missing.name,
+                    )
+                },
+                _ => format!("match on {} isn't exhaustive; add a `_` arm", ty),
+            }),
+        };
+
+        let start = self.current;
+        let before = self.variables.clone();
+        let blocks: Vec<Block> = matching.arms.iter().map(|_| self.function.new_block()).collect();
+        let terminator = match cases.is_empty() {
+            true => Terminator::Branch(Target::new(blocks[default])),
+            false => {
+                let value = match ty {
+                    Ty::Enum(_) => self.load(scrutinee, 0, &Ty::Scalar(TAG)),
+                    _ => scrutinee,
+                };
+                Terminator::Switch(
+                    value,
+                    cases.iter().map(|&(key, arm)| (key, Target::new(blocks[arm]))).collect(),
+                    Target::new(blocks[default]),
+                )
+            },
+        };
+        self.function.terminate(start, terminator);
+
+        // Each block that will branch to the join, the locals along that edge,
+        // and the arm's value, if wanted.
+        let mut incoming = Vec::new();
+        for (arm, &block) in matching.arms.iter().zip(&blocks) {
+            self.variables = before.clone();
+            self.current = block;
+
+            if let syntax::pattern::Syntax::Variant { ref variant, ref fields, .. } = arm.pattern {
+                let (_, found) = self.find_variant(&ty.to_string(), &variant.string)?;
+                for (binding, field) in fields.iter().zip(&found.fields) {
+                    if let Some(ref binding) = *binding {
+                        let value = self.load(scrutinee, field.offset, &field.ty);
+                        self.bind(&binding.string, value)?;
+                    }
+                }
+            }
+
+            let value = match downcast::<syntax::block::Syntax>(&*arm.body) {
+                Some(_) if wanted => return Err(
+                    "each arm of a `match` used as a value must be an expression".to_string()
+                ),
+                Some(body) => {
+                    self.block(body)?;
+                    None
+                },
+                None if wanted => {
+                    Some(self.owned(&*arm.body, expected.map(Ty::Scalar).as_ref())?)
+                },
+                // An arm's value isn't wanted, so it may call a function
+                // that returns nothing.
+                None => match call_of(&*arm.body) {
+                    Some(call) => {
+                        self.call(call, None)?;
+                        None
+                    },
+                    None => {
+                        self.expression(&*arm.body, None)?;
+                        None
+                    },
+                },
+            };
+            if !self.function.is_terminated(self.current) {
+                incoming.push((self.current, self.variables.clone(), value));
+            }
+        }
+
+        let join = self.function.new_block();
+        let mut result = None;
+        if let Some(&(_, _, Some(first))) = incoming.first() {
+            let first_type = self.ty(first);
+            for &(_, _, value) in &incoming {
+                let found = self.ty(value.unwrap());
+                if found != first_type {
+                    return Err(format!(
+                        "match arms have different types, {} and {}", first_type, found,
+                    ));
+                }
+            }
+            let parameter = self.function.add_parameter(join, first_type.ir_type());
+            self.typed(parameter, &first_type);
+            if incoming.iter().any(|&(_, _, value)| self.frame.contains(&value.unwrap())) {
+                self.frame.insert(parameter);
+            }
+            result = Some(parameter);
+        }
+        for &(predecessor, _, value) in &incoming {
+            self.function.terminate(predecessor, Terminator::Branch(Target {
+                block: join,
+                arguments: value.into_iter().collect(),
+            }));
+        }
+
+        self.current = join;
+        let incoming = incoming.into_iter()
+            .map(|(predecessor, variables, _)| (predecessor, variables))
+            .collect();
+        self.join(join, incoming, before)?;
+        Ok(result)
+    }
+
+    /// Check a pattern can match a value of type `ty`; return the tag or
+    /// value it matches, or `None` for `_`.
+    ///
+    fn pattern(
+        &self,
+        pattern: &syntax::pattern::Syntax,
+        ty: &Ty,
+    ) -> Result<Option<i128>, String> {
+        match *pattern {
+            syntax::pattern::Syntax::Wildcard => Ok(None),
+            syntax::pattern::Syntax::Literal(ref literal) => {
+                let (found, value) = match *literal {
+                    literal::Syntax::Integer(Integer { ty: Some(found), value }) => {
+                        (integer_type(found), value as i128)
+                    },
+                    literal::Syntax::Integer(Integer { ty: None, value }) => {
+                        match ty.scalar() {
+                            Some(scalar) if scalar.is_integer() => (scalar, value as i128),
+                            _ => (Type::I32, value as i128),
+                        }
+                    },
+                    literal::Syntax::Char(this_char) => (Type::Char, this_char as i128),
+                    literal::Syntax::Float(_) | literal::Syntax::String(_) => return Err(
+                        "only integers and chars can be literal patterns".to_string()
+                    ),
+                };
+                if Ty::Scalar(found) != *ty {
+                    return Err(format!("a pattern of type {} can't match {}", found, ty));
+                }
+                if found.is_integer() && !found.contains(value) {
+                    return Err(format!("pattern {} is out of range for {}", value, found));
+                }
+                Ok(Some(value))
+            },
+            syntax::pattern::Syntax::Variant { ref name, ref variant, ref fields } => {
+                if *ty != Ty::Enum(name.string.clone()) {
+                    return Err(format!(
+                        "pattern `{}::{}` can't match {}", name.string, variant.string, ty,
+                    ));
+                }
+                let (tag, found) = self.find_variant(&name.string, &variant.string)?;
+                if fields.len() != found.fields.len() {
+                    return Err(format!(
+                        "`{}::{}` has {} but the pattern has {}",
+                        name.string, variant.string, field_count(found.fields.len()), fields.len(),
+                    ));
+                }
+                Ok(Some(tag as i128))
+            },
+        }
+    }
+
+    /// Find a field, or a tuple's element, in the memory of the struct or
+    /// tuple it's part of; one in a local isn't copied to get at its fields.
+    ///
+    fn field(&mut self, field: &syntax::field::Syntax) -> Result<Place, String> {
+        let base = self.base(&*field.value)?;
+        let name = &field.name.string;
+        let (offset, ty) = match base.ty {
+            Ty::Struct(ref struct_name) => {
+                let layout = &self.layouts.structs[struct_name];
+                let found = layout.fields.iter().find(|found| found.name == *name).ok_or_else(
+                    || format!("struct `{}` has no field `{}`", struct_name, name)
+                )?;
+                (found.offset, found.ty.clone())
+            },
+            Ty::Tuple(ref elements) => {
+                let (fields, _, _) = self.layouts.tuple(elements);
+                let found = fields.into_iter().find(|found| found.name == *name).ok_or_else(
+                    || format!("tuple {} has no element `{}`", base.ty, name)
+                )?;
+                (found.offset, found.ty)
+            },
+            ref ty => return Err(format!("{} has no fields, so no `{}`", ty, name)),
+        };
+        Ok(Place {
+            offset: base.offset + offset,
+            ty,
+            ..base
+        })
+    }
+
+    /// Find where `expression` is, as `field()`, `index()` or `dereference()`
+    /// does, if it's a field, element or dereference, or a local whose address
+    /// is taken; otherwise lower it, copying it to a new slot if it isn't kept
+    /// in memory.
+    ///
+    fn place(&mut self, expression: &dyn syntax::Syntax) -> Result<Place, String> {
+        if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+            return self.place(&*expression.value);
+        }
+        if let Some(field) = downcast::<syntax::field::Syntax>(expression) {
+            return self.field(field);
+        }
+        if let Some(index) = downcast::<syntax::index::Syntax>(expression) {
+            return self.index(index);
+        }
+        if let Some(dereference) = downcast::<syntax::dereference::Syntax>(expression) {
+            return self.dereference(dereference);
+        }
+        if let Some(name) = downcast::<syntax::name::Syntax>(expression) {
+            if let Some((address, ty)) = self.boxed(&name.string) {
+                return Ok(Place { address, offset: 0, ty, mutable: true });
+            }
+        }
+
+        let value = self.expression(expression, None)?;
+        let ty = self.ty(value);
+        if ty.in_memory() {
+            return Ok(Place { address: value, offset: 0, ty, mutable: true });
+        }
+        let (size, align) = self.layouts.size_align(&ty);
+        let address = self.function.push(
+            self.current, Type::Ptr, InstructionKind::Slot(size, align),
+        );
+        self.frame.insert(address);
+        self.store(address, 0, value, &ty);
+        Ok(Place { address, offset: 0, ty, mutable: true })
+    }
+
+    /// Whether `place()` finds `expression` where it already is, rather than
+    /// lowering it.
+    ///
+    fn is_place(&self, expression: &dyn syntax::Syntax) -> bool {
+        if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+            return self.is_place(&*expression.value);
+        }
+        if let Some(name) = downcast::<syntax::name::Syntax>(expression) {
+            return self.boxed(&name.string).is_some();
+        }
+        downcast::<syntax::field::Syntax>(expression).is_some() ||
+            downcast::<syntax::index::Syntax>(expression).is_some() ||
+            downcast::<syntax::dereference::Syntax>(expression).is_some()
+    }
+
+    /// Find the struct, array or slice that `expression` is, as `place()`
+    /// does, or that it refers to, if it's a reference to one: what a field
+    /// or element is part of.
+    ///
+    fn base(&mut self, expression: &dyn syntax::Syntax) -> Result<Place, String> {
+        // A reference that isn't in memory needn't be put there first.
+        if !self.is_place(expression) {
+            let value = self.expression(expression, None)?;
+            return Ok(match self.ty(value) {
+                Ty::Ref(ty, mutable) => Place { address: value, offset: 0, ty: *ty, mutable },
+                ty => Place { address: value, offset: 0, ty, mutable: true },
+            });
+        }
+
+        let place = self.place(expression)?;
+        match place.ty.clone() {
+            Ty::Ref(ty, mutable) => Ok(Place {
+                address: self.load(place.address, place.offset, &place.ty),
+                offset: 0,
+                ty: *ty,
+                mutable: mutable && place.mutable,
+            }),
+            _ => Ok(place),
+        }
+    }
+
+    /// Find what the reference in `dereference` refers to.
+    ///
+    fn dereference(
+        &mut self,
+        dereference: &syntax::dereference::Syntax,
+    ) -> Result<Place, String> {
+        let value = self.expression(&*dereference.value, None)?;
+        match self.ty(value) {
+            Ty::Ref(ty, mutable) => Ok(Place { address: value, offset: 0, ty: *ty, mutable }),
+            ty => Err(format!("{} can't be dereferenced", ty.describe())),
+        }
+    }
+
+    /// Make a reference of type `ty` to what's `offset` bytes past `address`,
+    /// as a new `Value`.
+    ///
+    fn pointer(&mut self, address: Value, offset: u32, ty: &Ty) -> Value {
+        let offset = self.constant(Type::U64, offset as i128);
+        let pointer = self.function.push(
+            self.current, Type::Ptr, InstructionKind::Element(address, offset, 1),
+        );
+        self.typed(pointer, ty);
+        if self.frame.contains(&address) {
+            self.frame.insert(pointer);
+        }
+        pointer
+    }
+
+    /// Find the element `index` refers to. An index into an array that isn't
+    /// a constant, or any index into a slice, is checked when the program
+    /// runs, trapping if it's out of bounds.
+    ///
+    fn index(&mut self, index: &syntax::index::Syntax) -> Result<Place, String> {
+        let base = self.base(&*index.value)?;
+        let (element, length, mutable) = match base.ty {
+            Ty::Array(ref element, length) => ((**element).clone(), Some(length), base.mutable),
+            Ty::Slice(ref element, mutable) => {
+                ((**element).clone(), None, mutable && base.mutable)
+            },
+            ref ty => return Err(format!("{} can't be indexed", ty.describe())),
+        };
+        let (size, _) = self.layouts.size_align(&element);
+
+        let value = self.expression(&*index.index, Some(Type::U64))?;
+        let found = self.function.type_of(value);
+        if !found.is_integer() {
+            return Err(format!("index must be an integer, but has type {}", found));
+        }
+
+        let (address, offset, length) = match length {
+            Some(length) => {
+                if let Some(constant) = self.constant_of(value) {
+                    if !(0..length as i128).contains(&constant) {
+                        return Err(format!(
+                            "index {} is out of bounds for {}", constant, base.ty,
+                        ));
+                    }
+                    return Ok(Place {
+                        address: base.address,
+                        offset: base.offset + constant as u32 * size,
+                        ty: element,
+                        mutable,
+                    });
+                }
+                (base.address, base.offset, self.constant(Type::U64, length as i128))
+            },
+            // A slice's length is only known when the program runs.
+            None => {
+                let address = self.load(base.address, base.offset, &Ty::Scalar(Type::Ptr));
+                let length = self.load(
+                    base.address, base.offset + Type::Ptr.size(), &Ty::Scalar(Type::U64),
+                );
+                (address, 0, length)
+            },
+        };
+
+        let value = self.convert(value, Type::U64, "index")?;
+        let in_bounds = self.function.push(
+            self.current, Type::I32, InstructionKind::Binary(
+                BinaryOp::Lt, Overflow::Wrap, value, length,
+            ),
+        );
+        let ok = self.function.new_block();
+        let trap = self.function.new_block();
+        self.function.terminate(self.current, Terminator::CondBranch(
+            in_bounds, Target::new(ok), Target::new(trap),
+        ));
+        self.function.terminate(trap, Terminator::Trap);
+        self.current = ok;
+
+        let element_address = self.function.push(
+            self.current, Type::Ptr, InstructionKind::Element(address, value, size),
+        );
+        if self.frame.contains(&address) {
+            self.frame.insert(element_address);
+        }
+        Ok(Place { address: element_address, offset, ty: element, mutable })
+    }
+
+    /// The constant `value` is, if it's made by a `Const` in the current
+    /// block.
+    ///
+    fn constant_of(&self, value: Value) -> Option<i128> {
+        self.function.block(self.current).instructions.iter().rev()
+            .find(|instruction| instruction.result == value)
+            .and_then(|instruction| match instruction.kind {
+                InstructionKind::Const(constant) => Some(constant),
+                _ => None,
+            })
+    }
+
+    /// Lower an array literal, of `element`s if given; otherwise of whatever
+    /// type the first element has.
+    ///
+    fn array(
+        &mut self,
+        array: &syntax::array::Syntax,
+        element: Option<&Ty>,
+    ) -> Result<Value, String> {
+        let mut element = element.cloned();
+        let mut values = Vec::new();
+        for value in &array.elements {
+            let value = match element {
+                Some(ref ty) => {
+                    let value = self.expression_as(&**value, ty)?;
+                    self.expect_ty(value, ty, "element")?;
+                    value
+                },
+                None => {
+                    let value = self.expression(&**value, None)?;
+                    element = Some(self.ty(value));
+                    value
+                },
+            };
+            values.push(value);
+        }
+        let element = element.ok_or("the type of an empty array can't be inferred")?;
+
+        let ty = self.layouts.array(element.clone(), values.len() as u64)?;
+        let (size, _) = self.layouts.size_align(&element);
+        let address = self.slot(&ty);
+        for (index, value) in values.into_iter().enumerate() {
+            self.store(address, index as u32 * size, value, &element);
+        }
+        Ok(address)
+    }
+
+    /// Lower a tuple literal into a new slot, of `elements` if given;
+    /// otherwise of whatever types its elements have.
+    ///
+    fn tuple(
+        &mut self,
+        tuple: &syntax::tuple::Syntax,
+        elements: Option<&Vec<Ty>>,
+    ) -> Result<Value, String> {
+        let values = self.tuple_elements(tuple, elements)?;
+        let types: Vec<Ty> = values.iter().map(|&value| self.ty(value)).collect();
+        let (fields, _, _) = self.layouts.tuple(&types);
+        let ty = Ty::Tuple(types);
+        let address = self.slot(&ty);
+        for (field, value) in fields.iter().zip(values) {
+            self.store(address, field.offset, value, &field.ty);
+        }
+        Ok(address)
+    }
+
+    /// Lower each element of a tuple literal, checking it's of its type in
+    /// `elements`, if given, and copying it as `owned()` does.
+    ///
+    fn tuple_elements(
+        &mut self,
+        tuple: &syntax::tuple::Syntax,
+        elements: Option<&Vec<Ty>>,
+    ) -> Result<Vec<Value>, String> {
+        let mut values = Vec::new();
+        for (index, element) in tuple.elements.iter().enumerate() {
+            let ty = elements.map(|elements| &elements[index]);
+            let value = self.owned(&**element, ty)?;
+            if let Some(ty) = ty {
+                self.expect_ty(value, ty, &format!("element {}", index))?;
+            }
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// The elements of the tuple `expression` is, where a value of type
+    /// `expected` is wanted if that's known, the `what` of something. Those
+    /// of a literal, or a call returning them in registers, are never stored;
+    /// any other tuple's are copied out of it.
+    ///
+    fn elements(
+        &mut self,
+        expression: &dyn syntax::Syntax,
+        expected: Option<&Ty>,
+        what: &str,
+    ) -> Result<Vec<Value>, String> {
+        if let Some(tuple) = tuple_literal(expression) {
+            match expected {
+                None => return self.tuple_elements(tuple, None),
+                Some(Ty::Tuple(elements)) if elements.len() == tuple.elements.len() => {
+                    return self.tuple_elements(tuple, Some(elements));
+                },
+                Some(_) => (),
+            }
+        }
+        if let Some(call) = self.register_call(expression) {
+            let ty = self.signatures[&call.name.string].return_type.clone().unwrap();
+            if let Some(expected) = expected {
+                if ty != *expected {
+                    return Err(format!(
+                        "{} must have type {}, but has type {}", what, expected, ty,
+                    ));
+                }
+            }
+            return self.call_values(call, None);
+        }
+
+        let value = match expected {
+            Some(ty) => {
+                let value = self.expression_as(expression, ty)?;
+                self.expect_ty(value, ty, what)?;
+                value
+            },
+            None => self.expression(expression, None)?,
+        };
+        match self.ty(value) {
+            Ty::Tuple(ref elements) => {
+                let (fields, _, _) = self.layouts.tuple(elements);
+                Ok(fields.iter().map(|field| self.load(value, field.offset, &field.ty)).collect())
+            },
+            ty => Err(format!("{} isn't a tuple, so can't be taken apart", ty.describe())),
+        }
+    }
+
+    /// The call `expression` is, if it's a call to a function that returns
+    /// a tuple in registers.
+    ///
+    fn register_call<'e>(
+        &self,
+        expression: &'e dyn syntax::Syntax,
+    ) -> Option<&'e syntax::call::Syntax> {
+        if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+            return self.register_call(&*expression.value);
+        }
+        let call = downcast::<syntax::call::Syntax>(expression)?;
+        let return_type = self.signatures.get(&call.name.string)?.return_type.as_ref()?;
+        match (return_type, return_type.returned()) {
+            (Ty::Tuple(_), Some(_)) => Some(call),
+            _ => None,
+        }
+    }
+
+    /// Reserve memory for a struct or enum; return its address.
+    ///
+    fn slot(&mut self, ty: &Ty) -> Value {
+        let (size, align) = self.layouts.size_align(ty);
+        let address = self.function.push(
+            self.current, Type::Ptr, InstructionKind::Slot(size, align),
+        );
+        self.typed(address, ty);
+        self.frame.insert(address);
+        address
+    }
+
+    /// Note that `value` holds the address of a struct, enum, array or slice,
+    /// or is a reference, if `ty` is one.
+    ///
+    fn typed(&mut self, value: Value, ty: &Ty) {
+        if ty.scalar().is_none() {
+            self.types.insert(value, ty.clone());
+        }
+    }
+
+    /// Read a `ty` from `offset` bytes past `address`: load a scalar or
+    /// reference, or copy anything else to a new slot.
+    ///
+    fn load(&mut self, address: Value, offset: u32, ty: &Ty) -> Value {
+        match ty.in_memory() {
+            false => {
+                let value = self.function.push(
+                    self.current, ty.ir_type(), InstructionKind::Load(address, offset),
+                );
+                self.typed(value, ty);
+                if self.frame_stored && self.frame.contains(&address) &&
+                    ty.ir_type() == Type::Ptr {
+                    self.frame.insert(value);
+                }
+                value
+            },
+            true => {
+                let copy = self.slot(ty);
+                self.copy(copy, 0, address, offset, ty);
+                copy
+            },
+        }
+    }
+
+    /// Write `value`, a `ty`, to `offset` bytes past `address`.
+    ///
+    fn store(&mut self, address: Value, offset: u32, value: Value, ty: &Ty) {
+        match ty.in_memory() {
+            false => {
+                if self.frame.contains(&value) {
+                    self.frame_stored = true;
+                }
+                self.function.push(
+                    self.current, Type::I32, InstructionKind::Store(address, offset, value),
+                );
+            },
+            true => self.copy(address, offset, value, 0, ty),
+        }
+    }
+
+    /// Copy a struct or enum of type `ty` from `from_offset` bytes past
+    /// `from` to `to_offset` bytes past `to`.
+    ///
+    fn copy(&mut self, to: Value, to_offset: u32, from: Value, from_offset: u32, ty: &Ty) {
+        let (size, _) = self.layouts.size_align(ty);
+        self.function.push(
+            self.current, Type::I32, InstructionKind::Copy(to, to_offset, from, from_offset, size),
+        );
+    }
+
+    /// Bind the local `name` to `value`; or if its address is taken, and it
+    /// isn't kept in memory already, store `value` in a slot kept for it.
+    ///
+    fn bind(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let ty = self.ty(value);
+        let slot = match self.boxes.get(name).cloned() {
+            Some((slot, boxed)) => {
+                self.expect_ty(value, &boxed, &format!("value of `{}`", name))?;
+                slot
+            },
+            None if self.addressed.contains(name) && !ty.in_memory() => {
+                // The slot is made in the entry block, so that it's there
+                // wherever the local is.
+                let (size, align) = self.layouts.size_align(&ty);
+                let entry = self.function.entry();
+                let slot = self.function.push(
+                    entry, Type::Ptr, InstructionKind::Slot(size, align),
+                );
+                self.frame.insert(slot);
+                self.boxes.insert(name.to_string(), (slot, ty.clone()));
+                slot
+            },
+            None => {
+                self.variables.insert(name.to_string(), value);
+                return Ok(());
+            },
+        };
+        self.store(slot, 0, value, &ty);
+        // It's still only in scope where it's been assigned.
+        self.variables.insert(name.to_string(), slot);
+        Ok(())
+    }
+
+    /// The slot of the local `name`, and its type, if its address is taken
+    /// and it's in scope.
+    ///
+    fn boxed(&self, name: &str) -> Option<(Value, Ty)> {
+        match self.variables.contains_key(name) {
+            true  => self.boxes.get(name).cloned(),
+            false => None,
+        }
+    }
+
+    /// The type of `value`, which is a struct's or enum's if it's a `ptr` to
+    /// one.
+    ///
+    fn ty(&self, value: Value) -> Ty {
+        match self.types.get(&value) {
+            Some(ty) => ty.clone(),
+            None => Ty::Scalar(self.function.type_of(value)),
+        }
+    }
+
+    /// Whether `value` is a reference into the frame, or is kept in memory
+    /// that might hold one.
+    ///
+    fn escapes(&self, value: Value) -> bool {
+        match self.ty(value) {
+            ref ty if ty.in_memory() => {
+                self.frame_stored && self.frame.contains(&value) &&
+                    self.layouts.has_reference(ty)
+            },
+            _ => self.frame.contains(&value),
+        }
+    }
+
+    /// Lower the operands of `op`, which must be numbers of the same type, or
+    /// chars if it's a comparison; `expected` is the type expected of both, if
+    /// any.
+    ///
+    fn operands(
+        &mut self,
+        op: BinaryOp,
+        lhs: &dyn syntax::Syntax,
+        rhs: &dyn syntax::Syntax,
+        expected: Option<Type>,
+    ) -> Result<(Value, Value), String> {
+        // An unsuffixed literal takes the type of the other operand, so that
+        // one goes first. Literals have no side effects, so the order doesn't
+        // matter otherwise.
+        let (lhs, rhs) = match is_unsuffixed(lhs) && !is_unsuffixed(rhs) {
+            true => {
+                let rhs = self.expression(rhs, expected)?;
+                let rhs_type = self.function.type_of(rhs);
+                (self.expression(lhs, Some(rhs_type))?, rhs)
+            },
+            false => {
+                let lhs = self.expression(lhs, expected)?;
+                let lhs_type = self.function.type_of(lhs);
+                (lhs, self.expression(rhs, Some(lhs_type))?)
+            },
+        };
+
+        let ty = self.function.type_of(lhs);
+        match (ty, op.is_comparison()) {
+            (Type::Char, true) => (),
+            (Type::Char, false) => return Err(
+                "chars aren't numbers; convert to an integer with `as` first".to_string()
+            ),
+            _ => self.expect_number(lhs, "operand")?,
+        }
+        let rhs_type = self.function.type_of(rhs);
+        if (rhs_type.is_number() || rhs_type == Type::Char) && rhs_type != ty {
+            return Err(format!(
+                "operands have different types, {} and {}; convert one with `as`",
+                ty, rhs_type,
+            ));
+        }
+        self.expect(rhs, ty, "right-hand operand")?;
+        Ok((lhs, rhs))
+    }
+
+    /// Lower a call; a tuple it returns in registers is stored in a new
+    /// slot.
+    ///
+    fn call(
+        &mut self,
+        call: &syntax::call::Syntax,
+        expected: Option<Type>,
+    ) -> Result<Value, String> {
+        let values = self.call_values(call, expected)?;
+        if values.len() == 1 {
+            return Ok(values[0]);
+        }
+
+        let ty = self.signatures[&call.name.string].return_type.clone().unwrap();
+        let (fields, _, _) = match ty {
+            Ty::Tuple(ref elements) => self.layouts.tuple(elements),
+            _ => unreachable!("only a tuple is returned as more than one value"),
+        };
+        let address = self.slot(&ty);
+        for (field, value) in fields.iter().zip(values) {
+            self.store(address, field.offset, value, &field.ty);
+        }
+        Ok(address)
+    }
+
+    /// Lower a call; return each `Value` it returns.
+    ///
+    fn call_values(
+        &mut self,
+        call: &syntax::call::Syntax,
+        expected: Option<Type>,
+    ) -> Result<Vec<Value>, String> {
+        let name = &call.name.string;
+        if let Some((op, overflow)) = arithmetic_built_in(name) {
+            return self.arithmetic(call, op, overflow, expected).map(|value| vec![value]);
+        }
+
+        let signature = match name.as_str() {
+            _ if is_built_in(name) => None,
+            _ => Some(self.signatures.get(name).ok_or_else(
+                || format!("call to undefined function `{}`", name)
+            )?),
+        };
+        let arity = signature.map_or(1, |signature| signature.parameters.len());
+        if call.arguments.len() != arity {
+            return Err(format!(
+                "`{}` takes {} arguments but was given {}",
+                name, arity, call.arguments.len(),
+            ));
+        }
+
+        let mut arguments = Vec::new();
+        for (index, argument) in call.arguments.iter().enumerate() {
+            arguments.push(match signature.map(|signature| &signature.parameters[index]) {
+                Some(ty) => self.owned(&**argument, Some(ty))?,
+                None => self.expression(&**argument, None)?,
+            });
+        }
+
+        let signature = match signature {
+            Some(signature) => signature,
+            None => return self.built_in(name, arguments[0]).map(|value| vec![value]),
+        };
+
+        for (&argument, ty) in arguments.iter().zip(&signature.parameters) {
+            self.expect_ty(argument, ty, "argument")?;
+        }
+
+        // What's returned may refer to what any reference passed does, and
+        // may be stored through any `&mut` reference passed: only one to a
+        // local, holding no `&mut` reference itself, is safe to store it in.
+        let escaping = arguments.iter().any(|&argument| self.escapes(argument));
+        if escaping {
+            for (&argument, ty) in arguments.iter().zip(&signature.parameters) {
+                if !self.layouts.writes_reference(ty) {
+                    continue;
+                }
+                match *ty {
+                    Ty::Ref(ref referent, true) if self.frame.contains(&argument) &&
+                        !self.layouts.writes_reference(referent) => self.frame_stored = true,
+                    _ => return Err(format!(
+                        "can't pass `{}` a reference to a local along with a `&mut` \
+                         reference it could store it through",
+                        name,
+                    )),
+                }
+            }
+        }
+
+        // Anything else kept in memory is returned to memory the caller
+        // reserves.
+        // A function that returns nothing returns 0 to the IR.
+        let return_type = signature.return_type.clone().unwrap_or(Ty::Scalar(Type::I32));
+        let types = match (&return_type, return_type.returned()) {
+            (Ty::Tuple(elements), Some(_)) => elements.clone(),
+            (ty, Some(_)) => vec![ty.clone()],
+            (ty, None) => {
+                let sret = self.slot(ty);
+                arguments.insert(0, sret);
+                vec![ty.clone()]
+            },
+        };
+        let more: Vec<Value> = types[1..].iter()
+            .map(|ty| self.function.new_value(ty.ir_type()))
+            .collect();
+        let result = self.function.push(
+            self.current, types[0].ir_type(),
+            InstructionKind::Call(name.clone(), arguments, more.clone()),
+        );
+
+        let values: Vec<Value> = Some(result).into_iter().chain(more).collect();
+        for (&value, ty) in values.iter().zip(&types) {
+            self.typed(value, ty);
+            if escaping && self.layouts.has_reference(ty) {
+                match ty.in_memory() {
+                    true  => self.frame_stored = true,
+                    false => { self.frame.insert(value); },
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Lower a call to an arithmetic built-in, such as `wrapping_add`.
+    ///
+    fn arithmetic(
+        &mut self,
+        call: &syntax::call::Syntax,
+        op: BinaryOp,
+        overflow: Overflow,
+        expected: Option<Type>,
+    ) -> Result<Value, String> {
+        let name = &call.name.string;
+        if call.arguments.len() != 2 {
+            return Err(format!(
+                "`{}` takes 2 arguments but was given {}", name, call.arguments.len(),
+            ));
+        }
+
+        let (lhs, rhs) = self.operands(
+            op, &*call.arguments[0], &*call.arguments[1], expected,
+        )?;
+        let ty = self.function.type_of(lhs);
+        if !ty.is_integer() {
+            return Err(format!("`{}` takes integers, but was given {}s", name, ty));
+        }
+        Ok(self.function.push(
+            self.current, ty, InstructionKind::Binary(op, overflow, lhs, rhs),
+        ))
+    }
+
+    /// Lower a call to a built-in, of one argument.
+    ///
+    fn built_in(&mut self, name: &str, argument: Value) -> Result<Value, String> {
+        let ty = self.ty(argument);
+        if name == "len" {
+            let array = match ty {
+                Ty::Ref(ref ty, _) => &**ty,
+                ref ty => ty,
+            };
+            return match *array {
+                Ty::Array(_, length) => Ok(self.constant(Type::U64, length as i128)),
+                Ty::Slice(..) => Ok(self.load(
+                    argument, Type::Ptr.size(), &Ty::Scalar(Type::U64),
+                )),
+                _ => Err(format!(
+                    "`len` takes an array or slice, but was given {}", ty.describe(),
+                )),
+            };
+        }
+        if named_type(name).is_none() && ty.scalar().is_none() {
+            return Err(format!("can't print {}", ty.describe()));
+        }
+        match named_type(name) {
+            Some(ty) => self.convert(argument, ty, "argument"),
+            None => Ok(self.function.push(
+                self.current, Type::I32, InstructionKind::Print(argument),
+            )),
+        }
+    }
+
+    /// Convert `value`, the `what` of a conversion, to `ty`. Chars only
+    /// convert to and from integers.
+    ///
+    fn convert(&mut self, value: Value, ty: Type, what: &str) -> Result<Value, String> {
+        let from = self.function.type_of(value);
+        match (from, ty) {
+            (Type::Char, Type::Char) => (),
+            (Type::Char, to) if to.is_float() => return Err(format!(
+                "can't convert char to {}; convert to an integer first", to,
+            )),
+            (Type::Char, _) => (),
+            (_, Type::Char) if from.is_float() => return Err(format!(
+                "can't convert {} to char; convert to an integer first", from,
+            )),
+            _ => self.expect_number(value, what)?,
+        }
+        match from == ty {
+            true  => Ok(value),
+            false => Ok(self.function.push(
+                self.current, ty, InstructionKind::Convert(value),
+            )),
+        }
+    }
+
+    /// Check `value`, the `what` of something, is of type `ty`.
+    ///
+    fn expect(&self, value: Value, ty: Type, what: &str) -> Result<(), String> {
+        self.expect_ty(value, &Ty::Scalar(ty), what)
+    }
+
+    fn expect_ty(&self, value: Value, ty: &Ty, what: &str) -> Result<(), String> {
+        match self.ty(value) {
+            ref found if found == ty => Ok(()),
+            found => Err(format!("{} must have type {}, but has type {}", what, ty, found)),
+        }
+    }
+
+    fn expect_number(&self, value: Value, what: &str) -> Result<(), String> {
+        match self.ty(value) {
+            Ty::Scalar(found) if found.is_number() => Ok(()),
+            found => Err(format!("{} must be a number, but has type {}", what, found)),
+        }
+    }
+
+    fn constant(&mut self, ty: Type, value: i128) -> Value {
+        self.function.push(self.current, ty, InstructionKind::Const(value))
+    }
+
+    fn float(&mut self, ty: Type, value: f64) -> Value {
+        self.function.push(self.current, ty, InstructionKind::Float(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lower;
+    use ir::{ self, Overflow };
+    use lex::Lexer;
+    use parse::Parser;
+
+    /// Lower `source`, trapping on overflow, and check the IR is well formed.
+    ///
+    fn lowered(source: &str) -> Result<ir::Module, String> {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let tree = Parser::new().parse(tokens).unwrap();
+        let module = lower(&tree, Overflow::Trap)?;
+        ir::verify::verify(&module).unwrap();
+        Ok(module)
+    }
+
+    fn error(source: &str) -> String {
+        lowered(source).map(|_| ()).unwrap_err()
+    }
+
+    #[test]
+    fn a_function_with_a_return_type_must_return_a_value() {
+        assert_eq!(
+            error("f(x: i32) -> i32 { if x > 0 { return 7; } }"),
+            "in function `f`: missing return value at the end",
+        );
+        assert_eq!(
+            error("f(x: i32) -> i32 { if x > 0 { return; } return 7; }"),
+            "in function `f`: missing return value, of type i32",
+        );
+        // An end that's never reached needs no return.
+        lowered("f(x: i32) -> i32 { if x > 0 { return 7; } else { return 1; } }").unwrap();
+        lowered("f(x: i32) -> i32 { match x { 0 => { return 1; }, _ => { return 2; } } }")
+            .unwrap();
+    }
+
+    #[test]
+    fn a_function_without_a_return_type_returns_nothing() {
+        assert_eq!(
+            error("f(x: i32) { return x; }"),
+            "in function `f`: unexpected return value, as the function has no return type",
+        );
+        assert_eq!(
+            error("f() { }\nmain() -> i32 { return f(); }"),
+            "in function `main`: `f` returns nothing, so has no value",
+        );
+        lowered("f(x: i32) { if x > 0 { return; } }\nmain() { f(1); match 2 { _ => f(2) } }")
+            .unwrap();
+    }
+
+    #[test]
+    fn tuples_are_taken_apart_into_names() {
+        lowered("\
+            pair() -> (i32, i32) { return (1, 2); }
+            main() -> i32 {
+                (q, r) := pair();
+                (a, _, c) := (1, 2.5, 'c');
+                (x, y): (i64, u8) = (1, 2);
+                return q + r + a + c as i32 + x as i32 + y as i32;
+            }
+        ").unwrap();
+    }
+
+    #[test]
+    fn taking_a_tuple_apart_needs_a_name_for_each_element() {
+        assert_eq!(
+            error("main() -> i32 { (a, b) := (1, 2, 3); return a; }"),
+            "in function `main`: can't take 3 elements apart into 2 names",
+        );
+        assert_eq!(
+            error("pair() -> (i32, i32) { return (1, 2); }\n\
+                   main() -> i32 { (a, b, _) := pair(); return a; }"),
+            "in function `main`: can't take 2 elements apart into 3 names",
+        );
+        assert_eq!(
+            error("main() -> i32 { (a, b) := 5; return a; }"),
+            "in function `main`: i32 isn't a tuple, so can't be taken apart",
+        );
+        assert_eq!(
+            error("main() -> i32 { (a, a) := (1, 2); return a; }"),
+            "in function `main`: `a` is bound twice in taking a tuple apart",
+        );
+        // One name in parentheses isn't taking a tuple apart.
+        let tokens = Lexer::new().lex("main() -> i32 { (a) := (1, 2); return a; }").unwrap();
+        assert!(Parser::new().parse(tokens).is_none());
+    }
+
+    #[test]
+    fn a_match_must_cover_every_value() {
+        let shape = "enum Shape { Circle(i32), Rect(i32, i32), Empty }\n";
+        let cases = [
+            ("f(s: Shape) -> i32 { return match s { Shape::Circle(r) => r, Shape::Empty => 0 }; }",
+             "match on Shape isn't exhaustive: `Shape::Rect` isn't covered"),
+            ("f(x: i32) -> i32 { return match x { 0 => 1, 1 => 2 }; }",
+             "match on i32 isn't exhaustive; add a `_` arm"),
+            ("f(c: char) -> i32 { match c { 'a' => { return 1; } } return 0; }",
+             "match on char isn't exhaustive; add a `_` arm"),
+        ];
+        for (source, message) in cases {
+            assert_eq!(
+                error(&format!("{}{}", shape, source)), format!("in function `f`: {}", message),
+            );
+        }
+
+        // Every variant, or every value, needs no `_`.
+        lowered(&format!(
+            "{}f(s: Shape) -> i32 {{ \
+                 return match s {{ \
+                     Shape::Circle(r) => r, Shape::Rect(w, _) => w, Shape::Empty => 0, \
+                 }}; \
+             }}",
+            shape,
+        )).unwrap();
+        lowered("f(x: u8) -> i32 { return match x { _ => 1 }; }").unwrap();
+    }
+
+    #[test]
+    fn a_variant_needs_a_value_for_each_field() {
+        let shape = "enum Shape { Circle(i32), Rect(i32, i32), Empty }\n";
+        let cases = [
+            ("f(s: Shape) -> i32 { return match s { Shape::Circle(a, b) => a, _ => 0 }; }",
+             "`Shape::Circle` has 1 field but the pattern has 2"),
+            ("f(s: Shape) -> i32 { return match s { Shape::Rect(a) => a, _ => 0 }; }",
+             "`Shape::Rect` has 2 fields but the pattern has 1"),
+            ("f(x: i32) -> Shape { return Shape::Circle(x, x); }",
+             "`Shape::Circle` has 1 field but was given 2"),
+            ("f(x: i32) -> Shape { return Shape::Empty(x); }",
+             "`Shape::Empty` has 0 fields but was given 1"),
+        ];
+        for (source, message) in cases {
+            assert_eq!(
+                error(&format!("{}{}", shape, source)), format!("in function `f`: {}", message),
+            );
+        }
+    }
+
+    #[test]
+    fn constant_indices_out_of_bounds_are_errors() {
+        let cases = [
+            ("f() -> i32 { a = [1, 2, 3, 4]; return a[4]; }",
+             "index 4 is out of bounds for [i32; 4]"),
+            ("f() -> i32 { a = [1, 2, 3, 4]; a[7] = 0; return a[0]; }",
+             "index 7 is out of bounds for [i32; 4]"),
+            ("f() -> i32 { m: [[i32; 2]; 3] = [[1, 2], [3, 4], [5, 6]]; return m[2][2]; }",
+             "index 2 is out of bounds for [i32; 2]"),
+            ("f(i: u64) -> i32 { a = [1, 2, 3, 4]; return a[i] + a[9]; }",
+             "index 9 is out of bounds for [i32; 4]"),
+        ];
+        for (source, message) in cases {
+            assert_eq!(error(source), format!("in function `f`: {}", message), "{}", source);
+        }
+    }
+
+    /// An index only known when the program runs is checked then, branching
+    /// to a block that traps.
+    ///
+    #[test]
+    fn other_indices_are_checked_when_the_program_runs() {
+        let traps = |source: &str| {
+            let module = lowered(source).unwrap();
+            module.functions[0].blocks.iter()
+                .filter(|data| data.terminator == Some(ir::Terminator::Trap))
+                .count()
+        };
+        assert_eq!(traps("f() -> i32 { a = [1, 2, 3, 4]; return a[3]; }"), 0);
+        assert_eq!(traps("f(i: u64) -> i32 { a = [1, 2, 3, 4]; return a[i]; }"), 1);
+        assert_eq!(traps("f(s: &[i32]) -> i32 { return s[0] + s[1]; }"), 2);
+    }
+
+    #[test]
+    fn only_a_mut_reference_can_be_assigned_through() {
+        let structure = "struct S { a: i32, b: [i32; 2] }\n";
+        let sources = [
+            "f(r: &i32) { *r = 1; }",
+            "f(p: &i32) { r := p; *r = 1; }",
+            "f(s: &S) { s.a = 1; }",
+            "f(s: &S) { s.b[1] = 1; }",
+            "f(a: &[i32; 2]) { a[0] = 1; }",
+            "f(s: &[i32]) { s[0] = 1; }",
+        ];
+        for source in sources {
+            assert_eq!(
+                error(&format!("{}{}", structure, source)),
+                "in function `f`: can't assign through a `&` reference", "{}", source,
+            );
+        }
+
+        lowered(&format!(
+            "{}f(r: &mut i32, s: &mut S, a: &mut [i32; 2], t: &mut [i32]) {{ \
+                 *r = 1; s.a = 1; s.b[1] = 1; a[0] = 1; t[0] = 1; \
+             }}",
+            structure,
+        )).unwrap();
+    }
+
+    #[test]
+    fn a_mut_reference_cant_be_taken_through_a_shared_one() {
+        let structure = "struct S { a: i32, b: [i32; 2] }\n";
+        let sources = [
+            "f(r: &i32) { m := &mut *r; *m = 1; }",
+            "f(s: &S) { m := &mut s.a; *m = 1; }",
+            "f(s: &S) { m := &mut s.b[0]; *m = 1; }",
+            "f(s: &[i32]) { m := &mut s[0]; *m = 1; }",
+        ];
+        for source in sources {
+            assert_eq!(
+                error(&format!("{}{}", structure, source)),
+                "in function `f`: can't take a `&mut` reference through a `&` reference",
+                "{}", source,
+            );
+        }
+
+        // Every local can be changed, so `&mut` of one is fine, as it is
+        // through another `&mut`.
+        lowered(&format!(
+            "{}f(s: &mut S) {{ x := 1; m := &mut x; *m = 2; n := &mut s.b[1]; *n = x; }}",
+            structure,
+        )).unwrap();
+    }
+
+    #[test]
+    fn returning_a_reference_to_a_local_is_an_error() {
+        let message = "in function `f`: can't return a reference to a local, which doesn't \
+                       outlive the call";
+        let sources = [
+            "f(p: &i32) -> &i32 { x := 42; return &x; }",
+            // Through a local, a join, a call or a slice.
+            "f(p: &i32) -> &i32 { x := 42; r := &x; return r; }",
+            "f(p: &i32, c: i32) -> &i32 { x := 1; r := p; if c > 0 { r = &x; } return r; }",
+            "id(p: &i32) -> &i32 { return p; }\nf(p: &i32) -> &i32 { x := 1; return id(&x); }",
+            "f(p: &[i32]) -> &i32 { a := [1, 2]; s: &[i32] = &a; return &s[1]; }",
+            "f(p: &[i32]) -> &[i32] { a := [1, 2]; s: &[i32] = &a; return s; }",
+            // The caller's copy of an argument goes with the call too.
+            "struct S { a: i32 }\nf(s: S, p: &i32) -> &i32 { return &s.a; }",
+            "struct S { r: &i32 }\nf(p: &i32) -> S { x := 1; return S { r: &x }; }",
+        ];
+        for source in sources {
+            assert_eq!(error(source), message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn a_function_returning_a_reference_must_take_one() {
+        assert_eq!(
+            error("f() -> &i32 { x := 42; return &x; }"),
+            "in function `f`: returns &i32 but takes no reference for it to refer to",
+        );
+        assert_eq!(
+            error("struct S { r: &i32 }\nf(a: i32) -> S { return f(a); }"),
+            "in function `f`: returns S but takes no reference for it to refer to",
+        );
+    }
+
+    #[test]
+    fn a_reference_to_a_local_cant_be_stored_where_it_would_outlive_it() {
+        assert_eq!(
+            error("f(r: &mut &i32) { x := 1; *r = &x; }"),
+            "in function `f`: can't store a reference to a local where it would outlive it",
+        );
+        assert_eq!(
+            error("set(r: &mut &i32, v: &i32) { *r = v; }\n\
+                   f(r: &mut &i32) { x := 1; set(r, &x); }"),
+            "in function `f`: can't pass `set` a reference to a local along with a `&mut` \
+             reference it could store it through",
+        );
+    }
+
+    #[test]
+    fn references_that_come_from_arguments_can_be_returned() {
+        let source = "\
+            struct S { r: &i32 }
+            set(r: &mut &i32, v: &i32) { *r = v; }
+            get(s: &S) -> &i32 { return s.r; }
+            first(s: &[i32]) -> &i32 { return &s[0]; }
+            pick(c: i32, a: &mut i32, b: &mut i32) -> &mut i32 {
+                if c > 0 { return a; }
+                return b;
+            }
+            f(p: &i32, s: &S) -> &i32 {
+                r := p;
+                set(&mut r, s.r);
+                return get(s);
+            }
+            main() -> i32 {
+                x := 1;
+                y := 2;
+                a := [5, 6];
+                *pick(1, &mut x, &mut y) = 3;
+                s := S { r: &y };
+                return *f(&x, &s) + *first(&a);
+            }
+        ";
+        lowered(source).unwrap();
+    }
+}
@@ -0,0 +1,793 @@
+//! A typed, back-end independent intermediate representation.
+//!
+//! A `Module` is a list of `Function`s; a `Function` is a control-flow graph
+//! of basic `Block`s. Every `Value` used is defined exactly once (SSA), either
+//! as a block parameter or as the result of an `Instruction`. Rather than phi
+//! nodes, blocks take parameters, which branches pass arguments to.
+//!
+//! Every block ends in exactly one `Terminator`. The first block is the entry
+//! block; its parameters are the function's parameters, and nothing may
+//! branch to it.
+//!
+//! Values are scalars. Anything bigger, such as a struct, is kept in memory
+//! in the function's frame, reserved by a `Slot`, and handled by its address,
+//! a `ptr`; its parts are read with `Load` and written with `Store`, and it's
+//! copied whole with `Copy`. `Element` finds the address of an array's
+//! element at an index not known until run time.
+//!
+//! A function returns one value, or two: `Return` takes them all, and a
+//! `Call` defines a `Value` for each. Back-ends return both in registers.
+//!
+//! `lower` produces a `Module` from a `parse::Tree`, and `verify` checks one
+//! is well-formed. `Display` gives the textual dump used by `--emit=ir`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod lower;
+pub mod verify;
+
+#[derive (Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    /// A Unicode scalar value. It can be compared, and converted to and from
+    /// integers with `as`, but isn't a number.
+    Char,
+    /// A constant, NUL-terminated string; only good for printing.
+    Str,
+    /// The address of memory in a function's frame.
+    Ptr,
+}
+
+impl Type {
+    /// Every type that can be named in source, as `isize` and `usize` are
+    /// only other names for `i64` and `u64`.
+    pub const NUMBERS: [Type; 10] = [
+        Type::I8, Type::I16, Type::I32, Type::I64,
+        Type::U8, Type::U16, Type::U32, Type::U64,
+        Type::F32, Type::F64,
+    ];
+
+    /// Whether arithmetic and comparisons work on the type.
+    pub fn is_number(self) -> bool {
+        !matches!(self, Type::Char | Type::Str | Type::Ptr)
+    }
+
+    pub fn is_float(self) -> bool {
+        self == Type::F32 || self == Type::F64
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.is_number() && !self.is_float()
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(self, Type::I8 | Type::I16 | Type::I32 | Type::I64)
+    }
+
+    /// The width of the type in bits; a `str` or `ptr` is a 64-bit pointer.
+    pub fn bits(self) -> u32 {
+        match self {
+            Type::I8 | Type::U8 => 8,
+            Type::I16 | Type::U16 => 16,
+            Type::I32 | Type::U32 | Type::F32 | Type::Char => 32,
+            Type::I64 | Type::U64 | Type::F64 | Type::Str | Type::Ptr => 64,
+        }
+    }
+
+    /// The size of the type in memory, in bytes, which is also its alignment.
+    /// This is the same on every target, so layouts can be worked out before
+    /// choosing one.
+    pub fn size(self) -> u32 {
+        self.bits() / 8
+    }
+
+    /// The smallest and largest values of an integer type.
+    pub fn range(self) -> (i128, i128) {
+        debug_assert!(self.is_integer());
+        match self.is_signed() {
+            true  => (-(1 << (self.bits() - 1)), (1 << (self.bits() - 1)) - 1),
+            false => (0, (1 << self.bits()) - 1),
+        }
+    }
+
+    /// Whether `value` fits in an integer type.
+    pub fn contains(self, value: i128) -> bool {
+        let (min, max) = self.range();
+        min <= value && value <= max
+    }
+
+    /// Wrap `value` to an integer type, keeping its low bits.
+    pub fn wrap(self, value: i128) -> i128 {
+        let (min, _) = self.range();
+        let modulus = 1i128 << self.bits();
+        (value - min).rem_euclid(modulus) + min
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::Char => write!(f, "char"),
+            Type::Str => write!(f, "str"),
+            Type::Ptr => write!(f, "ptr"),
+        }
+    }
+}
+
+#[derive (Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(pub usize);
+
+#[derive (Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Block(pub usize);
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "block{}", self.0)
+    }
+}
+
+/// An operation on two values of the same type. Arithmetic gives the same
+/// type, and what it does if an integer result doesn't fit is up to its
+/// `Overflow`; comparisons give an `i32` of 1 or 0, and compare unsigned
+/// integers as unsigned.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(self) -> bool {
+        !matches!(self, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul)
+    }
+
+    pub fn is_commutative(self) -> bool {
+        matches!(self, BinaryOp::Add | BinaryOp::Mul | BinaryOp::Eq | BinaryOp::Ne)
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BinaryOp::Add => write!(f, "add"),
+            BinaryOp::Sub => write!(f, "sub"),
+            BinaryOp::Mul => write!(f, "mul"),
+            BinaryOp::Eq => write!(f, "eq"),
+            BinaryOp::Ne => write!(f, "ne"),
+            BinaryOp::Lt => write!(f, "lt"),
+            BinaryOp::Le => write!(f, "le"),
+            BinaryOp::Gt => write!(f, "gt"),
+            BinaryOp::Ge => write!(f, "ge"),
+        }
+    }
+}
+
+/// What integer arithmetic does when its result doesn't fit its type.
+/// Comparisons and floats never overflow, and always have `Wrap`.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// Keep the low bits of the result.
+    Wrap,
+    /// Stop the program.
+    Trap,
+    /// Give the type's smallest or largest value, whichever is nearer.
+    Saturate,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Overflow::Wrap => write!(f, "wrap"),
+            Overflow::Trap => write!(f, "trap"),
+            Overflow::Saturate => write!(f, "saturate"),
+        }
+    }
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub enum InstructionKind {
+    /// A constant integer, which fits the result's type.
+    Const(i128),
+    /// A constant `f32` or `f64`, as given by the result's type. An `f32` is
+    /// kept exactly as it was rounded to `f32`.
+    Float(f64),
+    /// A constant string.
+    Str(String),
+    Binary(BinaryOp, Overflow, Value, Value),
+    /// Convert between numeric types, to the result's type, as Rust's `as`
+    /// does. Integers are truncated to narrower types, and sign-extended to
+    /// wider ones if signed, zero-extended if not. Floats convert to integers
+    /// by truncating, saturating if out of range (and NaN gives 0).
+    Convert(Value),
+    /// Call a function of the `Module` by name, with arguments. The result is
+    /// the first value it returns; the last `Value`s are the rest, which the
+    /// call defines too.
+    Call(String, Vec<Value>, Vec<Value>),
+    /// Print a number, char or `str` to standard output, with no newline.
+    /// The result is always 0.
+    Print(Value),
+    /// Reserve this many bytes of the function's frame, aligned to this
+    /// power of two; the result is their address. Each `Slot` has its own
+    /// memory, which lasts until the function returns, and isn't initialised.
+    Slot(u32, u32),
+    /// Read a value of the result's type from a `ptr`, plus an offset in
+    /// bytes, which is aligned for the type.
+    Load(Value, u32),
+    /// Write the second value to the first, a `ptr`, plus an offset in bytes,
+    /// as for `Load`. The result is always 0.
+    Store(Value, u32, Value),
+    /// Copy this many bytes from the second `ptr`, plus an offset, to the
+    /// first, plus an offset; the two don't overlap. The result is always 0.
+    Copy(Value, u32, Value, u32, u32),
+    /// The address of an element of an array at a `ptr`: the `ptr` plus the
+    /// second value, a `u64` index, times this many bytes.
+    Element(Value, Value, u32),
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub result: Value,
+    pub kind: InstructionKind,
+}
+
+impl Instruction {
+    /// The `Value`s this instruction uses.
+    ///
+    pub fn operands(&self) -> Vec<Value> {
+        match self.kind {
+            InstructionKind::Const(_) |
+            InstructionKind::Float(_) |
+            InstructionKind::Str(_) |
+            InstructionKind::Slot(..) => Vec::new(),
+            InstructionKind::Binary(_, _, lhs, rhs) => vec![lhs, rhs],
+            InstructionKind::Convert(value) => vec![value],
+            InstructionKind::Call(_, ref arguments, _) => arguments.clone(),
+            InstructionKind::Print(value) => vec![value],
+            InstructionKind::Load(address, _) => vec![address],
+            InstructionKind::Store(address, _, value) => vec![address, value],
+            InstructionKind::Copy(to, _, from, _, _) => vec![to, from],
+            InstructionKind::Element(address, index, _) => vec![address, index],
+        }
+    }
+
+    /// The `Value`s this instruction defines: its result, and any more a call
+    /// returns.
+    ///
+    pub fn results(&self) -> Vec<Value> {
+        let mut results = vec![self.result];
+        if let InstructionKind::Call(_, _, ref more) = self.kind {
+            results.extend(more.iter().cloned());
+        }
+        results
+    }
+
+    /// Replace each `Value` this instruction uses with `f` of it.
+    ///
+    pub fn map_operands<F>(&mut self, mut f: F) where F: FnMut(Value) -> Value {
+        match self.kind {
+            InstructionKind::Const(_) |
+            InstructionKind::Float(_) |
+            InstructionKind::Str(_) |
+            InstructionKind::Slot(..) => (),
+            InstructionKind::Convert(ref mut value) => *value = f(*value),
+            InstructionKind::Binary(_, _, ref mut lhs, ref mut rhs) => {
+                *lhs = f(*lhs);
+                *rhs = f(*rhs);
+            },
+            InstructionKind::Call(_, ref mut arguments, _) => {
+                for argument in arguments {
+                    *argument = f(*argument);
+                }
+            },
+            InstructionKind::Print(ref mut value) => *value = f(*value),
+            InstructionKind::Load(ref mut address, _) => *address = f(*address),
+            InstructionKind::Store(ref mut address, _, ref mut value) => {
+                *address = f(*address);
+                *value = f(*value);
+            },
+            InstructionKind::Copy(ref mut to, _, ref mut from, _, _) => {
+                *to = f(*to);
+                *from = f(*from);
+            },
+            InstructionKind::Element(ref mut address, ref mut index, _) => {
+                *address = f(*address);
+                *index = f(*index);
+            },
+        }
+    }
+}
+
+/// A branch target along with the arguments for its parameters.
+///
+#[derive (Debug, Clone, PartialEq)]
+pub struct Target {
+    pub block: Block,
+    pub arguments: Vec<Value>,
+}
+
+impl Target {
+    pub fn new(block: Block) -> Self {
+        Target {
+            block,
+            arguments: Vec::new(),
+        }
+    }
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Return(Vec<Value>),
+    Branch(Target),
+    /// Go to the first `Target` if the condition is non-zero, otherwise the
+    /// second.
+    CondBranch(Value, Target, Target),
+    /// Go to the `Target` of the case equal to the value, an integer or
+    /// char, or to the last `Target` if there's none. The cases are distinct,
+    /// and fit the value's type.
+    Switch(Value, Vec<(i128, Target)>, Target),
+    /// Stop the program, as arithmetic that traps on overflow does: for an
+    /// index out of bounds, say.
+    Trap,
+}
+
+impl Terminator {
+    pub fn targets(&self) -> Vec<&Target> {
+        match *self {
+            Terminator::Return(_) | Terminator::Trap => Vec::new(),
+            Terminator::Branch(ref target) => vec![target],
+            Terminator::CondBranch(_, ref then_target, ref else_target) => {
+                vec![then_target, else_target]
+            },
+            Terminator::Switch(_, ref cases, ref default) => {
+                cases.iter().map(|(_, target)| target).chain(Some(default)).collect()
+            },
+        }
+    }
+
+    pub fn targets_mut(&mut self) -> Vec<&mut Target> {
+        match *self {
+            Terminator::Return(_) | Terminator::Trap => Vec::new(),
+            Terminator::Branch(ref mut target) => vec![target],
+            Terminator::CondBranch(_, ref mut then_target, ref mut else_target) => {
+                vec![then_target, else_target]
+            },
+            Terminator::Switch(_, ref mut cases, ref mut default) => {
+                cases.iter_mut().map(|(_, target)| target).chain(Some(default)).collect()
+            },
+        }
+    }
+
+    /// The `Value`s this terminator uses, including branch arguments.
+    ///
+    pub fn operands(&self) -> Vec<Value> {
+        let mut operands = match *self {
+            Terminator::Return(ref values) => values.clone(),
+            Terminator::Branch(_) | Terminator::Trap => Vec::new(),
+            Terminator::CondBranch(condition, _, _) |
+            Terminator::Switch(condition, _, _) => vec![condition],
+        };
+        for target in self.targets() {
+            operands.extend(target.arguments.iter().cloned());
+        }
+        operands
+    }
+
+    /// Replace each `Value` this terminator uses with `f` of it.
+    ///
+    pub fn map_operands<F>(&mut self, mut f: F) where F: FnMut(Value) -> Value {
+        match *self {
+            Terminator::Return(ref mut values) => {
+                for value in values {
+                    *value = f(*value);
+                }
+            },
+            Terminator::Branch(_) | Terminator::Trap => (),
+            Terminator::CondBranch(ref mut condition, _, _) |
+            Terminator::Switch(ref mut condition, _, _) => {
+                *condition = f(*condition)
+            },
+        }
+        for target in self.targets_mut() {
+            for argument in &mut target.arguments {
+                *argument = f(*argument);
+            }
+        }
+    }
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub struct BlockData {
+    pub parameters: Vec<Value>,
+    pub instructions: Vec<Instruction>,
+    /// Only `None` while the block is being built.
+    pub terminator: Option<Terminator>,
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// The type of each value returned.
+    pub returns: Vec<Type>,
+
+    pub blocks: Vec<BlockData>,
+    /// The type of each `Value`, by index. Passes that remove instructions
+    /// leave their results here, undefined.
+    pub values: Vec<Type>,
+}
+
+impl Function {
+    pub fn new(name: String, returns: Vec<Type>) -> Self {
+        Function {
+            name,
+            returns,
+            blocks: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn entry(&self) -> Block { Block(0) }
+
+    pub fn parameters(&self) -> &[Value] {
+        &self.blocks[0].parameters
+    }
+
+    pub fn block(&self, block: Block) -> &BlockData {
+        &self.blocks[block.0]
+    }
+
+    pub fn block_mut(&mut self, block: Block) -> &mut BlockData {
+        &mut self.blocks[block.0]
+    }
+
+    pub fn type_of(&self, value: Value) -> Type {
+        self.values[value.0]
+    }
+
+    pub fn new_value(&mut self, ty: Type) -> Value {
+        self.values.push(ty);
+        Value(self.values.len() - 1)
+    }
+
+    pub fn new_block(&mut self) -> Block {
+        self.blocks.push(BlockData {
+            parameters: Vec::new(),
+            instructions: Vec::new(),
+            terminator: None,
+        });
+        Block(self.blocks.len() - 1)
+    }
+
+    pub fn add_parameter(&mut self, block: Block, ty: Type) -> Value {
+        let value = self.new_value(ty);
+        self.block_mut(block).parameters.push(value);
+        value
+    }
+
+    /// Append an instruction producing a value of type `ty` to `block`.
+    ///
+    pub fn push(
+        &mut self,
+        block: Block,
+        ty: Type,
+        kind: InstructionKind,
+    ) -> Value {
+        let result = self.new_value(ty);
+        self.block_mut(block).instructions.push(Instruction {
+            result,
+            kind,
+        });
+        result
+    }
+
+    pub fn terminate(&mut self, block: Block, terminator: Terminator) {
+        let data = self.block_mut(block);
+        debug_assert!(data.terminator.is_none());
+        data.terminator = Some(terminator);
+    }
+
+    pub fn is_terminated(&self, block: Block) -> bool {
+        self.block(block).terminator.is_some()
+    }
+
+    /// Replace every use of each key of `replacements` with its value,
+    /// following chains of replacements.
+    ///
+    pub fn replace_uses(&mut self, replacements: &HashMap<Value, Value>) {
+        if replacements.is_empty() {
+            return;
+        }
+
+        let resolve = |mut value: Value| {
+            while let Some(replacement) = replacements.get(&value) {
+                value = *replacement;
+            }
+            value
+        };
+
+        for data in &mut self.blocks {
+            for instruction in &mut data.instructions {
+                instruction.map_operands(&resolve);
+            }
+            if let Some(ref mut terminator) = data.terminator {
+                terminator.map_operands(&resolve);
+            }
+        }
+    }
+
+    /// The offset of each `Slot`'s memory from the bottom of the frame, and
+    /// the size of all of it, rounded up to a multiple of 16 so a frame can
+    /// go on a 16-byte aligned stack. The frame itself must be aligned to the
+    /// largest alignment of any `Slot`.
+    pub fn slots(&self) -> (HashMap<Value, u32>, u32) {
+        let mut offsets = HashMap::new();
+        let mut size: u32 = 0;
+        for instruction in self.blocks.iter().flat_map(|data| &data.instructions) {
+            if let InstructionKind::Slot(slot_size, align) = instruction.kind {
+                let offset = size.next_multiple_of(align);
+                offsets.insert(instruction.result, offset);
+                size = offset + slot_size;
+            }
+        }
+        (offsets, size.next_multiple_of(16))
+    }
+
+    /// The successors of every block, by index.
+    ///
+    pub fn successors(&self) -> Vec<Vec<Block>> {
+        self.blocks.iter().map(|data| match data.terminator {
+            Some(ref terminator) => {
+                terminator.targets().iter().map(|target| target.block).collect()
+            },
+            None => Vec::new(),
+        }).collect()
+    }
+
+    /// The predecessors of every block, by index. A block appears once per
+    /// edge, so may appear twice for a `CondBranch` with both targets the
+    /// same, or a `Switch` with two.
+    ///
+    pub fn predecessors(&self) -> Vec<Vec<Block>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for (index, successors) in self.successors().into_iter().enumerate() {
+            for successor in successors {
+                predecessors[successor.0].push(Block(index));
+            }
+        }
+        predecessors
+    }
+
+    /// The blocks reachable from the entry, in reverse post-order.
+    ///
+    pub fn reverse_post_order(&self) -> Vec<Block> {
+        let successors = self.successors();
+        let mut visited = vec![false; self.blocks.len()];
+        let mut order = Vec::new();
+
+        // Iterative depth-first search; each stack entry is a block and how
+        // many of its successors have been visited.
+        let mut stack = vec![(self.entry(), 0)];
+        visited[0] = true;
+        while let Some(&mut (block, ref mut next)) = stack.last_mut() {
+            if let Some(&successor) = successors[block.0].get(*next) {
+                *next += 1;
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            else {
+                order.push(block);
+                stack.pop();
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    /// The immediate dominator of every reachable block, by index; `None` for
+    /// the entry and for unreachable blocks.
+    ///
+    /// This is the iterative algorithm of Cooper, Harvey and Kennedy.
+    ///
+    pub fn immediate_dominators(&self) -> Vec<Option<Block>> {
+        let order = self.reverse_post_order();
+        let predecessors = self.predecessors();
+
+        let mut position = vec![usize::MAX; self.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            position[block.0] = index;
+        }
+
+        let mut idoms: Vec<Option<Block>> = vec![None; self.blocks.len()];
+        idoms[0] = Some(self.entry());
+
+        let intersect = |idoms: &[Option<Block>], mut a: Block, mut b: Block| {
+            while a != b {
+                while position[a.0] > position[b.0] {
+                    a = idoms[a.0].unwrap();
+                }
+                while position[b.0] > position[a.0] {
+                    b = idoms[b.0].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &predecessors[block.0] {
+                    if idoms[predecessor.0].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(idom) => intersect(&idoms, predecessor, idom),
+                    });
+                }
+
+                if new_idom.is_some() && idoms[block.0] != new_idom {
+                    idoms[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idoms[0] = None;
+        idoms
+    }
+}
+
+#[derive (Debug, Clone, PartialEq)]
+pub struct Module {
+    pub functions: Vec<Function>,
+}
+
+impl Module {
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters().iter().map(
+            |parameter| format!("{}: {}", parameter, self.type_of(*parameter))
+        ).collect();
+        let returns: Vec<String> = self.returns.iter().map(Type::to_string).collect();
+        writeln!(
+            f, "function {}({}) -> {} {{",
+            self.name, parameters.join(", "), returns.join(", "),
+        )?;
+
+        for (index, data) in self.blocks.iter().enumerate() {
+            let parameters: Vec<String> = data.parameters.iter().map(
+                |parameter| format!("{}: {}", parameter, self.type_of(*parameter))
+            ).collect();
+            writeln!(f, "{}({}):", Block(index), parameters.join(", "))?;
+
+            for instruction in &data.instructions {
+                let results: Vec<String> = instruction.results().iter().map(
+                    |result| format!("{}: {}", result, self.type_of(*result))
+                ).collect();
+                write!(f, "    {} = ", results.join(", "))?;
+                match instruction.kind {
+                    InstructionKind::Const(value) => writeln!(f, "const {}", value)?,
+                    InstructionKind::Float(value) => writeln!(f, "float {:?}", value)?,
+                    InstructionKind::Str(ref string) => writeln!(f, "str {:?}", string)?,
+                    InstructionKind::Convert(value) => writeln!(f, "convert {}", value)?,
+                    InstructionKind::Binary(op, Overflow::Wrap, lhs, rhs) => {
+                        writeln!(f, "{} {}, {}", op, lhs, rhs)?
+                    },
+                    InstructionKind::Binary(op, overflow, lhs, rhs) => {
+                        writeln!(f, "{}.{} {}, {}", op, overflow, lhs, rhs)?
+                    },
+                    InstructionKind::Call(ref name, ref arguments, _) => {
+                        writeln!(f, "call {}({})", name, list(arguments))?
+                    },
+                    InstructionKind::Print(value) => writeln!(f, "print {}", value)?,
+                    InstructionKind::Slot(size, align) => {
+                        writeln!(f, "slot {}, align {}", size, align)?
+                    },
+                    InstructionKind::Load(address, offset) => {
+                        writeln!(f, "load {}+{}", address, offset)?
+                    },
+                    InstructionKind::Store(address, offset, value) => {
+                        writeln!(f, "store {}+{}, {}", address, offset, value)?
+                    },
+                    InstructionKind::Copy(to, to_offset, from, from_offset, size) => {
+                        writeln!(
+                            f, "copy {}+{}, {}+{}, {}", to, to_offset, from, from_offset, size,
+                        )?
+                    },
+                    InstructionKind::Element(address, index, size) => {
+                        writeln!(f, "element {}, {}, {}", address, index, size)?
+                    },
+                }
+            }
+
+            match data.terminator {
+                Some(Terminator::Return(ref values)) => {
+                    writeln!(f, "    return {}", list(values))?
+                },
+                Some(Terminator::Branch(ref target)) => {
+                    writeln!(f, "    branch {}", target)?
+                },
+                Some(Terminator::CondBranch(condition, ref then_target, ref else_target)) => {
+                    writeln!(f, "    cond_branch {}, {}, {}", condition, then_target, else_target)?
+                },
+                Some(Terminator::Switch(value, ref cases, ref default)) => {
+                    let cases: Vec<String> = cases.iter().map(
+                        |(case, target)| format!("{}: {}", case, target)
+                    ).collect();
+                    writeln!(f, "    switch {}, [{}], {}", value, cases.join(", "), default)?
+                },
+                Some(Terminator::Trap) => writeln!(f, "    trap")?,
+                None => writeln!(f, "    <unterminated>")?,
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", self.block, list(&self.arguments))
+    }
+}
+
+fn list(values: &[Value]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(", ")
+}
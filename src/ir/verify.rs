@@ -0,0 +1,494 @@
+//! Checking that a `Module` is well-formed.
+//!
+//! Lowering and optimisation passes should only ever produce IR that passes;
+//! a failure here is a bug in the compiler, not the program being compiled.
+
+use std::collections::HashSet;
+
+use ir::{ Block, Function, InstructionKind, Module, Overflow, Terminator, Type, Value };
+
+pub fn verify(module: &Module) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for function in &module.functions {
+        if !names.insert(&function.name) {
+            return Err(format!("function `{}` defined twice", function.name));
+        }
+
+        Verifier::new(module, function).verify().map_err(
+            |message| format!("in function `{}`: {}", function.name, message)
+        )?;
+    }
+    Ok(())
+}
+
+struct Verifier<'a> {
+    module: &'a Module,
+    function: &'a Function,
+
+    /// The block defining each value, and its position within the block:
+    /// 0 for a block parameter, or 1 + the index of the instruction.
+    definitions: Vec<Option<(Block, usize)>>,
+    idoms: Vec<Option<Block>>,
+    reachable: Vec<bool>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(module: &'a Module, function: &'a Function) -> Self {
+        Verifier {
+            module,
+            function,
+            definitions: vec![None; function.values.len()],
+            idoms: Vec::new(),
+            reachable: vec![false; function.blocks.len()],
+        }
+    }
+
+    fn verify(mut self) -> Result<(), String> {
+        let function = self.function;
+
+        if function.blocks.is_empty() {
+            return Err("no entry block".to_string());
+        }
+        if !(1..=2).contains(&function.returns.len()) {
+            return Err(format!(
+                "returns {} values, but must return one or two", function.returns.len(),
+            ));
+        }
+
+        // Every value must be defined at most once. (Values may go undefined
+        // if they're unused; see `check_use()`.)
+        for (index, data) in function.blocks.iter().enumerate() {
+            let block = Block(index);
+
+            for parameter in &data.parameters {
+                self.define(*parameter, block, 0)?;
+            }
+            for (position, instruction) in data.instructions.iter().enumerate() {
+                for result in instruction.results() {
+                    self.define(result, block, position + 1)?;
+                }
+            }
+
+            if data.terminator.is_none() {
+                return Err(format!("{} has no terminator", block));
+            }
+        }
+
+        for block in function.reverse_post_order() {
+            self.reachable[block.0] = true;
+        }
+        self.idoms = function.immediate_dominators();
+
+        for (index, data) in function.blocks.iter().enumerate() {
+            let block = Block(index);
+
+            for (position, instruction) in data.instructions.iter().enumerate() {
+                for operand in instruction.operands() {
+                    self.check_use(operand, block, position + 1)?;
+                }
+                self.check_instruction(&instruction.kind, instruction.result)
+                    .map_err(|message| format!("{}: {}", instruction.result, message))?;
+            }
+
+            let terminator = data.terminator.as_ref().unwrap();
+            for operand in terminator.operands() {
+                self.check_use(operand, block, data.instructions.len() + 1)?;
+            }
+            self.check_terminator(terminator)
+                .map_err(|message| format!("{}: {}", block, message))?;
+        }
+
+        Ok(())
+    }
+
+    fn define(
+        &mut self,
+        value: Value,
+        block: Block,
+        position: usize,
+    ) -> Result<(), String> {
+        match self.definitions.get_mut(value.0) {
+            None => Err(format!("{} has no type", value)),
+            Some(&mut Some(_)) => Err(format!("{} is defined more than once", value)),
+            Some(definition) => {
+                *definition = Some((block, position));
+                Ok(())
+            },
+        }
+    }
+
+    /// Check that the definition of `value` dominates a use of it at
+    /// `position` in `block`.
+    ///
+    fn check_use(
+        &self,
+        value: Value,
+        block: Block,
+        position: usize,
+    ) -> Result<(), String> {
+        let (definer, defined_at) = match self.definitions.get(value.0) {
+            Some(&Some(definition)) => definition,
+            _ => return Err(format!("{} uses undefined {}", block, value)),
+        };
+
+        // As in LLVM, anything goes in unreachable code.
+        if !self.reachable[block.0] {
+            return Ok(());
+        }
+
+        let dominates = if definer == block {
+            defined_at < position
+        }
+        else {
+            let mut dominator = self.idoms[block.0];
+            while let Some(ancestor) = dominator {
+                if ancestor == definer {
+                    break;
+                }
+                dominator = self.idoms[ancestor.0];
+            }
+            dominator.is_some()
+        };
+
+        match dominates {
+            true  => Ok(()),
+            false => Err(format!("use of {} in {} is not dominated by its definition", value, block)),
+        }
+    }
+
+    fn check_instruction(
+        &self,
+        kind: &InstructionKind,
+        result: Value,
+    ) -> Result<(), String> {
+        let function = self.function;
+        let result_type = function.type_of(result);
+
+        match *kind {
+            InstructionKind::Const(value) if result_type == Type::Char => {
+                match (0..=0x10FFFF).contains(&value) && !(0xD800..0xE000).contains(&value) {
+                    true  => Ok(()),
+                    false => Err(format!("constant {} is not a Unicode scalar value", value)),
+                }
+            },
+            InstructionKind::Const(value) => match result_type.is_integer() {
+                true if result_type.contains(value) => Ok(()),
+                true  => Err(format!("constant {} is out of range for {}", value, result_type)),
+                false => Err(format!("expected an integer type but found {}", result_type)),
+            },
+            InstructionKind::Float(_) => match result_type.is_float() {
+                true  => Ok(()),
+                false => Err(format!("expected a float type but found {}", result_type)),
+            },
+            InstructionKind::Str(_) => expect_type(Type::Str, result_type),
+            InstructionKind::Binary(op, overflow, lhs, rhs) => {
+                let operand_type = function.type_of(lhs);
+                match (operand_type, op.is_comparison()) {
+                    (Type::Char, true) => (),
+                    _ => expect_number(operand_type)?,
+                }
+                expect_type(operand_type, function.type_of(rhs))?;
+                if overflow != Overflow::Wrap && (op.is_comparison() || operand_type.is_float()) {
+                    return Err(format!(
+                        "`{}` of {} can't overflow, so can't {}", op, operand_type, overflow,
+                    ));
+                }
+                match op.is_comparison() {
+                    true  => expect_type(Type::I32, result_type),
+                    false => expect_type(operand_type, result_type),
+                }
+            },
+            // Chars convert to and from integers, but not floats.
+            InstructionKind::Convert(value) => match (function.type_of(value), result_type) {
+                (Type::Char, Type::Char) => Ok(()),
+                (Type::Char, other) | (other, Type::Char) => match other.is_integer() {
+                    true  => Ok(()),
+                    false => Err(format!("expected an integer but found {}", other)),
+                },
+                (from, to) => {
+                    expect_number(from)?;
+                    expect_number(to)
+                },
+            },
+            InstructionKind::Call(ref name, ref arguments, ref more) => {
+                let callee = self.module.function(name).ok_or_else(
+                    || format!("call to undefined function `{}`", name)
+                )?;
+
+                let parameters = callee.parameters();
+                if parameters.len() != arguments.len() {
+                    return Err(format!(
+                        "`{}` takes {} arguments but was given {}",
+                        name, parameters.len(), arguments.len(),
+                    ));
+                }
+                for (parameter, argument) in parameters.iter().zip(arguments) {
+                    expect_type(callee.type_of(*parameter), function.type_of(*argument))?;
+                }
+                let results: Vec<Type> = Some(result_type).into_iter()
+                    .chain(more.iter().map(|value| function.type_of(*value)))
+                    .collect();
+                expect_returns(&callee.returns, &results)
+            },
+            // Every type but an address can be printed.
+            InstructionKind::Print(value) => match function.type_of(value) {
+                Type::Ptr => Err("can't print a ptr".to_string()),
+                _ => expect_type(Type::I32, result_type),
+            },
+            InstructionKind::Slot(_, align) => match align.is_power_of_two() {
+                true  => expect_type(Type::Ptr, result_type),
+                false => Err(format!("alignment {} is not a power of two", align)),
+            },
+            InstructionKind::Load(address, offset) => {
+                expect_type(Type::Ptr, function.type_of(address))?;
+                expect_memory(result_type, offset)
+            },
+            InstructionKind::Store(address, offset, value) => {
+                expect_type(Type::Ptr, function.type_of(address))?;
+                expect_memory(function.type_of(value), offset)?;
+                expect_type(Type::I32, result_type)
+            },
+            InstructionKind::Copy(to, _, from, _, _) => {
+                expect_type(Type::Ptr, function.type_of(to))?;
+                expect_type(Type::Ptr, function.type_of(from))?;
+                expect_type(Type::I32, result_type)
+            },
+            InstructionKind::Element(address, index, _) => {
+                expect_type(Type::Ptr, function.type_of(address))?;
+                expect_type(Type::U64, function.type_of(index))?;
+                expect_type(Type::Ptr, result_type)
+            },
+        }
+    }
+
+    fn check_terminator(&self, terminator: &Terminator) -> Result<(), String> {
+        let function = self.function;
+
+        match *terminator {
+            Terminator::Return(ref values) => {
+                let types: Vec<Type> = values.iter().map(|value| function.type_of(*value))
+                    .collect();
+                expect_returns(&function.returns, &types)?
+            },
+            Terminator::Branch(_) | Terminator::Trap => (),
+            Terminator::CondBranch(condition, _, _) => {
+                expect_type(Type::I32, function.type_of(condition))?
+            },
+            Terminator::Switch(value, ref cases, _) => {
+                let ty = function.type_of(value);
+                if !ty.is_integer() && ty != Type::Char {
+                    return Err(format!("expected an integer or char but found {}", ty));
+                }
+                for (index, &(case, _)) in cases.iter().enumerate() {
+                    let fits = match ty {
+                        Type::Char => (0..=0x10FFFF).contains(&case),
+                        _ => ty.contains(case),
+                    };
+                    if !fits {
+                        return Err(format!("case {} is out of range for {}", case, ty));
+                    }
+                    if cases[..index].iter().any(|&(earlier, _)| earlier == case) {
+                        return Err(format!("case {} appears twice", case));
+                    }
+                }
+            },
+        }
+
+        for target in terminator.targets() {
+            if target.block == function.entry() {
+                return Err("branch to the entry block".to_string());
+            }
+
+            let parameters = match function.blocks.get(target.block.0) {
+                Some(data) => &data.parameters,
+                None => return Err(format!("branch to non-existent {}", target.block)),
+            };
+            if parameters.len() != target.arguments.len() {
+                return Err(format!(
+                    "{} takes {} arguments but was given {}",
+                    target.block, parameters.len(), target.arguments.len(),
+                ));
+            }
+            for (parameter, argument) in parameters.iter().zip(&target.arguments) {
+                expect_type(function.type_of(*parameter), function.type_of(*argument))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn expect_number(found: Type) -> Result<(), String> {
+    match found.is_number() {
+        true  => Ok(()),
+        false => Err(format!("expected a number but found {}", found)),
+    }
+}
+
+/// Check that a value of type `found` can be kept in memory at `offset`.
+fn expect_memory(found: Type, offset: u32) -> Result<(), String> {
+    match found {
+        Type::Str => Err("can't keep a str in memory".to_string()),
+        _ if !offset.is_multiple_of(found.size()) => {
+            Err(format!("offset {} is misaligned for {}", offset, found))
+        },
+        _ => Ok(()),
+    }
+}
+
+fn expect_type(expected: Type, found: Type) -> Result<(), String> {
+    match expected == found {
+        true  => Ok(()),
+        false => Err(format!("expected {} but found {}", expected, found)),
+    }
+}
+
+/// Check that values of the types `found` are what a function returns.
+fn expect_returns(expected: &[Type], found: &[Type]) -> Result<(), String> {
+    if expected.len() != found.len() {
+        return Err(format!(
+            "expected {} returned values but found {}", expected.len(), found.len(),
+        ));
+    }
+    for (&expected, &found) in expected.iter().zip(found) {
+        expect_type(expected, found)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify;
+    use ir::{ Block, Function, InstructionKind, Module, Target, Terminator, Type };
+
+    /// `f(v0: i32) -> i32`, whose entry block branches on `v0` to `block1`
+    /// or `block2`, each of which goes to `block3`, taking an `i32`.
+    ///
+    fn diamond() -> Function {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let condition = function.add_parameter(entry, Type::I32);
+        for _ in 0..3 {
+            function.new_block();
+        }
+        function.add_parameter(Block(3), Type::I32);
+
+        function.terminate(entry, Terminator::CondBranch(
+            condition, Target::new(Block(1)), Target::new(Block(2)),
+        ));
+        for block in [Block(1), Block(2)] {
+            let value = function.push(block, Type::I32, InstructionKind::Const(1));
+            function.terminate(block, Terminator::Branch(Target {
+                block: Block(3),
+                arguments: vec![value],
+            }));
+        }
+        let joined = function.block(Block(3)).parameters[0];
+        function.terminate(Block(3), Terminator::Return(vec![joined]));
+        function
+    }
+
+    fn check(function: Function) -> Result<(), String> {
+        verify(&Module { functions: vec![function] })
+    }
+
+    /// Check that `function` fails to verify, with a message containing
+    /// `expected`.
+    ///
+    fn rejects(function: Function, expected: &str) {
+        match check(function) {
+            Ok(()) => panic!("verified, but expected `{}`", expected),
+            Err(message) => assert!(
+                message.contains(expected), "expected `{}` but got `{}`", expected, message,
+            ),
+        }
+    }
+
+    #[test]
+    fn accepts_well_formed() {
+        assert_eq!(check(diamond()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_use_of_undefined_value() {
+        let mut function = diamond();
+        let undefined = function.new_value(Type::I32);
+        function.block_mut(Block(3)).terminator = Some(Terminator::Return(vec![undefined]));
+        rejects(function, "block3 uses undefined v");
+    }
+
+    #[test]
+    fn rejects_use_not_dominated_by_definition() {
+        let mut function = diamond();
+        let from_block1 = function.block(Block(1)).instructions[0].result;
+        function.block_mut(Block(3)).terminator = Some(Terminator::Return(vec![from_block1]));
+        rejects(function, "is not dominated by its definition");
+    }
+
+    #[test]
+    fn rejects_use_before_definition_in_block() {
+        let mut function = diamond();
+        let later = function.push(Block(1), Type::I32, InstructionKind::Const(2));
+        function.block_mut(Block(1)).instructions[0].kind = InstructionKind::Convert(later);
+        rejects(function, "is not dominated by its definition");
+    }
+
+    #[test]
+    fn rejects_missing_terminator() {
+        let mut function = diamond();
+        function.block_mut(Block(2)).terminator = None;
+        rejects(function, "block2 has no terminator");
+    }
+
+    #[test]
+    fn rejects_wrong_number_of_branch_arguments() {
+        let mut function = diamond();
+        function.block_mut(Block(1)).terminator = Some(Terminator::Branch(Target::new(Block(3))));
+        rejects(function, "block3 takes 1 arguments but was given 0");
+    }
+
+    #[test]
+    fn rejects_branch_argument_of_wrong_type() {
+        let mut function = diamond();
+        let wide = function.push(Block(2), Type::I64, InstructionKind::Const(1));
+        function.block_mut(Block(2)).terminator = Some(Terminator::Branch(Target {
+            block: Block(3),
+            arguments: vec![wide],
+        }));
+        rejects(function, "expected i32 but found i64");
+    }
+
+    #[test]
+    fn rejects_block_parameter_defined_twice() {
+        let mut function = diamond();
+        let parameter = function.block(Block(3)).parameters[0];
+        function.block_mut(Block(2)).parameters.push(parameter);
+        rejects(function, "is defined more than once");
+    }
+
+    #[test]
+    fn rejects_block_parameter_used_outside_its_block() {
+        let mut function = diamond();
+        let parameter = function.block(Block(3)).parameters[0];
+        function.block_mut(Block(1)).instructions[0].kind = InstructionKind::Convert(parameter);
+        rejects(function, "is not dominated by its definition");
+    }
+
+    #[test]
+    fn rejects_branch_to_entry() {
+        let mut function = diamond();
+        function.block_mut(Block(1)).terminator = Some(Terminator::Branch(Target {
+            block: Block(0),
+            arguments: Vec::new(),
+        }));
+        rejects(function, "branch to the entry block");
+    }
+
+    #[test]
+    fn rejects_return_of_wrong_type() {
+        let mut function = diamond();
+        let wide = function.push(Block(3), Type::I64, InstructionKind::Const(1));
+        function.block_mut(Block(3)).terminator = Some(Terminator::Return(vec![wide]));
+        rejects(function, "expected i32 but found i64");
+    }
+}
@@ -1,32 +1,75 @@
-extern crate phf;
 extern crate enum_map;
 
+use std::mem;
+
 #[derive (Debug, Copy, Clone, PartialEq, Enum)]
 pub enum TokenKind {
     // 'Grammar'
     GramComma,
     GramSemicolon,
     GramColon,
+    /// `::`, between an enum's name and a variant's.
+    GramPath,
+    /// `=>`, between a `match` arm's pattern and its body.
+    GramFatArrow,
+    /// `_`, the pattern that matches anything.
+    GramUnderscore,
+    /// `.`, before a field's name.
+    GramDot,
+    /// `->`, before a return type.
+    GramArrow,
+    /// `#`, to the end of the line.
     GramComment,
+    /// `##`, to the end of the line; kept as the `doc` of the next token.
+    GramDocComment,
+    /// `#[`, which `]#` closes; these nest, and may span lines.
+    GramCommentOpen,
+    GramCommentClose,
 
     BraceOpen,
     BraceClose,
     ParenOpen,
     ParenClose,
+    BracketOpen,
+    BracketClose,
 
     // 'Operation'
     OpAssign,
     OpAdd,
     OpSub,
+    /// `*`, also to dereference a reference.
     OpMul,
+    /// `&`, to take a reference, or before a type to make a reference type.
+    OpAmp,
+    OpEq,
+    OpNe,
+    OpLt,
+    OpLe,
+    OpGt,
+    OpGe,
 
     // 'Keyword'
     KeyReturn,
     KeyIf, // @OPTION do I want if/else?
     KeyElse,
+    KeyAs,
+    KeyStruct,
+    KeyEnum,
+    KeyMatch,
+    /// `mut`, after `&` for a reference that can be assigned through.
+    KeyMut,
 
     // 'Literal'
+    /// Decimal, or hex, octal or binary with `0x`, `0o` or `0b`, with any `_`
+    /// separators and an optional type suffix; see `get_integer()`.
     LitInteger,
+    /// Decimal, with a fraction, an exponent or both, or an `f32` or `f64`
+    /// suffix; see `get_float()`.
+    LitFloat,
+    /// Double-quoted, with escapes; see `unescape()`.
+    LitString,
+    /// Single-quoted, with the same escapes; see `get_char()`.
+    LitChar,
 
     // 'Other': placeholder name
     OthName,
@@ -48,72 +91,148 @@ struct TokenProperties {
     pub can_follow: TokenCanFollowImmediately,
 }
 
-static SYMBOLS: phf::Map<&'static str, TokenProperties> = phf_map! {
-    "," => TokenProperties {
+static SYMBOLS: &[(&str, TokenProperties)] = &[
+    (",", TokenProperties {
         kind: TokenKind::GramComma,
-        can_follow: TokenCanFollowImmediately::Can
-    },
-    ";" => TokenProperties {
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    (";", TokenProperties {
         kind: TokenKind::GramSemicolon,
         can_follow: TokenCanFollowImmediately::Can,
-    },
-    ":" => TokenProperties {
+    }),
+    ("::", TokenProperties {
+        kind: TokenKind::GramPath,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    (":", TokenProperties {
         kind: TokenKind::GramColon,
         can_follow: TokenCanFollowImmediately::Can,
-    },
-    "#" => TokenProperties {
+    }),
+    (".", TokenProperties {
+        kind: TokenKind::GramDot,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    // The longer symbols must come first, since the first match is taken.
+    ("##", TokenProperties {
+        kind: TokenKind::GramDocComment,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    ("#[", TokenProperties {
+        kind: TokenKind::GramCommentOpen,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    ("]#", TokenProperties {
+        kind: TokenKind::GramCommentClose,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    ("#", TokenProperties {
         kind: TokenKind::GramComment,
         can_follow: TokenCanFollowImmediately::Can,
-    },
+    }),
 
-    "=" => TokenProperties {
+    ("->", TokenProperties {
+        kind: TokenKind::GramArrow,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+
+    ("=>", TokenProperties {
+        kind: TokenKind::GramFatArrow,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    ("==", TokenProperties {
+        kind: TokenKind::OpEq,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    ("!=", TokenProperties {
+        kind: TokenKind::OpNe,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    ("<=", TokenProperties {
+        kind: TokenKind::OpLe,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    (">=", TokenProperties {
+        kind: TokenKind::OpGe,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    ("<", TokenProperties {
+        kind: TokenKind::OpLt,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    (">", TokenProperties {
+        kind: TokenKind::OpGt,
+        can_follow: TokenCanFollowImmediately::Cannot,
+    }),
+    ("=", TokenProperties {
         kind: TokenKind::OpAssign,
         can_follow: TokenCanFollowImmediately::Cannot,
-    },
-    "+" => TokenProperties {
+    }),
+    ("+", TokenProperties {
         kind: TokenKind::OpAdd,
         can_follow: TokenCanFollowImmediately::Cannot,
-    },
-    "-" => TokenProperties {
+    }),
+    ("-", TokenProperties {
         kind: TokenKind::OpSub,
         can_follow: TokenCanFollowImmediately::Cannot,
-    },
-    "*" => TokenProperties {
+    }),
+    // These are also prefix operators, as in `f(*a, &b)`.
+    ("*", TokenProperties {
         kind: TokenKind::OpMul,
-        can_follow: TokenCanFollowImmediately::Cannot,
-    },
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    ("&", TokenProperties {
+        kind: TokenKind::OpAmp,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
 
-    "{" => TokenProperties {
+    ("{", TokenProperties {
         kind: TokenKind::BraceOpen,
         can_follow: TokenCanFollowImmediately::Cannot,
-    },
-    "}" => TokenProperties {
+    }),
+    ("}", TokenProperties {
         kind: TokenKind::BraceClose,
         can_follow: TokenCanFollowImmediately::Cannot,
-    },
-    "(" => TokenProperties {
+    }),
+    ("(", TokenProperties {
         kind: TokenKind::ParenOpen,
         can_follow: TokenCanFollowImmediately::Can,
-    },
-    ")" => TokenProperties {
+    }),
+    (")", TokenProperties {
         kind: TokenKind::ParenClose,
         can_follow: TokenCanFollowImmediately::Can,
-    },
-};
+    }),
+    ("[", TokenProperties {
+        kind: TokenKind::BracketOpen,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+    ("]", TokenProperties {
+        kind: TokenKind::BracketClose,
+        can_follow: TokenCanFollowImmediately::Can,
+    }),
+];
 
 /// Key-words are kept separately from symbols because the former must be
 /// delimited from other tokens, where the latter have exceptions.
 ///
-static KEYWORDS: phf::Map<&'static str, TokenKind> = phf_map! {
-    "return" => TokenKind::KeyReturn,
-    "if"     => TokenKind::KeyIf,
-    "else"   => TokenKind::KeyElse,
-};
+static KEYWORDS: &[(&str, TokenKind)] = &[
+    ("return", TokenKind::KeyReturn),
+    ("if", TokenKind::KeyIf),
+    ("else", TokenKind::KeyElse),
+    ("as", TokenKind::KeyAs),
+    ("struct", TokenKind::KeyStruct),
+    ("enum", TokenKind::KeyEnum),
+    ("match", TokenKind::KeyMatch),
+    ("mut", TokenKind::KeyMut),
+];
 
 #[derive (Debug)]
 pub struct Token<'a> {
     pub kind: TokenKind,
     pub source: &'a str,
+    /// The lines of any doc comment just before this token, without their
+    /// `##`. This is trivia, like other comments, but it's kept for whatever
+    /// follows to document itself with (see `function::Syntax`).
+    pub doc: Vec<&'a str>,
 }
 
 impl<'a> Token<'a> {
@@ -124,6 +243,7 @@ impl<'a> Token<'a> {
         Token {
             kind,
             source,
+            doc: Vec::new(),
         }
     }
 }
@@ -141,6 +261,16 @@ struct TokenIter<'a> {
     /// Whether there was whitespace (or the start or end to a line) immediately
     /// before the start of `word`.
     whitespace: WhitespaceState,
+
+    /// Whether a `"` or `'` starts a string or character literal. Within
+    /// comments they don't, so that e.g. a lone `"` can't hide the end of a
+    /// block comment.
+    strings: bool,
+
+    /// The last token, if it was a symbol. A few tokens may follow certain
+    /// symbols without whitespace: a key-word after `&`, as in `&mut`, and
+    /// `=` after `:`, as in `a := b`.
+    previous: Option<TokenKind>,
 }
 
 impl <'a> TokenIter<'a> {
@@ -148,6 +278,8 @@ impl <'a> TokenIter<'a> {
         TokenIter {
             word,
             whitespace: WhitespaceState::StartOfLine,
+            strings: true,
+            previous: None,
         }
     }
 
@@ -196,15 +328,18 @@ impl <'a> TokenIter<'a> {
     fn eat_name(&mut self) -> Token<'a> {
         assert_ne!(self.word.len(), 0);
 
-        let mut valid = true;
-
         let mut remaining = self.word;
-        // Note len() is number of bytes, not chars. We'll stick to bytes (we
-        // are enforcing ASCII anyway).
-        while remaining.len() > 0 {
+        // Note len() is number of bytes, not chars. Symbols are all ASCII, so
+        // we can match them bytewise, but we must advance by whole chars so
+        // as never to slice one in half; non-ASCII chars simply make the name
+        // invalid.
+        while !remaining.is_empty() {
             if remaining.bytes().next().unwrap().is_ascii_whitespace() {
                 break;
             }
+            if self.strings && remaining.starts_with(['"', '\'']) {
+                break;
+            }
 
             // Don't just break on invalidity; break on either whitespace or an
             // in principle valid symbol.
@@ -212,29 +347,26 @@ impl <'a> TokenIter<'a> {
             // invalid characters as one Token, rather than acting naively as if
             // they were completely unrelated invalidities.
 
-            let mut found_symbol = None;
-            let mut len_max_symbol = 0;
-            for symbol in SYMBOLS.keys() {
-                if symbol.len() > self.word.len() {
-                    continue;
-                }
-                let trunc = &remaining[..symbol.len()];
-
-                if symbol.len() > len_max_symbol {
-                    len_max_symbol = symbol.len();
-                }
-
-                if *symbol == trunc {
-                    found_symbol = Some(*symbol);
-                    break;
-                }
-            }
+            let found_symbol = SYMBOLS.iter()
+                .find(|&&(symbol, _)| remaining.starts_with(symbol));
+
+            // The sign of a float's exponent, as in `3.0e-4`, isn't an
+            // operator, and nor is its point, if digits follow it. So `t.0.x`
+            // is `0` then `.x`, though `t.0.1` has the float `0.1`.
+            let name = &self.word[..self.word.len() - remaining.len()];
+            let in_exponent = is_decimal(name) && !name.contains(['i', 'u']) &&
+                (name.ends_with('e') || name.ends_with('E')) &&
+                (remaining.starts_with('-') || remaining.starts_with('+'));
+            let in_float = is_decimal(name) && !name.contains('.') &&
+                remaining.strip_prefix('.').is_some_and(
+                    |fraction| fraction.bytes().next().is_some_and(|digit| digit.is_ascii_digit())
+                );
 
             match found_symbol {
+                Some(_) if in_exponent || in_float => remaining = &remaining[1..],
                 None => {
-                    // len_max_symbol is the length we've checked against
-                    // symbols; we can advance that much.
-                    remaining = &remaining[len_max_symbol..];
+                    let len_char = remaining.chars().next().unwrap().len_utf8();
+                    remaining = &remaining[len_char..];
                 },
                 Some(_) => {
                     // Don't change remaining; it correctly starts just after
@@ -244,12 +376,8 @@ impl <'a> TokenIter<'a> {
             }
         }
 
-        // @OPTION is there a better way to compare slices?
-        //
-        let len_name = unsafe {
-            remaining.as_ptr().offset_from(self.word.as_ptr())
-        };
-        let len_name = len_name as usize;
+        // `remaining` is always a suffix of `word`.
+        let len_name = self.word.len() - remaining.len();
 
         let name = &self.word[..len_name];
 
@@ -259,6 +387,31 @@ impl <'a> TokenIter<'a> {
 
         Token::new(kind, name)
     }
+
+    /// Advance past a string or character literal, from its opening `quote`
+    /// to its closing one, or to the end of the line if there isn't one
+    /// (which `unescape()` or `get_char()` will report).
+    ///
+    fn eat_quoted(&mut self, quote: char, kind: TokenKind) -> Token<'a> {
+        let mut escaped = false;
+        let mut len_string = self.word.len();
+        for (index, this_char) in self.word.char_indices().skip(1) {
+            match this_char {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if this_char == quote => {
+                    len_string = index + 1;
+                    break;
+                },
+                _ => (),
+            }
+        }
+
+        let string = &self.word[..len_string];
+        self.word = &self.word[len_string..];
+
+        Token::new(kind, string)
+    }
 }
 
 impl <'a> Iterator for TokenIter<'a> {
@@ -269,26 +422,33 @@ impl <'a> Iterator for TokenIter<'a> {
         // last word (or line).
         //
         self.eat_whitespace();
+        let previous = self.previous.take();
 
         // The only case where we stop iterating is when we run out of word.
         //
-        if self.word.len() == 0 {
+        if self.word.is_empty() {
             return None;
         }
 
+        // Strings and chars can contain anything, so they come first.
+        if self.strings && self.word.starts_with('"') {
+            return Some(self.eat_quoted('"', TokenKind::LitString));
+        }
+        if self.strings && self.word.starts_with('\'') {
+            return Some(self.eat_quoted('\'', TokenKind::LitChar));
+        }
+
         // Try to match a symbol or key-word.
 
         for (symbol, TokenProperties {
             kind, can_follow,
-        }) in SYMBOLS.entries() {
-            if symbol.len() > self.word.len() {
-                continue;
-            }
-            // Note Range for str is in bytes, not chars (as is len()).
-            let trunc = &self.word[..symbol.len()];
-
-            if *symbol == trunc {
+        }) in SYMBOLS {
+            // Note Range for str is in bytes, not chars (as is len()); this is
+            // only a char boundary because the match succeeded.
+            if self.word.starts_with(symbol) {
+                let trunc = &self.word[..symbol.len()];
                 self.word = &self.word[symbol.len()..];
+                self.previous = Some(*kind);
 
                 return match self.whitespace {
                     WhitespaceState::NoWhitespace => {
@@ -298,6 +458,11 @@ impl <'a> Iterator for TokenIter<'a> {
                         //
                         match can_follow {
                             TokenCanFollowImmediately::Can => Some(Token::new(*kind, trunc)),
+                            TokenCanFollowImmediately::Cannot
+                                if *kind == TokenKind::OpAssign &&
+                                   previous == Some(TokenKind::GramColon) => {
+                                Some(Token::new(*kind, trunc))
+                            },
                             TokenCanFollowImmediately::Cannot => Some(Token::new(TokenKind::OthInvalid, trunc)),
                         }
                     },
@@ -306,13 +471,10 @@ impl <'a> Iterator for TokenIter<'a> {
             }
         }
 
-        for (keyword, token) in KEYWORDS.entries() {
-            if keyword.len() > self.word.len() {
-                continue;
-            }
-            let trunc = &self.word[..keyword.len()];
+        for (keyword, token) in KEYWORDS {
+            if self.word.starts_with(keyword) {
+                let trunc = &self.word[..keyword.len()];
 
-            if *keyword == trunc {
                 // This could still be a name, e.g. main_a.
                 // We must check what comes afterward.
                 //
@@ -324,8 +486,12 @@ impl <'a> Iterator for TokenIter<'a> {
                     // character within a name - not actual validity, which will
                     // be checked by eat_name().
                     //
-                    if next_char == '_' as u8 ||
-                       next_char.is_ascii_alphanumeric() {
+                    // Non-ASCII chars count too, so that e.g. `ifé` is lexed
+                    // as one (invalid) name.
+                    //
+                    if next_char == b'_' ||
+                       next_char.is_ascii_alphanumeric() ||
+                       !next_char.is_ascii() {
                         // continue, not break, in case some symbols contain
                         // others, as e.g. += contains +.
                         continue;
@@ -334,15 +500,17 @@ impl <'a> Iterator for TokenIter<'a> {
 
                 self.word = &self.word[keyword.len()..];
 
-                // Key-words must be preceded by whitespace; this determines
-                // validity as a key-word.
+                // Key-words must be preceded by whitespace (or `&`); this
+                // determines validity as a key-word.
                 //
                 // @OPTION technically this is backwards; we could have checked
                 // higher up whether there was whitespace, and if not, only
                 // matched against symbols.
                 //
                 return match self.whitespace {
-                    WhitespaceState::NoWhitespace => Some(Token::new(TokenKind::OthInvalid, trunc)),
+                    WhitespaceState::NoWhitespace if previous != Some(TokenKind::OpAmp) => {
+                        Some(Token::new(TokenKind::OthInvalid, trunc))
+                    },
                     _ => Some(Token::new(*token, trunc)),
                 };
             }
@@ -357,46 +525,147 @@ impl <'a> Iterator for TokenIter<'a> {
 
 /// The state of lexing with regard to comments.
 ///
-#[derive (Debug, Copy, Clone)]
+#[derive (Debug, Copy, Clone, PartialEq)]
 enum LexerState {
     Lexing,
-    MidComment,
+    /// Until the end of the line.
+    LineComment,
+    /// Within this many block comments.
+    BlockComment(usize),
 }
 
+#[derive (Default)]
 pub struct Lexer {}
 
 impl Lexer {
     pub fn new() -> Self { Self {} }
 
+    /// Split `source` into `Token`s, leaving out comments.
+    ///
+    /// A `]#` with no block comment open is a `]` followed by a line comment,
+    /// or a block comment if it's `]#[`.
+    ///
+    /// Fails on a block comment that's never closed, and on a bad integer or
+    /// string literal; anything else that isn't a valid token is left to the
+    /// parser as `TokenKind::OthInvalid`.
+    ///
     pub fn lex<'a>(
         &self,
         source: &'a str,
-    ) -> Vec<Token<'a>> {
+    ) -> Result<Vec<Token<'a>>, String> {
         let mut tokens = Vec::new();
 
+        // Block comments carry over from line to line; line comments don't.
+        let mut state = LexerState::Lexing;
+        // Where the outermost open block comment started, as (line, column).
+        let mut opening = (0, 0);
+        // Doc comment lines yet to be given to a token.
+        let mut doc = Vec::new();
+
         // Iterators are lazy
         //
         // @OPTION however, this will still probably search for a \n every time
         // rather than simply stopping when it hits one. Custom iterators?
         //
         let lines = source.lines();
-        for line in lines {
-            let mut state = LexerState::Lexing;
+        for (index, line) in lines.enumerate() {
+            if state == LexerState::LineComment {
+                state = LexerState::Lexing;
+            }
 
             let mut iter = TokenIter::new(line);
+            loop {
+                iter.strings = state == LexerState::Lexing;
+                let token = match iter.next() {
+                    Some(token) => token,
+                    None => break,
+                };
+
+                // The iterator has just advanced past `token`.
+                let offset = line.len() - iter.word.len() - token.source.len();
+                let position = (index + 1, line[..offset].chars().count() + 1);
+
+                match (state, token.kind) {
+                    (LexerState::Lexing, TokenKind::GramCommentOpen) => {
+                        opening = position;
+                    },
+                    (LexerState::Lexing, TokenKind::GramDocComment) => {
+                        // The rest of the line is the comment.
+                        let text = &line[offset + token.source.len()..];
+                        doc.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+                        break;
+                    },
+                    (LexerState::Lexing, TokenKind::LitInteger) => {
+                        if let Err(message) = get_integer(token.source) {
+                            return Err(format!(
+                                "{}, at line {}, column {}",
+                                message, position.0, position.1,
+                            ));
+                        }
+                    },
+                    (LexerState::Lexing, TokenKind::LitFloat) => {
+                        if let Err(message) = get_float(token.source) {
+                            return Err(format!(
+                                "{}, at line {}, column {}",
+                                message, position.0, position.1,
+                            ));
+                        }
+                    },
+                    (LexerState::Lexing, TokenKind::LitString) => {
+                        if let Err(message) = unescape(token.source) {
+                            return Err(format!(
+                                "{}, at line {}, column {}",
+                                message, position.0, position.1,
+                            ));
+                        }
+                    },
+                    (LexerState::Lexing, TokenKind::LitChar) => {
+                        if let Err(message) = get_char(token.source) {
+                            return Err(format!(
+                                "{}, at line {}, column {}",
+                                message, position.0, position.1,
+                            ));
+                        }
+                    },
+                    (LexerState::Lexing, TokenKind::GramCommentClose) => {
+                        // Nothing to close, so it's a `]` and then a comment:
+                        // a line comment, as in `a[0]# first`, or a block
+                        // comment, as in `a[0]#[ first ]#`.
+                        let mut bracket = Token::new(TokenKind::BracketClose, &token.source[..1]);
+                        bracket.doc = mem::take(&mut doc);
+                        tokens.push(bracket);
+                        match iter.word.strip_prefix('[') {
+                            Some(rest) => {
+                                iter.word = rest;
+                                opening = (position.0, position.1 + 1);
+                                state = LexerState::BlockComment(1);
+                            },
+                            None => state = LexerState::LineComment,
+                        }
+                        continue;
+                    },
+                    _ => (),
+                }
 
-            while let Some(token) = iter.next() {
                 let (new_token, new_state) = Self::handle_comments(token, state);
 
                 state = new_state;
 
-                if let Some(some_token) = new_token {
+                if let Some(mut some_token) = new_token {
+                    some_token.doc = mem::take(&mut doc);
                     tokens.push(some_token);
                 }
             }
         }
 
-        tokens
+        if let LexerState::BlockComment(_) = state {
+            return Err(format!(
+                "unterminated block comment, opened at line {}, column {}",
+                opening.0, opening.1,
+            ));
+        }
+
+        Ok(tokens)
     }
 
     /// Decide whether or not to keep `token`, given `state`, the state of
@@ -408,23 +677,37 @@ impl Lexer {
         token: Token<'a>,
         state: LexerState,
     ) -> (Option<Token<'a>>, LexerState) {
-        match (&state, &token.kind) {
+        match (state, token.kind) {
             // Start ignoring on comment
-            (&LexerState::Lexing, &TokenKind::GramComment) => {
-                (None, LexerState::MidComment)
+            (LexerState::Lexing, TokenKind::GramComment) => {
+                (None, LexerState::LineComment)
+            },
+            (LexerState::Lexing, TokenKind::GramCommentOpen) => {
+                (None, LexerState::BlockComment(1))
             },
 
-            // Stop ignoring on end of comment
-            (&LexerState::MidComment, &TokenKind::GramComment) => {
+            // Nothing ends a line comment but the end of the line.
+            (LexerState::LineComment, _) => {
+                (None, LexerState::LineComment)
+            },
+
+            // Block comments nest; only the outermost close ends the comment.
+            (LexerState::BlockComment(depth), TokenKind::GramCommentOpen) => {
+                (None, LexerState::BlockComment(depth + 1))
+            },
+            (LexerState::BlockComment(1), TokenKind::GramCommentClose) => {
                 (None, LexerState::Lexing)
             },
+            (LexerState::BlockComment(depth), TokenKind::GramCommentClose) => {
+                (None, LexerState::BlockComment(depth - 1))
+            },
 
             // Ignore when mid-comment
-            (&LexerState::MidComment, _) => {
-                (None, LexerState::MidComment)
+            (LexerState::BlockComment(depth), _) => {
+                (None, LexerState::BlockComment(depth))
             },
 
-            (&LexerState::Lexing, _) => {
+            (LexerState::Lexing, _) => {
                 (Some(token), LexerState::Lexing)
             },
         }
@@ -432,9 +715,22 @@ impl Lexer {
 }
 
 fn lex_name(source: &str) -> TokenKind {
-    if is_valid_integer(source) {
+    // Names can't start with a digit, so anything that does is meant as a
+    // number; whether it's a valid one is checked by `get_integer()` or
+    // `get_float()`. (An integer suffix, like `usize`, may have an `e`.)
+    let before_suffix = source.split(['i', 'u']).next().unwrap();
+    if is_decimal(source) && (
+        before_suffix.contains(['.', 'e', 'E']) ||
+        source.ends_with("f32") || source.ends_with("f64")
+    ) {
+        return TokenKind::LitFloat;
+    }
+    if source.bytes().next().unwrap().is_ascii_digit() {
         return TokenKind::LitInteger;
     }
+    else if source == "_" {
+        return TokenKind::GramUnderscore;
+    }
     else if is_valid_name(source) {
         return TokenKind::OthName;
     }
@@ -445,9 +741,10 @@ fn is_valid_name(mut source: &str) -> bool {
     assert_ne!(source.len(), 0);
 
     // Exceptions for first char
-    // @OPTION as in Rust, accept leading underscore
+    // As in Rust, accept a leading underscore; it marks a name as deliberately
+    // unused (see `lint`). A lone `_` is still caught below.
     let first_char = source.bytes().next().unwrap();
-    if !first_char.is_ascii_alphabetic() {
+    if !first_char.is_ascii_alphabetic() && first_char != b'_' {
         return false;
     }
     let mut prev_char = first_char;
@@ -456,9 +753,9 @@ fn is_valid_name(mut source: &str) -> bool {
 
     for this_char in source.bytes() {
         // Allow _ as exception to alphanumericality
-        if this_char == '_' as u8 {
+        if this_char == b'_' {
             // Don't allow __
-            if prev_char == '_' as u8 {
+            if prev_char == b'_' {
                 return false;
             }
         }
@@ -470,25 +767,592 @@ fn is_valid_name(mut source: &str) -> bool {
     }
 
     // Exceptions for last char: don't accept _
-    if prev_char == '_' as u8 {
+    if prev_char == b'_' {
         return false;
     }
     true
 }
 
-fn is_valid_integer(source: &str) -> bool {
-    match get_integer(source) {
-        Some(_) => true,
-        None => false,
+/// The type an integer literal's suffix gives it.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IntegerType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    ISize,
+    USize,
+}
+
+impl IntegerType {
+    const ALL: [IntegerType; 10] = [
+        IntegerType::I8,
+        IntegerType::I16,
+        IntegerType::I32,
+        IntegerType::I64,
+        IntegerType::U8,
+        IntegerType::U16,
+        IntegerType::U32,
+        IntegerType::U64,
+        IntegerType::ISize,
+        IntegerType::USize,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntegerType::I8  => "i8",
+            IntegerType::I16 => "i16",
+            IntegerType::I32 => "i32",
+            IntegerType::I64 => "i64",
+            IntegerType::U8  => "u8",
+            IntegerType::U16 => "u16",
+            IntegerType::U32 => "u32",
+            IntegerType::U64 => "u64",
+            IntegerType::ISize => "isize",
+            IntegerType::USize => "usize",
+        }
+    }
+
+    /// The largest value of the type.
+    pub fn max(self) -> u64 {
+        match self {
+            IntegerType::I8  => i8::MAX as u64,
+            IntegerType::I16 => i16::MAX as u64,
+            IntegerType::I32 => i32::MAX as u64,
+            IntegerType::I64 => i64::MAX as u64,
+            IntegerType::U8  => u8::MAX as u64,
+            IntegerType::U16 => u16::MAX as u64,
+            IntegerType::U32 => u32::MAX as u64,
+            IntegerType::U64 => u64::MAX,
+            IntegerType::ISize => i64::MAX as u64,
+            IntegerType::USize => u64::MAX,
+        }
+    }
+}
+
+/// The value of an integer literal, and its type if it has a suffix.
+///
+/// Literals can't be negative; `-` is always an operator.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Integer {
+    pub value: u64,
+    pub ty: Option<IntegerType>,
+}
+
+/// Parse an integer literal.
+///
+/// A literal is decimal, or hex, octal or binary with a `0x`, `0o` or `0b`
+/// prefix. Its digits may be separated by `_`, and it may end with a type
+/// suffix, such as `u8` (which may also follow an `_`). Its value must fit
+/// its type; without a suffix, the type is inferred later, so here it need
+/// only fit a `u64`.
+///
+pub fn get_integer(source: &str) -> Result<Integer, String> {
+    let (radix, rest) = match source.get(..2) {
+        Some("0x") => (16, &source[2..]),
+        Some("0o") => (8, &source[2..]),
+        Some("0b") => (2, &source[2..]),
+        _ => (10, source),
+    };
+
+    // No digit in any radix is an `i` or `u`, so a suffix starts at the first.
+    let (digits, suffix) = match rest.find(['i', 'u']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+
+    let ty = match suffix {
+        "" => None,
+        _ => Some(*IntegerType::ALL.iter()
+            .find(|ty| ty.name() == suffix)
+            .ok_or_else(|| format!("unknown integer suffix `{}` in `{}`", suffix, source))?),
+    };
+
+    let digits: String = digits.chars().filter(|&this_char| this_char != '_').collect();
+    if digits.is_empty() {
+        return Err(format!("integer literal `{}` has no digits", source));
+    }
+    if let Some(invalid) = digits.chars().find(|this_char| !this_char.is_digit(radix)) {
+        return Err(format!("invalid digit `{}` in integer literal `{}`", invalid, source));
+    }
+
+    let max = ty.unwrap_or(IntegerType::U64).max();
+    let value = u64::from_str_radix(&digits, radix).ok().filter(|value| *value <= max);
+    match value {
+        Some(value) => Ok(Integer { value, ty }),
+        None => Err(format!(
+            "integer literal `{}` is out of range for {}",
+            source, ty.unwrap_or(IntegerType::U64).name(),
+        )),
+    }
+}
+
+/// Whether `source` starts like a decimal number: with a digit, but not a
+/// `0x`, `0o` or `0b` prefix.
+///
+fn is_decimal(source: &str) -> bool {
+    source.bytes().next().is_some_and(|first| first.is_ascii_digit()) &&
+        !matches!(source.get(..2), Some("0x") | Some("0o") | Some("0b"))
+}
+
+/// The type a float literal's suffix gives it.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FloatType {
+    F32,
+    F64,
+}
+
+impl FloatType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatType::F32 => "f32",
+            FloatType::F64 => "f64",
+        }
     }
 }
 
-/// Parse an integer.
-pub fn get_integer(source: &str) -> Option<i32> {
-    // @TODO technically Rust's integer format won't be ok because it includes
-    // e.g., 1i32
-    match source.parse::<i32>() {
-        Ok(int) => Some(int),
-        Err(_)  => None,
+/// The value of a float literal, and its type if it has a suffix.
+///
+/// As with `Integer`, literals can't be negative.
+///
+#[derive (Debug, Copy, Clone, PartialEq)]
+pub struct Float {
+    pub value: f64,
+    pub ty: Option<FloatType>,
+}
+
+/// Parse a float literal.
+///
+/// A literal is decimal digits with a fraction (`1.5`), an exponent (`2e10`),
+/// or both (`3.0e-4`), and may end with a suffix of `f32` or `f64`. A
+/// fraction must have digits on both sides of the `.`. As with integers,
+/// digits may be separated by `_`. Its value must be finite in its type,
+/// which is `f64` if there's no suffix.
+///
+pub fn get_float(source: &str) -> Result<Float, String> {
+    let (number, ty) = if let Some(number) = source.strip_suffix("f32") {
+        (number, Some(FloatType::F32))
+    }
+    else if let Some(number) = source.strip_suffix("f64") {
+        (number, Some(FloatType::F64))
+    }
+    else {
+        (source, None)
+    };
+    let number: String = number.chars().filter(|&this_char| this_char != '_').collect();
+
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(index) => (&number[..index], Some(&number[index + 1..])),
+        None => (number.as_str(), None),
+    };
+    let digits = |digits: &str| {
+        !digits.is_empty() && digits.bytes().all(|digit| digit.is_ascii_digit())
+    };
+    let valid = match mantissa.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(mantissa),
+    } && exponent.is_none_or(|exponent| {
+        digits(exponent.strip_prefix(['-', '+']).unwrap_or(exponent))
+    });
+    if !valid {
+        return Err(format!("invalid float literal `{}`", source));
+    }
+
+    let value: f64 = number.parse()
+        .map_err(|_| format!("invalid float literal `{}`", source))?;
+    let finite = match ty {
+        Some(FloatType::F32) => (value as f32).is_finite(),
+        _ => value.is_finite(),
+    };
+    match finite {
+        true  => Ok(Float { value, ty }),
+        false => Err(format!(
+            "float literal `{}` is out of range for {}",
+            source, ty.unwrap_or(FloatType::F64).name(),
+        )),
+    }
+}
+
+/// Get the contents of a string literal, with its escapes replaced.
+///
+/// The escapes are `\n`, `\t`, `\\`, `\"`, `\'`, and `\u{...}` for any
+/// Unicode scalar value, in hex. Strings can't contain NUL, since they're
+/// NUL-terminated in every back-end.
+///
+pub fn unescape(source: &str) -> Result<String, String> {
+    let string = unescape_quoted(source, '"', "string literal")?;
+    match string.contains('\0') {
+        true  => Err("strings can't contain NUL".to_string()),
+        false => Ok(string),
+    }
+}
+
+/// Get the value of a character literal, which has the escapes of a string
+/// literal (see `unescape()`), and may be NUL.
+///
+pub fn get_char(source: &str) -> Result<char, String> {
+    let contents = unescape_quoted(source, '\'', "character literal")?;
+    let mut chars = contents.chars();
+    match (chars.next(), chars.next()) {
+        (Some(this_char), None) => Ok(this_char),
+        _ => Err(format!("character literal {} isn't one character", source)),
+    }
+}
+
+/// Get the contents of `source`, a `what` between `quote`s, with its escapes
+/// replaced.
+///
+fn unescape_quoted(source: &str, quote: char, what: &str) -> Result<String, String> {
+    let mut chars = source.chars();
+    if chars.next() != Some(quote) {
+        return Err(format!("{} doesn't start with `{}`", what, quote));
+    }
+
+    let mut string = String::new();
+    loop {
+        let this_char = match chars.next() {
+            Some(this_char) if this_char == quote => break,
+            Some(this_char) => this_char,
+            None => return Err(format!("unterminated {}", what)),
+        };
+        if this_char != '\\' {
+            string.push(this_char);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('u') => unescape_unicode(&mut chars)?,
+            Some(other) => return Err(format!("unknown escape `\\{}` in {}", other, what)),
+            None => return Err(format!("unterminated {}", what)),
+        };
+        string.push(escaped);
+    }
+
+    if chars.next().is_some() {
+        return Err(format!("{} continues after its closing `{}`", what, quote));
+    }
+    Ok(string)
+}
+
+/// Decode the `{...}` of a `\u{...}` escape.
+///
+fn unescape_unicode(chars: &mut std::str::Chars) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err("expected `{` after `\\u`".to_string());
+    }
+
+    let mut digits = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(digit) if digit.is_ascii_hexdigit() && digits.len() < 6 => digits.push(digit),
+            _ => return Err("expected 1 to 6 hex digits and `}` after `\\u{`".to_string()),
+        }
+    }
+
+    let scalar = u32::from_str_radix(&digits, 16)
+        .map_err(|_| "expected hex digits after `\\u{`".to_string())?;
+    match std::char::from_u32(scalar) {
+        Some(this_char) => Ok(this_char),
+        None => Err(format!("`\\u{{{}}}` is not a Unicode scalar value", digits)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        get_float, get_integer, lex_name, Float, FloatType, Integer, IntegerType, Lexer,
+        TokenKind,
+    };
+
+    /// The source of each token in `source`.
+    ///
+    fn sources(source: &str) -> Vec<&str> {
+        Lexer::new().lex(source).unwrap().iter().map(|token| token.source).collect()
+    }
+
+    fn error(source: &str) -> String {
+        Lexer::new().lex(source).unwrap_err()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn line_comment_ends_with_line() {
+        assert_eq!(sources("a # b c\nd"), ["a", "d"]);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(sources("a #[ b #[ c ]# d ]# e"), ["a", "e"]);
+        assert_eq!(sources("a #[ #[ #[ ]# ]# b ]# c"), ["a", "c"]);
+        assert_eq!(
+            error("a #[ #[ ]# b"),
+            "unterminated block comment, opened at line 1, column 3",
+        );
+    }
+
+    #[test]
+    fn block_comment_spans_lines() {
+        assert_eq!(sources("a #[ b\nc\n  d ]# e\nf"), ["a", "e", "f"]);
+    }
+
+    #[test]
+    fn block_comment_ignores_line_comments_and_quotes() {
+        // Neither `#` nor `"` hides the close.
+        assert_eq!(sources("a #[ # b ]# c"), ["a", "c"]);
+        assert_eq!(sources("a #[ \" b ]# c \"d\""), ["a", "c", "\"d\""]);
+        assert_eq!(sources("a #[ ' ]# c"), ["a", "c"]);
+    }
+
+    #[test]
+    fn unterminated_block_comment_gives_where_it_opened() {
+        assert_eq!(
+            error("a\n  b #[ c\n#[ d ]#\ne"),
+            "unterminated block comment, opened at line 2, column 5",
+        );
+        // Columns count chars, not bytes.
+        assert_eq!(
+            error("\"é\" #[ c"),
+            "unterminated block comment, opened at line 1, column 5",
+        );
+    }
+
+    #[test]
+    fn close_without_open_is_bracket_then_line_comment() {
+        assert_eq!(sources("return a[0]# first\nb"), ["return", "a", "[", "0", "]", "b"]);
+        assert_eq!(sources("a[0]#]# b"), ["a", "[", "0", "]"]);
+        // Unless it's followed by a `[`, making the `#` open a block comment.
+        assert_eq!(sources("a[1]#[ note ]# b"), ["a", "[", "1", "]", "b"]);
+        assert_eq!(sources("a[1]#[ note\n ]# b"), ["a", "[", "1", "]", "b"]);
+        assert_eq!(sources("a[1]#[ #[ ]# ]# b"), ["a", "[", "1", "]", "b"]);
+        assert_eq!(
+            error("a[1]#[ note"),
+            "unterminated block comment, opened at line 1, column 5",
+        );
+        // A `#` before a `[`, on the other hand, opens a block comment.
+        assert_eq!(
+            error("a = #[1, 2]\nb"),
+            "unterminated block comment, opened at line 1, column 5",
+        );
+    }
+
+    /// The kind and source of each token in `source`.
+    ///
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        Lexer::new().lex(source).unwrap().iter()
+            .map(|token| (token.kind, token.source))
+            .collect()
+    }
+
+    #[test]
+    fn numbers_are_told_apart() {
+        let cases = [
+            ("0", TokenKind::LitInteger),
+            ("1_000i64", TokenKind::LitInteger),
+            // Hex digits and integer suffixes aren't exponents.
+            ("0x1e", TokenKind::LitInteger),
+            ("0x1E5", TokenKind::LitInteger),
+            ("5usize", TokenKind::LitInteger),
+            ("7isize", TokenKind::LitInteger),
+            ("0xff_u8", TokenKind::LitInteger),
+            ("1.5", TokenKind::LitFloat),
+            ("2e10", TokenKind::LitFloat),
+            ("3.0e-4f32", TokenKind::LitFloat),
+            ("3f32", TokenKind::LitFloat),
+            // An exponent before an integer suffix makes a bad float.
+            ("1e5u8", TokenKind::LitFloat),
+            ("x1", TokenKind::OthName),
+            ("_", TokenKind::GramUnderscore),
+        ];
+        for (source, kind) in cases {
+            assert_eq!(lex_name(source), kind, "{}", source);
+        }
+    }
+
+    #[test]
+    fn integers() {
+        let integer = |value, ty| Ok(Integer { value, ty });
+        let cases = [
+            ("42", integer(42, None)),
+            ("1_000i64", integer(1000, Some(IntegerType::I64))),
+            ("0x1e", integer(0x1e, None)),
+            ("0xFF", integer(255, None)),
+            ("0xff_u8", integer(255, Some(IntegerType::U8))),
+            ("0o17", integer(15, None)),
+            ("0b1010_1010", integer(0xaa, None)),
+            ("5usize", integer(5, Some(IntegerType::USize))),
+            ("127i8", integer(127, Some(IntegerType::I8))),
+            ("18446744073709551615", integer(u64::MAX, None)),
+            ("9223372036854775807isize", integer(i64::MAX as u64, Some(IntegerType::ISize))),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(get_integer(source), expected, "{}", source);
+        }
+
+        let errors = [
+            ("256u8", "integer literal `256u8` is out of range for u8"),
+            ("128i8", "integer literal `128i8` is out of range for i8"),
+            ("0x100u8", "integer literal `0x100u8` is out of range for u8"),
+            ("9223372036854775808i64", "integer literal `9223372036854775808i64` is out of \
+                                        range for i64"),
+            ("18446744073709551616", "integer literal `18446744073709551616` is out of \
+                                      range for u64"),
+            ("0b102", "invalid digit `2` in integer literal `0b102`"),
+            ("0o78", "invalid digit `8` in integer literal `0o78`"),
+            ("0x", "integer literal `0x` has no digits"),
+            ("5u7", "unknown integer suffix `u7` in `5u7`"),
+        ];
+        for (source, message) in errors {
+            assert_eq!(get_integer(source), Err(message.to_string()), "{}", source);
+        }
+    }
+
+    #[test]
+    fn floats() {
+        let float = |value, ty| Ok(Float { value, ty });
+        let cases = [
+            ("1.5", float(1.5, None)),
+            ("2e10", float(2e10, None)),
+            ("2E+3", float(2e3, None)),
+            ("3.0e-4f32", float(3.0e-4, Some(FloatType::F32))),
+            ("1_000.25", float(1000.25, None)),
+            ("1.5e3_f64", float(1.5e3, Some(FloatType::F64))),
+            ("3f32", float(3.0, Some(FloatType::F32))),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(get_float(source), expected, "{}", source);
+        }
+
+        let errors = [
+            ("1e5u8", "invalid float literal `1e5u8`"),
+            ("2e", "invalid float literal `2e`"),
+            ("2e+", "invalid float literal `2e+`"),
+            ("1e400", "float literal `1e400` is out of range for f64"),
+            ("1e39f32", "float literal `1e39f32` is out of range for f32"),
+        ];
+        for (source, message) in errors {
+            assert_eq!(get_float(source), Err(message.to_string()), "{}", source);
+        }
+    }
+
+    #[test]
+    fn points_after_numbers() {
+        use super::TokenKind::{ GramDot, LitFloat, LitInteger, OthName };
+
+        // A point is only a float's if a digit follows it.
+        assert_eq!(
+            tokens("t.0.x"),
+            [(OthName, "t"), (GramDot, "."), (LitInteger, "0"), (GramDot, "."), (OthName, "x")],
+        );
+        assert_eq!(tokens("t.0.1"), [(OthName, "t"), (GramDot, "."), (LitFloat, "0.1")]);
+        assert_eq!(tokens("1.5.2"), [(LitFloat, "1.5"), (GramDot, "."), (LitInteger, "2")]);
+        assert_eq!(tokens("3.0e-4 - 1"), [
+            (LitFloat, "3.0e-4"), (TokenKind::OpSub, "-"), (LitInteger, "1"),
+        ]);
+    }
+
+    #[test]
+    fn bad_numbers_fail_with_position() {
+        assert_eq!(
+            Lexer::new().lex("x = 1e5u8;").unwrap_err(),
+            "invalid float literal `1e5u8`, at line 1, column 5",
+        );
+        assert_eq!(
+            Lexer::new().lex("\n  256u8").unwrap_err(),
+            "integer literal `256u8` is out of range for u8, at line 2, column 3",
+        );
+    }
+
+    #[test]
+    fn non_ascii_in_numbers_fails_without_panicking() {
+        let errors = [
+            ("1é.0", "invalid float literal `1é.0`"),
+            ("1e3ü00", "invalid float literal `1e3ü00`"),
+            ("0😀.0", "invalid float literal `0😀.0`"),
+            ("1.0é", "invalid float literal `1.0é`"),
+            ("1.5f3é", "invalid float literal `1.5f3é`"),
+            ("2é", "invalid digit `é` in integer literal `2é`"),
+            ("0x1é", "invalid digit `é` in integer literal `0x1é`"),
+        ];
+        for (number, message) in errors {
+            assert_eq!(
+                Lexer::new().lex(&format!("x := {};", number)).unwrap_err(),
+                format!("{}, at line 1, column 6", message),
+            );
+        }
+    }
+
+    #[test]
+    fn multi_byte_chars_in_literals_and_comments() {
+        assert_eq!(sources("x = \"héllo 😀\";"), ["x", "=", "\"héllo 😀\"", ";"]);
+        assert_eq!(sources("c = 'é'; d = '😀';"), ["c", "=", "'é'", ";", "d", "=", "'😀'", ";"]);
+        assert_eq!(sources("a # ü 😀\nb #[ é ]# c"), ["a", "b", "c"]);
+        assert_eq!(
+            error("x = 'é😀';"),
+            "character literal 'é😀' isn't one character, at line 1, column 5",
+        );
+    }
+
+    #[test]
+    fn non_ascii_makes_a_name_invalid() {
+        for (source, invalid) in [("é = 1;", "é"), ("ifé = 1;", "ifé"), ("a😀b(x)", "a😀b")] {
+            let tokens = Lexer::new().lex(source).unwrap();
+            assert_eq!(tokens[0].kind, TokenKind::OthInvalid, "{}", source);
+            assert_eq!(tokens[0].source, invalid);
+        }
+    }
+
+    /// Whatever multi-byte char turns up wherever, lexing gives tokens or an
+    /// error, and never slices a char in half.
+    ///
+    #[test]
+    fn lexing_never_panics_on_multi_byte_chars() {
+        let source = "main() -> i32 { x := 0x1f + 2.5e3 as i32; s = \"a\\n\"; # c\n\
+                      #[ d ]# return a[0]#[ e ]# + 'f'; } ## g";
+        for inserted in ["é", "ü", "€", "😀", "\u{10FFFF}"] {
+            for (offset, _) in source.char_indices() {
+                let mut source = source.to_string();
+                source.insert_str(offset, inserted);
+                let _ = Lexer::new().lex(&source);
+            }
+        }
+    }
+
+    /// The doc comment lines given to each token in `source`.
+    ///
+    fn docs(source: &str) -> Vec<Vec<&str>> {
+        Lexer::new().lex(source).unwrap().into_iter().map(|token| token.doc).collect()
+    }
+
+    #[test]
+    fn doc_comments_are_kept_for_the_next_token() {
+        assert_eq!(
+            docs("## One.\n##\n##   Two.  \nf x"),
+            [vec!["One.", "", "  Two."], vec![]],
+        );
+        // Only the token straight after gets them, wherever it is.
+        assert_eq!(docs("a ## One.\n\n## Two.\nb c"), [vec![], vec!["One.", "Two."], vec![]]);
+        // A doc comment may be made of multi-byte chars.
+        assert_eq!(docs("## é 😀\nf"), [vec!["é 😀"]]);
+    }
+
+    #[test]
+    fn other_comments_are_not_doc_comments() {
+        assert_eq!(docs("# One.\nf"), [Vec::<&str>::new()]);
+        assert_eq!(docs("#[ ## One. ]# f"), [Vec::<&str>::new()]);
+        assert_eq!(docs("#[\n## One.\n]#\nf"), [Vec::<&str>::new()]);
+        // Nothing follows to be documented.
+        assert_eq!(sources("f ## One."), ["f"]);
+    }
+}
@@ -0,0 +1,32 @@
+//! The Adze compiler, as a library.
+//!
+//! The front end is the stable public API: `Lexer` turns source text into
+//! `Token`s, and `Parser` turns those into a `Tree` of `syntax` nodes.
+//!
+//! The rest (`lint`, `doc`, `ir`, `opt`, `gen` and `vm`) is what `adzec` is
+//! built from, and is public so that it can be; expect it to change.
+//!
+//! ```
+//! let tokens = adze::Lexer::new().lex("main() -> i32 { return 0; }").unwrap();
+//! let tree = adze::Parser::new().parse(tokens).unwrap();
+//!
+//! assert_eq!(tree.functions[0].signature.name.string, "main");
+//! ```
+
+#[macro_use]
+extern crate enum_map;
+#[macro_use]
+extern crate lazy_static;
+
+pub mod lex;
+pub mod parse;
+pub mod lint;
+pub mod doc;
+pub mod ir;
+pub mod opt;
+pub mod gen;
+pub mod vm;
+
+pub use lex::{ Lexer, Token, TokenKind };
+pub use parse::{ Parser, Tree };
+pub use parse::syntax;
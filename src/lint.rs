@@ -0,0 +1,491 @@
+//! Warnings for code that is never used or never run.
+//!
+//! - locals that are assigned but never read;
+//! - parameters that are never read;
+//! - functions never called on any path from `main` (only checked if there is
+//!   a `main`, since otherwise the source is presumably a library);
+//! - statements that can't be reached, because every path before them
+//!   returns;
+//! - `match` arms that can't be reached, because an arm before them matches
+//!   everything they would.
+//!
+//! As in Rust, a local, parameter or function whose name starts with `_` is
+//! never warned about.
+
+use std::collections::{ HashMap, HashSet };
+
+use lex::Integer;
+use parse::Tree;
+use parse::syntax::{ self, downcast, literal };
+
+pub fn lint(tree: &Tree) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Each function's callees, for the unused-function check.
+    let mut calls = HashMap::new();
+
+    // The number of variants of each enum, to tell when a `match` has
+    // covered them all.
+    let enums: HashMap<&str, usize> = tree.enums.iter()
+        .map(|declaration| (declaration.name.string.as_str(), declaration.variants.len()))
+        .collect();
+
+    for function in &tree.functions {
+        let mut linter = Linter::new(&function.signature.name.string, &enums);
+        linter.function(function);
+
+        warnings.append(&mut linter.warnings);
+        calls.insert(function.signature.name.string.as_str(), linter.calls);
+    }
+
+    if calls.contains_key("main") {
+        let mut used = HashSet::new();
+        let mut pending = vec!["main"];
+        while let Some(name) = pending.pop() {
+            if !used.insert(name) {
+                continue;
+            }
+            if let Some(callees) = calls.get(name) {
+                pending.extend(callees.iter().map(|callee| callee.as_str()));
+            }
+        }
+
+        for function in &tree.functions {
+            let name = &function.signature.name.string;
+            if !used.contains(name.as_str()) && !name.starts_with('_') {
+                warnings.push(format!("function `{}` is never used", name));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// The state of linting a single function.
+///
+struct Linter<'a> {
+    function: &'a str,
+    enums: &'a HashMap<&'a str, usize>,
+    warnings: Vec<String>,
+
+    /// Locals in order of first assignment.
+    assigned: Vec<String>,
+    read: HashSet<String>,
+    calls: HashSet<String>,
+    /// Locals and parameters that may hold references, which assigning to a
+    /// field or element through does read.
+    references: HashSet<String>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(function: &'a str, enums: &'a HashMap<&'a str, usize>) -> Self {
+        Linter {
+            function,
+            enums,
+            warnings: Vec::new(),
+            assigned: Vec::new(),
+            read: HashSet::new(),
+            calls: HashSet::new(),
+            references: HashSet::new(),
+        }
+    }
+
+    fn function(&mut self, function: &syntax::function::Syntax) {
+        for parameter in &function.signature.parameters {
+            if let Some(parameter) = downcast::<syntax::parameter::Syntax>(&**parameter) {
+                if let Some(syntax::ty::Syntax::Reference(..)) = parameter.ty {
+                    self.references.insert(parameter.name.string.clone());
+                }
+            }
+        }
+
+        self.block(&function.block);
+
+        for parameter in &function.signature.parameters {
+            if let Some(parameter) = downcast::<syntax::parameter::Syntax>(&**parameter) {
+                let name = &parameter.name.string;
+                if !self.read.contains(name) && !name.starts_with('_') {
+                    self.warnings.push(format!(
+                        "unused parameter `{}` in function `{}`", name, self.function,
+                    ));
+                }
+            }
+        }
+
+        for name in &self.assigned {
+            if !self.read.contains(name) && !name.starts_with('_') {
+                self.warnings.push(format!(
+                    "unused variable `{}` in function `{}`", name, self.function,
+                ));
+            }
+        }
+    }
+
+    /// Lint a block; return whether it always returns.
+    ///
+    fn block(&mut self, block: &syntax::block::Syntax) -> bool {
+        let mut returns = false;
+        let mut warned = false;
+
+        for statement in &block.statements {
+            // Only the first unreachable statement is worth a warning; the
+            // rest are still walked, so their uses count.
+            if returns && !warned {
+                self.warnings.push(format!(
+                    "unreachable statement in function `{}`", self.function,
+                ));
+                warned = true;
+            }
+
+            returns |= self.statement(&**statement);
+        }
+
+        returns
+    }
+
+    /// Lint a statement; return whether it always returns.
+    ///
+    fn statement(&mut self, statement: &dyn syntax::Syntax) -> bool {
+        if let Some(statement) = downcast::<syntax::statement::Syntax>(statement) {
+            self.statement(&*statement.block)
+        }
+        else if let Some(block) = downcast::<syntax::block::Syntax>(statement) {
+            self.block(block)
+        }
+        else if let Some(init) = downcast::<syntax::init::Syntax>(statement) {
+            self.expression(&*init.rhs);
+
+            // Without types, a call is taken to maybe return a reference.
+            let reference = match init.lhs_type {
+                Some(ref ty) => matches!(*ty, syntax::ty::Syntax::Reference(..)),
+                None => {
+                    let mut rhs = &*init.rhs;
+                    while let Some(expression) = downcast::<syntax::expression::Syntax>(rhs) {
+                        rhs = &*expression.value;
+                    }
+                    downcast::<syntax::reference::Syntax>(rhs).is_some() ||
+                        downcast::<syntax::call::Syntax>(rhs).is_some()
+                },
+            };
+            for name in init.lhs.names() {
+                if reference {
+                    self.references.insert(name.string.clone());
+                }
+                if !self.assigned.contains(&name.string) {
+                    self.assigned.push(name.string.clone());
+                }
+            }
+            false
+        }
+        else if let Some(assign) = downcast::<syntax::assign::Syntax>(statement) {
+            // Assigning to a field or element of a local doesn't read the
+            // local, though an index does read what's in it.
+            self.place(&*assign.place);
+            self.expression(&*assign.rhs);
+            false
+        }
+        else if let Some(ret) = downcast::<syntax::ret::Syntax>(statement) {
+            if let Some(ref value) = ret.value {
+                self.expression(value);
+            }
+            true
+        }
+        else if let Some(conditional) = downcast::<syntax::conditional::Syntax>(statement) {
+            self.expression(&conditional.condition);
+
+            let then_returns = self.block(&conditional.then_block);
+            match conditional.else_block {
+                Some(ref else_block) => {
+                    let else_returns = self.statement(&**else_block);
+                    then_returns && else_returns
+                },
+                None => false,
+            }
+        }
+        else if let Some(call) = downcast::<syntax::call::Syntax>(statement) {
+            self.expression(call);
+            false
+        }
+        else if let Some(matching) = downcast::<syntax::matching::Syntax>(statement) {
+            self.matching(matching)
+        }
+        else {
+            false
+        }
+    }
+
+    /// Lint a `match`; return whether it always returns: whether it's
+    /// exhaustive, and every arm is a block that always returns.
+    ///
+    fn matching(&mut self, matching: &syntax::matching::Syntax) -> bool {
+        self.expression(&matching.value);
+
+        // What the arms so far match: everything, once there's been a `_` or
+        // every variant; otherwise these literals' values, or variants.
+        let mut everything = false;
+        let mut values = Vec::new();
+        let mut variants: Vec<&str> = Vec::new();
+        let mut returns = true;
+
+        for arm in &matching.arms {
+            let reachable = !everything && match arm.pattern {
+                syntax::pattern::Syntax::Wildcard => {
+                    everything = true;
+                    true
+                },
+                syntax::pattern::Syntax::Literal(ref literal) => {
+                    let value = match *literal {
+                        literal::Syntax::Integer(Integer { value, .. }) => Some(value as i128),
+                        literal::Syntax::Char(this_char) => Some(this_char as i128),
+                        _ => None,
+                    };
+                    match value {
+                        Some(value) if values.contains(&value) => false,
+                        Some(value) => {
+                            values.push(value);
+                            true
+                        },
+                        None => true,
+                    }
+                },
+                syntax::pattern::Syntax::Variant { ref name, ref variant, .. } => {
+                    match variants.contains(&variant.string.as_str()) {
+                        true => false,
+                        false => {
+                            variants.push(&variant.string);
+                            let count = self.enums.get(name.string.as_str());
+                            everything = count == Some(&variants.len());
+                            true
+                        },
+                    }
+                },
+            };
+            if !reachable {
+                self.warnings.push(format!(
+                    "unreachable match arm in function `{}`", self.function,
+                ));
+            }
+
+            if let syntax::pattern::Syntax::Variant { ref fields, .. } = arm.pattern {
+                for field in fields.iter().flatten() {
+                    if !self.assigned.contains(&field.string) {
+                        self.assigned.push(field.string.clone());
+                    }
+                }
+            }
+
+            match downcast::<syntax::block::Syntax>(&*arm.body) {
+                Some(body) => returns &= self.block(body),
+                None => {
+                    self.expression(&*arm.body);
+                    returns = false;
+                },
+            }
+        }
+
+        returns && everything
+    }
+
+    /// Walk what's assigned to: only the indexes in it, and any reference
+    /// assigned through, are read.
+    ///
+    fn place(&mut self, place: &dyn syntax::Syntax) {
+        if let Some(name) = downcast::<syntax::name::Syntax>(place) {
+            if self.references.contains(&name.string) {
+                self.read.insert(name.string.clone());
+            }
+        }
+        else if let Some(field) = downcast::<syntax::field::Syntax>(place) {
+            self.place(&*field.value);
+        }
+        else if let Some(index) = downcast::<syntax::index::Syntax>(place) {
+            self.place(&*index.value);
+            self.expression(&*index.index);
+        }
+        else if let Some(expression) = downcast::<syntax::expression::Syntax>(place) {
+            self.place(&*expression.value);
+        }
+        else if let Some(dereference) = downcast::<syntax::dereference::Syntax>(place) {
+            self.expression(&*dereference.value);
+        }
+    }
+
+    fn expression(&mut self, expression: &dyn syntax::Syntax) {
+        if let Some(expression) = downcast::<syntax::expression::Syntax>(expression) {
+            self.expression(&*expression.value);
+        }
+        else if let Some(name) = downcast::<syntax::name::Syntax>(expression) {
+            self.read.insert(name.string.clone());
+        }
+        else if let Some(binary) = downcast::<syntax::binary::Syntax>(expression) {
+            self.expression(&*binary.lhs);
+            self.expression(&*binary.rhs);
+        }
+        else if let Some(cast) = downcast::<syntax::cast::Syntax>(expression) {
+            self.expression(&*cast.value);
+        }
+        else if let Some(call) = downcast::<syntax::call::Syntax>(expression) {
+            self.calls.insert(call.name.string.clone());
+            for argument in &call.arguments {
+                self.expression(&**argument);
+            }
+        }
+        else if let Some(construct) = downcast::<syntax::construct::Syntax>(expression) {
+            for (_, value) in &construct.fields {
+                self.expression(&**value);
+            }
+        }
+        else if let Some(field) = downcast::<syntax::field::Syntax>(expression) {
+            self.expression(&*field.value);
+        }
+        else if let Some(variant) = downcast::<syntax::variant::Syntax>(expression) {
+            for argument in &variant.arguments {
+                self.expression(&**argument);
+            }
+        }
+        else if let Some(array) = downcast::<syntax::array::Syntax>(expression) {
+            for element in &array.elements {
+                self.expression(&**element);
+            }
+        }
+        else if let Some(tuple) = downcast::<syntax::tuple::Syntax>(expression) {
+            for element in &tuple.elements {
+                self.expression(&**element);
+            }
+        }
+        else if let Some(index) = downcast::<syntax::index::Syntax>(expression) {
+            self.expression(&*index.value);
+            self.expression(&*index.index);
+        }
+        else if let Some(reference) = downcast::<syntax::reference::Syntax>(expression) {
+            self.expression(&*reference.value);
+        }
+        else if let Some(dereference) = downcast::<syntax::dereference::Syntax>(expression) {
+            self.expression(&*dereference.value);
+        }
+        else if let Some(matching) = downcast::<syntax::matching::Syntax>(expression) {
+            self.matching(matching);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lint;
+    use lex::Lexer;
+    use parse::Parser;
+
+    fn warnings(source: &str) -> Vec<String> {
+        let tokens = Lexer::new().lex(source).unwrap();
+        lint(&Parser::new().parse(tokens).unwrap())
+    }
+
+    #[test]
+    fn unused_local() {
+        assert_eq!(
+            warnings("main() -> i32 {\n    x = 1;\n    y = 2;\n    return y;\n}\n"),
+            ["unused variable `x` in function `main`"],
+        );
+    }
+
+    #[test]
+    fn unused_parameter() {
+        assert_eq!(
+            warnings("f(a: i32, b: i32) -> i32 {\n    return b;\n}\n"),
+            ["unused parameter `a` in function `f`"],
+        );
+    }
+
+    #[test]
+    fn unused_function_with_main() {
+        let source = "\
+            used() -> i32 {\n    return 1;\n}\n\
+            unused() -> i32 {\n    return 2;\n}\n\
+            main() -> i32 {\n    return used();\n}\n";
+        assert_eq!(warnings(source), ["function `unused` is never used"]);
+    }
+
+    #[test]
+    fn no_unused_functions_without_main() {
+        assert!(warnings("f() -> i32 {\n    return 1;\n}\n").is_empty());
+    }
+
+    #[test]
+    fn statement_after_if_else_that_returns() {
+        let source = "\
+            main() -> i32 {\n\
+            \x20   if 1 {\n        return 1;\n    }\n\
+            \x20   else {\n        return 2;\n    }\n\
+            \x20   return 3;\n\
+            }\n";
+        assert_eq!(warnings(source), ["unreachable statement in function `main`"]);
+    }
+
+    #[test]
+    fn statement_after_if_without_else_is_reachable() {
+        let source = "\
+            main() -> i32 {\n\
+            \x20   if 1 {\n        return 1;\n    }\n\
+            \x20   return 3;\n\
+            }\n";
+        assert!(warnings(source).is_empty());
+    }
+
+    #[test]
+    fn unreachable_match_arms() {
+        let shape = "enum Shape {\n    Circle(i32),\n    Empty,\n}\n";
+        let arms = [
+            // After a `_`, after the same value, and after every variant.
+            "0 => 1,\n        _ => 2,\n        1 => 3,",
+            "0 => 1,\n        0 => 2,\n        _ => 3,",
+            "'a' => 1,\n        'a' => 2,\n        _ => 3,",
+        ];
+        for arms in arms {
+            let source = format!(
+                "main() -> i32 {{\n    return match 0 {{\n        {}\n    }};\n}}\n", arms,
+            );
+            assert_eq!(
+                warnings(&source), ["unreachable match arm in function `main`"], "{}", arms,
+            );
+        }
+
+        let source = format!(
+            "{}main() -> i32 {{\n\
+             \x20   return match Shape::Empty {{\n\
+             \x20       Shape::Circle(r) => r,\n\
+             \x20       Shape::Empty => 0,\n\
+             \x20       _ => 1,\n\
+             \x20   }};\n\
+             }}\n",
+            shape,
+        );
+        assert_eq!(warnings(&source), ["unreachable match arm in function `main`"]);
+    }
+
+    #[test]
+    fn reachable_match_arms_are_silent() {
+        let source = "\
+            main() -> i32 {\n\
+            \x20   return match 1 {\n        0 => 1,\n        1 => 2,\n        _ => 3,\n    };\n\
+            }\n";
+        assert!(warnings(source).is_empty());
+    }
+
+    #[test]
+    fn underscore_names_are_silent() {
+        let source = "\
+            _helper(_unused: i32) -> (i32, i32) {\n    return (1, 2);\n}\n\
+            main() -> i32 {\n\
+            \x20   _x = 1;\n\
+            \x20   (_, b) := _helper(0);\n\
+            \x20   return b;\n\
+            }\n";
+        assert!(warnings(source).is_empty());
+
+        // Not a call from `main`, but still silent, for its name.
+        let source = "\
+            _never() -> i32 {\n    return 1;\n}\n\
+            main() -> i32 {\n    return 0;\n}\n";
+        assert!(warnings(source).is_empty());
+    }
+}
@@ -1,20 +1,26 @@
-#![feature(ptr_offset_from)]
-#![feature(plugin)]
-
-#![plugin(phf_macros)]
-extern crate phf;
-#[macro_use]
-extern crate enum_map;
-#[macro_use]
-extern crate lazy_static;
+extern crate adze;
 extern crate memmap;
 
 use std::fs;
 use std::env;
 use std::str;
+use std::process;
+use std::path::Path;
+use std::io::{ self, Write };
 
-mod lex;
-mod parse;
+use adze::{ lex, parse, lint, doc, ir, opt, gen, vm };
+
+/// What `adzec` should output, as chosen by `--emit=`.
+///
+enum Emit {
+    /// Nothing; only check that the source compiles.
+    Nothing,
+    Ir,
+    LlvmIr,
+    Wat,
+    Asm,
+    Bytecode,
+}
 
 fn main() {
     // Initialise lexer
@@ -25,31 +31,248 @@ fn main() {
     //
     let args: Vec<String> = env::args().collect();
 
-    // Get filename argument
+    if args.get(1).map(String::as_str) == Some("doc") {
+        document(&lexer, &args[2..]);
+        return;
+    }
+
+    // Get options and the filename argument
     // (args[0] is the path name)
     //
-    if args.len() < 2 {
-        panic!("Use: `adzec example.adze`");
+    let mut emit = Emit::Nothing;
+    let mut regalloc = gen::x86_64::RegAlloc::LinearScan;
+    let mut level = opt::Level::O1;
+    let mut overflow = ir::Overflow::Trap;
+    let mut run = false;
+    let mut path = None;
+    for arg in &args[1..] {
+        if let Some(kind) = arg.strip_prefix("--emit=") {
+            emit = match kind {
+                "ir" => Emit::Ir,
+                "llvm-ir" => Emit::LlvmIr,
+                "wat" => Emit::Wat,
+                "asm" => Emit::Asm,
+                "bytecode" => Emit::Bytecode,
+                other => fail(&format!("unknown --emit kind `{}`", other)),
+            };
+        }
+        else if let Some(strategy) = arg.strip_prefix("--regalloc=") {
+            regalloc = match strategy {
+                "linear-scan" => gen::x86_64::RegAlloc::LinearScan,
+                "stack" => gen::x86_64::RegAlloc::Stack,
+                other => fail(&format!("unknown --regalloc strategy `{}`", other)),
+            };
+        }
+        else if let Some(behaviour) = arg.strip_prefix("--overflow=") {
+            overflow = match behaviour {
+                "trap" => ir::Overflow::Trap,
+                "wrap" => ir::Overflow::Wrap,
+                other => fail(&format!("unknown --overflow behaviour `{}`", other)),
+            };
+        }
+        else if let Some(number) = arg.strip_prefix("-O") {
+            level = match number {
+                "0" => opt::Level::O0,
+                "1" => opt::Level::O1,
+                "2" => opt::Level::O2,
+                other => fail(&format!("unknown optimisation level `{}`", other)),
+            };
+        }
+        else if arg == "--run" {
+            run = true;
+        }
+        else {
+            path = Some(arg);
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => panic!(
+            "Use: `adzec [--emit=ir|llvm-ir|wat|asm|bytecode] \
+             [--regalloc=linear-scan|stack] [-O0|-O1|-O2] [--overflow=trap|wrap] \
+             [--run] example.adze`, \
+             or `adzec doc ...`"
+        ),
+    };
+
+    let tree = load(&lexer, path);
+
+    for warning in lint::lint(&tree) {
+        eprintln!("warning: {}", warning);
+    }
+
+    let mut module = match ir::lower::lower(&tree, overflow) {
+        Ok(module) => module,
+        Err(message) => fail(&message),
+    };
+    verify(&module);
+
+    if let Err(message) = opt::optimise(&mut module, level) {
+        fail(&message);
+    }
+    verify(&module);
+
+    let output = match emit {
+        Emit::Nothing => Ok(String::new()),
+        Emit::Ir => Ok(module.to_string()),
+        Emit::LlvmIr => gen::llvm::emit(&module),
+        Emit::Wat => gen::wat::emit(&module),
+        Emit::Asm => gen::x86_64::emit(&module, regalloc),
+        Emit::Bytecode => vm::compile::compile(&module)
+            .map(|program| vm::bytecode::disassemble(&program)),
+    };
+
+    match output {
+        Ok(output) => print!("{}", output),
+        Err(message) => fail(&message),
+    }
+
+    // Running `main` makes its result the exit code, as it would be for a
+    // native executable.
+    //
+    if run {
+        let result = vm::compile::compile(&module).and_then(|program| {
+            vm::machine::Machine::new(&program).run("main", &[])
+        });
+        // `process::exit()` doesn't flush what the program printed.
+        let _ = io::stdout().flush();
+
+        match result {
+            Ok(vm::bytecode::Scalar::I32(value)) => process::exit(value),
+            Ok(value) => fail(&format!("`main` returned {}, not an i32", value)),
+            Err(message) => fail(&message),
+        }
+    }
+}
+
+/// `adzec doc`: write a documentation page for each source file given.
+///
+fn document(lexer: &lex::Lexer, args: &[String]) {
+    let mut format = doc::Format::Html;
+    let mut out = "doc";
+    let mut paths = Vec::new();
+    for arg in args {
+        if let Some(kind) = arg.strip_prefix("--format=") {
+            format = match kind {
+                "html" => doc::Format::Html,
+                "markdown" => doc::Format::Markdown,
+                other => fail(&format!("unknown --format `{}`", other)),
+            };
+        }
+        else if let Some(dir) = arg.strip_prefix("--out=") {
+            out = dir;
+        }
+        else {
+            paths.push(arg);
+        }
+    }
+    if paths.is_empty() {
+        panic!(
+            "Use: `adzec doc [--format=html|markdown] [--out=directory] \
+             example.adze...`"
+        );
     }
-    let path = &args[1];
 
+    // Each file is a module, named for the file.
+    let trees: Vec<(String, parse::Tree)> = paths.iter().map(|path| {
+        let name = Path::new(path).file_stem()
+            .map_or(path.to_string(), |stem| stem.to_string_lossy().into_owned());
+        (name, load(lexer, path))
+    }).collect();
+    let modules: Vec<doc::Module> = trees.iter()
+        .map(|(name, tree)| doc::Module { name, tree })
+        .collect();
+
+    if let Err(error) = fs::create_dir_all(out) {
+        fail(&format!("couldn't create `{}`: {}", out, error));
+    }
+    for (name, page) in doc::document(&modules, format) {
+        let path = Path::new(out).join(name);
+        if let Err(error) = fs::write(&path, page) {
+            fail(&format!("couldn't write `{}`: {}", path.display(), error));
+        }
+    }
+}
+
+/// Read, lex and parse the source file at `path`.
+///
+fn load(lexer: &lex::Lexer, path: &str) -> parse::Tree {
     // @TODO error handling
 
     let file = fs::File::open(path).unwrap();
 
     let map = unsafe { memmap::Mmap::map(&file).unwrap() };
 
-    // @OPTION safer: from_utf8 on smaller slices, within Lexer?
-    // Or just do it the once on the whole file, given the semantics would be
-    // the same?
+    // Validate the whole file up front; the lexer can then work on `str`s,
+    // and needn't worry about bad bytes, only about multi-byte chars.
     //
-    let text = unsafe { str::from_utf8_unchecked(&map) };
+    let text = match str::from_utf8(&map) {
+        Ok(text) => text,
+        Err(error) => fail(&utf8_error(path, &map, &error)),
+    };
 
-    let tokens = lexer.lex(text);
+    let tokens = match lexer.lex(text) {
+        Ok(tokens) => tokens,
+        Err(message) => fail(&message),
+    };
 
     let parser = parse::Parser::new();
 
-    let tree = parser.parse(tokens);
+    match parser.parse(tokens) {
+        Some(tree) => tree,
+        None => fail(&format!("failed to parse `{}`", path)),
+    }
+}
+
+/// Check `module` is well-formed; there's a bug in the compiler if it isn't.
+///
+fn verify(module: &ir::Module) {
+    if let Err(message) = ir::verify::verify(module) {
+        fail(&format!("invalid IR: {}", message));
+    }
+}
+
+/// Describe where `bytes`, the contents of `path`, stop being valid UTF-8.
+///
+fn utf8_error(path: &str, bytes: &[u8], error: &str::Utf8Error) -> String {
+    let offset = error.valid_up_to();
+    let line = bytes[..offset].iter().filter(|byte| **byte == b'\n').count() + 1;
+
+    format!(
+        "`{}` is not valid UTF-8: invalid byte 0x{:02x} at offset {} (line {})",
+        path, bytes[offset], offset, line,
+    )
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use super::utf8_error;
 
-    // @TODO transform AST into ASM
+    fn error(bytes: &[u8]) -> String {
+        utf8_error("a.adze", bytes, &str::from_utf8(bytes).unwrap_err())
+    }
+
+    #[test]
+    fn utf8_errors_give_the_first_bad_byte() {
+        assert_eq!(
+            error(b"main() {\n  x = 1;\xff\n}\n"),
+            "`a.adze` is not valid UTF-8: invalid byte 0xff at offset 17 (line 2)",
+        );
+        // A char cut short, at the end or before another.
+        assert_eq!(
+            error(b"x\n\n\xc3"),
+            "`a.adze` is not valid UTF-8: invalid byte 0xc3 at offset 3 (line 3)",
+        );
+        assert_eq!(
+            error(b"# \xe2\x82\nx"),
+            "`a.adze` is not valid UTF-8: invalid byte 0xe2 at offset 2 (line 1)",
+        );
+    }
 }
@@ -0,0 +1,293 @@
+//! Common subexpression elimination.
+//!
+//! An instruction computing the same thing as one that dominates it is
+//! removed, and its uses replaced with the earlier result. Only constants,
+//! arithmetic, comparisons, conversions and element addresses are considered,
+//! since calls and prints have side effects (and there's no gain in sharing
+//! strings). Each slot is different memory, and a load may see a store in
+//! between.
+//! Arithmetic that may trap is considered too: if the first traps, the one it
+//! replaces is never reached.
+//!
+//! The operands of commutative operations are put in a canonical order first,
+//! so that `a + b` and `b + a` are found to be the same.
+
+use std::collections::HashMap;
+
+use ir::{ self, BinaryOp, Block, InstructionKind, Overflow, Type, Value };
+
+pub fn cse(module: &mut ir::Module) {
+    for function in &mut module.functions {
+        cse_function(function);
+    }
+}
+
+/// What an instruction computes, as far as CSE is concerned.
+///
+#[derive (Debug, Clone, PartialEq, Eq, Hash)]
+enum Expression {
+    /// An integer's type and value.
+    Const(Type, i128),
+    /// A float's type and bits.
+    Float(Type, u64),
+    Binary(BinaryOp, Overflow, Value, Value),
+    /// A conversion to the type.
+    Convert(Type, Value),
+    Element(Value, Value, u32),
+}
+
+fn expression(kind: &InstructionKind, ty: Type) -> Option<Expression> {
+    match *kind {
+        InstructionKind::Const(value) => Some(Expression::Const(ty, value)),
+        InstructionKind::Float(value) => Some(Expression::Float(ty, value.to_bits())),
+        InstructionKind::Binary(op, overflow, lhs, rhs) => {
+            let (lhs, rhs) = match op.is_commutative() && rhs < lhs {
+                true  => (rhs, lhs),
+                false => (lhs, rhs),
+            };
+            Some(Expression::Binary(op, overflow, lhs, rhs))
+        },
+        InstructionKind::Convert(value) => Some(Expression::Convert(ty, value)),
+        InstructionKind::Element(address, index, size) => {
+            Some(Expression::Element(address, index, size))
+        },
+        InstructionKind::Str(_) |
+        InstructionKind::Call(..) |
+        InstructionKind::Print(_) |
+        InstructionKind::Slot(..) |
+        InstructionKind::Load(..) |
+        InstructionKind::Store(..) |
+        InstructionKind::Copy(..) => None,
+    }
+}
+
+fn cse_function(function: &mut ir::Function) {
+    // The type of every value, since blocks are borrowed mutably below.
+    let types = function.values.clone();
+
+    // The dominator tree, as each block's children.
+    let mut children = vec![Vec::new(); function.blocks.len()];
+    for (index, idom) in function.immediate_dominators().into_iter().enumerate() {
+        if let Some(idom) = idom {
+            children[idom.0].push(Block(index));
+        }
+    }
+
+    // Walk the tree depth-first, with a scope of available expressions per
+    // block; each stack entry is a block and the expressions it added.
+    let mut available: HashMap<Expression, Value> = HashMap::new();
+    let mut replacements = HashMap::new();
+    let mut stack = vec![(function.entry(), None)];
+
+    while let Some((block, added)) = stack.pop() {
+        // Leaving a block's subtree: forget what it made available.
+        if let Some(added) = added {
+            for expression in added {
+                available.remove(&expression);
+            }
+            continue;
+        }
+
+        let mut added: Vec<Expression> = Vec::new();
+        let instructions = &mut function.block_mut(block).instructions;
+
+        let mut index = 0;
+        while index < instructions.len() {
+            // Operands are replaced as they're found, so later expressions
+            // using a removed result still match.
+            let instruction = &mut instructions[index];
+            instruction.map_operands(|value| *replacements.get(&value).unwrap_or(&value));
+
+            let ty = types[instruction.result.0];
+            let expression = match expression(&instruction.kind, ty) {
+                Some(expression) => expression,
+                None => {
+                    index += 1;
+                    continue;
+                },
+            };
+            match available.get(&expression) {
+                Some(&earlier) => {
+                    replacements.insert(instruction.result, earlier);
+                    instructions.remove(index);
+                },
+                None => {
+                    available.insert(expression.clone(), instruction.result);
+                    added.push(expression);
+                    index += 1;
+                },
+            }
+        }
+
+        stack.push((block, Some(added)));
+        for child in &children[block.0] {
+            stack.push((*child, None));
+        }
+    }
+
+    // This also catches uses as block arguments and in terminators.
+    function.replace_uses(&replacements);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cse;
+    use ir::verify::verify;
+    use ir::{
+        BinaryOp, Block, Function, InstructionKind, Module, Overflow, Target, Terminator, Type,
+        Value,
+    };
+
+    /// Run CSE over `module`, checking it's well formed before and after.
+    ///
+    fn eliminate(module: &mut Module) {
+        verify(module).unwrap();
+        cse(module);
+        verify(module).unwrap();
+    }
+
+    /// A function `f` whose entry block prints each of the values `build`
+    /// pushes to it, and returns 0.
+    ///
+    fn printing<F>(parameters: usize, build: F) -> Function
+        where F: FnOnce(&mut Function, &[Value]) -> Vec<Value>
+    {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let parameters: Vec<Value> = (0..parameters)
+            .map(|_| function.add_parameter(entry, Type::I32))
+            .collect();
+        for value in build(&mut function, &parameters) {
+            function.push(entry, Type::I32, InstructionKind::Print(value));
+        }
+        let zero = function.push(entry, Type::I32, InstructionKind::Const(0));
+        function.terminate(entry, Terminator::Return(vec![zero]));
+        function
+    }
+
+    /// What's printed in `block`, and how many other instructions it has.
+    ///
+    fn printed(function: &Function, block: Block) -> (Vec<Value>, usize) {
+        let mut printed = Vec::new();
+        let mut others = 0;
+        for instruction in &function.block(block).instructions {
+            match instruction.kind {
+                InstructionKind::Print(value) => printed.push(value),
+                _ => others += 1,
+            }
+        }
+        (printed, others)
+    }
+
+    #[test]
+    fn shares_the_same_expression() {
+        let add = |lhs, rhs| InstructionKind::Binary(BinaryOp::Add, Overflow::Wrap, lhs, rhs);
+        let sub = |lhs, rhs| InstructionKind::Binary(BinaryOp::Sub, Overflow::Wrap, lhs, rhs);
+        let mut values = Vec::new();
+        let function = printing(2, |function, parameters| {
+            let (a, b) = (parameters[0], parameters[1]);
+            let entry = function.entry();
+            values = vec![
+                function.push(entry, Type::I32, add(a, b)),
+                function.push(entry, Type::I32, add(b, a)),
+                function.push(entry, Type::I32, sub(a, b)),
+                function.push(entry, Type::I32, sub(b, a)),
+            ];
+            values.clone()
+        });
+
+        let mut module = Module { functions: vec![function] };
+        eliminate(&mut module);
+        // The constant returned is the other instruction left.
+        assert_eq!(
+            printed(&module.functions[0], Block(0)),
+            (vec![values[0], values[0], values[2], values[3]], 4),
+        );
+    }
+
+    #[test]
+    fn shares_constants_only_of_the_same_type() {
+        let mut values = Vec::new();
+        let function = printing(0, |function, _| {
+            let entry = function.entry();
+            values = vec![
+                function.push(entry, Type::I32, InstructionKind::Const(1)),
+                function.push(entry, Type::I32, InstructionKind::Const(1)),
+                function.push(entry, Type::I64, InstructionKind::Const(1)),
+                function.push(entry, Type::F64, InstructionKind::Float(1.0)),
+                function.push(entry, Type::F64, InstructionKind::Float(1.0)),
+                function.push(entry, Type::F32, InstructionKind::Float(1.0)),
+            ];
+            values.clone()
+        });
+
+        let mut module = Module { functions: vec![function] };
+        eliminate(&mut module);
+        assert_eq!(
+            printed(&module.functions[0], Block(0)).0,
+            vec![values[0], values[0], values[2], values[3], values[3], values[5]],
+        );
+    }
+
+    /// An expression is only shared with one in a block that dominates it:
+    /// here the entry block, and not the other side of the branch.
+    ///
+    #[test]
+    fn only_shares_with_dominating_blocks() {
+        let mut function = Function::new("f".to_string(), vec![Type::I32, Type::I32]);
+        let entry = function.new_block();
+        let a = function.add_parameter(entry, Type::I32);
+        let (left, right) = (function.new_block(), function.new_block());
+        let mul = InstructionKind::Binary(BinaryOp::Mul, Overflow::Trap, a, a);
+        let sub = InstructionKind::Binary(BinaryOp::Sub, Overflow::Trap, a, a);
+        let product = function.push(entry, Type::I32, mul.clone());
+        function.terminate(entry, Terminator::CondBranch(
+            a, Target::new(left), Target::new(right),
+        ));
+        let mut differences = Vec::new();
+        for block in [left, right] {
+            let difference = function.push(block, Type::I32, sub.clone());
+            let again = function.push(block, Type::I32, mul.clone());
+            function.terminate(block, Terminator::Return(vec![difference, again]));
+            differences.push(difference);
+        }
+
+        let mut module = Module { functions: vec![function] };
+        eliminate(&mut module);
+        let function = &module.functions[0];
+        for (&block, difference) in [left, right].iter().zip(differences) {
+            assert_eq!(function.block(block).instructions.len(), 1);
+            assert_eq!(
+                function.block(block).terminator,
+                Some(Terminator::Return(vec![difference, product])),
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_side_effects_and_memory_alone() {
+        let mut callee = Function::new("g".to_string(), vec![Type::I32]);
+        let entry = callee.new_block();
+        let zero = callee.push(entry, Type::I32, InstructionKind::Const(0));
+        callee.terminate(entry, Terminator::Return(vec![zero]));
+
+        let mut values = Vec::new();
+        let function = printing(0, |function, _| {
+            let entry = function.entry();
+            let call = || InstructionKind::Call("g".to_string(), Vec::new(), Vec::new());
+            let first = function.push(entry, Type::I32, call());
+            let second = function.push(entry, Type::I32, call());
+            let slot = function.push(entry, Type::Ptr, InstructionKind::Slot(4, 4));
+            function.push(entry, Type::I32, InstructionKind::Store(slot, 0, first));
+            let before = function.push(entry, Type::I32, InstructionKind::Load(slot, 0));
+            function.push(entry, Type::I32, InstructionKind::Store(slot, 0, second));
+            let after = function.push(entry, Type::I32, InstructionKind::Load(slot, 0));
+            values = vec![first, second, before, after];
+            values.clone()
+        });
+
+        let mut module = Module { functions: vec![callee, function] };
+        eliminate(&mut module);
+        assert_eq!(printed(module.function("f").unwrap(), Block(0)), (values, 8));
+    }
+}
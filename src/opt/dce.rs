@@ -0,0 +1,380 @@
+//! Dead code elimination.
+//!
+//! - Branches and switches on a constant become unconditional;
+//! - blocks that can't be reached are removed;
+//! - a block whose only predecessor branches straight to it is merged into
+//!   that predecessor;
+//! - instructions whose results are never used, and block parameters that are
+//!   never used, are removed. Calls, prints, stores, copies and arithmetic
+//!   that may trap are always kept, since they have side effects.
+
+use std::collections::{ HashMap, HashSet };
+
+use ir::{ self, Block, InstructionKind, Overflow, Target, Terminator, Value };
+
+pub fn dce(module: &mut ir::Module) {
+    for function in &mut module.functions {
+        fold_branches(function);
+        // Unreachable blocks are removed before merging too, since their
+        // branches would otherwise count as predecessors.
+        remove_unreachable(function);
+        merge_blocks(function);
+        remove_unreachable(function);
+        remove_unused(function);
+    }
+}
+
+fn fold_branches(function: &mut ir::Function) {
+    let mut constants = HashMap::new();
+    for data in &function.blocks {
+        for instruction in &data.instructions {
+            if let InstructionKind::Const(value) = instruction.kind {
+                constants.insert(instruction.result, value);
+            }
+        }
+    }
+
+    for data in &mut function.blocks {
+        let folded = match data.terminator {
+            Some(Terminator::CondBranch(condition, ref then_target, ref else_target)) => {
+                match constants.get(&condition) {
+                    Some(&0) => Some(else_target.clone()),
+                    Some(_)  => Some(then_target.clone()),
+                    None => None,
+                }
+            },
+            Some(Terminator::Switch(value, ref cases, ref default)) => {
+                constants.get(&value).map(|constant| {
+                    cases.iter().find(|&&(case, _)| case == *constant)
+                        .map_or(default, |(_, target)| target)
+                        .clone()
+                })
+            },
+            _ => None,
+        };
+
+        if let Some(target) = folded {
+            data.terminator = Some(Terminator::Branch(target));
+        }
+    }
+}
+
+fn merge_blocks(function: &mut ir::Function) {
+    let mut predecessors = function.predecessors();
+
+    for index in 0..function.blocks.len() {
+        // Keep merging into this block for as long as it ends in a branch to
+        // a block with no other predecessor.
+        while let Some(Terminator::Branch(ref target)) = function.blocks[index].terminator {
+            let target = target.clone();
+            let successor = target.block;
+            if successor.0 == index || predecessors[successor.0] != vec![Block(index)] {
+                break;
+            }
+
+            let merged = function.block_mut(successor).clone();
+            let replacements: HashMap<Value, Value> = merged.parameters.iter().cloned()
+                .zip(target.arguments.iter().cloned())
+                .collect();
+
+            // The successor is left unreachable, to be removed; its values
+            // are now defined in this block instead.
+            {
+                let data = function.block_mut(successor);
+                data.parameters.clear();
+                data.instructions.clear();
+            }
+
+            let data = &mut function.blocks[index];
+            data.instructions.extend(merged.instructions);
+            data.terminator = merged.terminator;
+
+            // The successor's successors now have this block as predecessor.
+            for target in data.terminator.as_ref().unwrap().targets() {
+                for predecessor in &mut predecessors[target.block.0] {
+                    if *predecessor == successor {
+                        *predecessor = Block(index);
+                    }
+                }
+            }
+            predecessors[successor.0].clear();
+
+            function.replace_uses(&replacements);
+        }
+    }
+}
+
+fn remove_unreachable(function: &mut ir::Function) {
+    let mut order = function.reverse_post_order();
+    if order.len() == function.blocks.len() {
+        return;
+    }
+
+    // Keep the remaining blocks in their original order.
+    order.sort();
+
+    let mut renumbering = HashMap::new();
+    for (new, old) in order.iter().enumerate() {
+        renumbering.insert(*old, Block(new));
+    }
+
+    let mut blocks = Vec::new();
+    for old in order {
+        let mut data = function.block(old).clone();
+        if let Some(ref mut terminator) = data.terminator {
+            for target in terminator.targets_mut() {
+                target.block = renumbering[&target.block];
+            }
+        }
+        blocks.push(data);
+    }
+    function.blocks = blocks;
+}
+
+fn remove_unused(function: &mut ir::Function) {
+    // Mark everything used by a terminator, a call, a print or arithmetic
+    // that may trap, and then everything those use, and so on.
+    let mut definitions = HashMap::new();
+    for (index, data) in function.blocks.iter().enumerate() {
+        for (position, instruction) in data.instructions.iter().enumerate() {
+            for result in instruction.results() {
+                definitions.insert(result, (index, position));
+            }
+        }
+    }
+
+    // Block parameters and the branch arguments that feed them.
+    let mut parameters = HashMap::new();
+    for (index, data) in function.blocks.iter().enumerate() {
+        for (position, parameter) in data.parameters.iter().enumerate() {
+            parameters.insert(*parameter, (Block(index), position));
+        }
+    }
+    let mut arguments: HashMap<(Block, usize), Vec<Value>> = HashMap::new();
+    for data in &function.blocks {
+        for target in data.terminator.as_ref().unwrap().targets() {
+            for (position, argument) in target.arguments.iter().enumerate() {
+                arguments.entry((target.block, position)).or_default()
+                    .push(*argument);
+            }
+        }
+    }
+
+    let mut live = HashSet::new();
+    let mut pending = Vec::new();
+    for data in &function.blocks {
+        for instruction in &data.instructions {
+            if let InstructionKind::Call(..) |
+                   InstructionKind::Print(_) |
+                   InstructionKind::Store(..) |
+                   InstructionKind::Copy(..) |
+                   InstructionKind::Binary(_, Overflow::Trap, _, _) = instruction.kind {
+                pending.push(instruction.result);
+            }
+        }
+        // Branch arguments are only live if the parameter is.
+        match *data.terminator.as_ref().unwrap() {
+            Terminator::Return(ref values) => pending.extend(values.iter().cloned()),
+            Terminator::CondBranch(condition, _, _) |
+            Terminator::Switch(condition, _, _) => pending.push(condition),
+            Terminator::Branch(_) | Terminator::Trap => (),
+        }
+    }
+    // The function's own parameters are always kept.
+    pending.extend(function.parameters().iter().cloned());
+
+    while let Some(value) = pending.pop() {
+        if !live.insert(value) {
+            continue;
+        }
+
+        if let Some(&(index, position)) = definitions.get(&value) {
+            pending.extend(function.blocks[index].instructions[position].operands());
+        }
+        if let Some(&(block, position)) = parameters.get(&value) {
+            if let Some(arguments) = arguments.get(&(block, position)) {
+                pending.extend(arguments.iter().cloned());
+            }
+        }
+    }
+
+    // Sweep, dropping dead parameters' arguments along with them.
+    let entry = function.entry();
+    let mut dead_parameters = HashMap::new();
+    for (index, data) in function.blocks.iter_mut().enumerate() {
+        data.instructions.retain(|instruction| live.contains(&instruction.result));
+
+        if Block(index) != entry {
+            let dead: Vec<usize> = data.parameters.iter().enumerate()
+                .filter(|&(_, parameter)| !live.contains(parameter))
+                .map(|(position, _)| position)
+                .collect();
+            data.parameters.retain(|parameter| live.contains(parameter));
+            dead_parameters.insert(Block(index), dead);
+        }
+    }
+    for data in &mut function.blocks {
+        for target in data.terminator.as_mut().unwrap().targets_mut() {
+            remove_arguments(target, &dead_parameters);
+        }
+    }
+}
+
+fn remove_arguments(target: &mut Target, dead_parameters: &HashMap<Block, Vec<usize>>) {
+    if let Some(dead) = dead_parameters.get(&target.block) {
+        // Backwards, so earlier positions stay valid.
+        for position in dead.iter().rev() {
+            target.arguments.remove(*position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dce;
+    use ir::verify::verify;
+    use ir::{
+        BinaryOp, Block, Function, InstructionKind, Module, Overflow, Target, Terminator, Type,
+    };
+
+    /// Run DCE over `function`, checking it's well formed before and after.
+    ///
+    fn eliminated(function: Function) -> Function {
+        let mut module = Module { functions: vec![function] };
+        verify(&module).unwrap();
+        dce(&mut module);
+        verify(&module).unwrap();
+        module.functions.pop().unwrap()
+    }
+
+    /// `f() -> i32`, which branches on a constant `condition` to a block
+    /// returning 7 or one returning 8, through a joining block.
+    ///
+    fn constant_branch(condition: i128) -> Function {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let (then_block, else_block, join) =
+            (function.new_block(), function.new_block(), function.new_block());
+        let result = function.add_parameter(join, Type::I32);
+
+        let condition = function.push(entry, Type::I32, InstructionKind::Const(condition));
+        function.terminate(entry, Terminator::CondBranch(
+            condition, Target::new(then_block), Target::new(else_block),
+        ));
+        for &(block, value) in &[(then_block, 7), (else_block, 8)] {
+            let value = function.push(block, Type::I32, InstructionKind::Const(value));
+            function.terminate(block, Terminator::Branch(Target {
+                block: join,
+                arguments: vec![value],
+            }));
+        }
+        function.terminate(join, Terminator::Return(vec![result]));
+        function
+    }
+
+    #[test]
+    fn branches_on_constants_are_folded_and_blocks_merged() {
+        for &(condition, expected) in &[(1, 7), (0, 8)] {
+            let function = eliminated(constant_branch(condition));
+            assert_eq!(function.blocks.len(), 1);
+            let entry = function.block(Block(0));
+            assert_eq!(entry.instructions.len(), 1);
+            assert_eq!(entry.instructions[0].kind, InstructionKind::Const(expected));
+            assert_eq!(
+                entry.terminator,
+                Some(Terminator::Return(vec![entry.instructions[0].result])),
+            );
+        }
+    }
+
+    #[test]
+    fn switches_on_constants_are_folded() {
+        for &(value, expected) in &[(2, 2), (5, 3)] {
+            let mut function = Function::new("f".to_string(), vec![Type::I32]);
+            let entry = function.new_block();
+            let cases: Vec<Block> = (0..4).map(|_| function.new_block()).collect();
+            let scrutinee = function.push(entry, Type::I32, InstructionKind::Const(value));
+            function.terminate(entry, Terminator::Switch(
+                scrutinee,
+                (0..3).map(|case| (case as i128, Target::new(cases[case]))).collect(),
+                Target::new(cases[3]),
+            ));
+            for (index, &block) in cases.iter().enumerate() {
+                let result = function.push(block, Type::I32, InstructionKind::Const(index as i128));
+                function.terminate(block, Terminator::Return(vec![result]));
+            }
+
+            let function = eliminated(function);
+            assert_eq!(function.blocks.len(), 1);
+            assert_eq!(
+                function.block(Block(0)).instructions[0].kind,
+                InstructionKind::Const(expected),
+            );
+        }
+    }
+
+    #[test]
+    fn unused_results_are_removed_but_side_effects_kept() {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let a = function.add_parameter(entry, Type::I32);
+        let add = |overflow| InstructionKind::Binary(BinaryOp::Add, overflow, a, a);
+        function.push(entry, Type::I32, add(Overflow::Wrap));
+        function.push(entry, Type::I32, add(Overflow::Saturate));
+        function.push(entry, Type::Str, InstructionKind::Str("unused".to_string()));
+        let slot = function.push(entry, Type::Ptr, InstructionKind::Slot(4, 4));
+        function.push(entry, Type::I32, InstructionKind::Load(slot, 0));
+        let kept = vec![
+            add(Overflow::Trap),
+            InstructionKind::Print(a),
+            InstructionKind::Store(slot, 0, a),
+        ];
+        for kind in &kept {
+            function.push(entry, Type::I32, kind.clone());
+        }
+        function.terminate(entry, Terminator::Return(vec![a]));
+
+        let function = eliminated(function);
+        let kinds: Vec<InstructionKind> = function.block(Block(0)).instructions.iter()
+            .map(|instruction| instruction.kind.clone())
+            .collect();
+        assert_eq!(kinds, [&[InstructionKind::Slot(4, 4)], &kept[..]].concat());
+    }
+
+    /// A block parameter that's never used is removed, along with the
+    /// arguments branches pass it.
+    ///
+    #[test]
+    fn unused_block_parameters_are_removed() {
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let a = function.add_parameter(entry, Type::I32);
+        let (then_block, else_block) = (function.new_block(), function.new_block());
+        let join = function.new_block();
+        function.add_parameter(join, Type::I32);
+        let used = function.add_parameter(join, Type::I32);
+
+        function.terminate(entry, Terminator::CondBranch(
+            a, Target::new(then_block), Target::new(else_block),
+        ));
+        for block in [then_block, else_block] {
+            let value = function.push(block, Type::I32, InstructionKind::Const(1));
+            function.terminate(block, Terminator::Branch(Target {
+                block: join,
+                arguments: vec![value, a],
+            }));
+        }
+        function.terminate(join, Terminator::Return(vec![used]));
+
+        let function = eliminated(function);
+        assert_eq!(function.block(join).parameters, vec![used]);
+        for block in [then_block, else_block] {
+            let data = function.block(block);
+            assert!(data.instructions.is_empty());
+            assert_eq!(data.terminator, Some(Terminator::Branch(Target {
+                block: join,
+                arguments: vec![a],
+            })));
+        }
+    }
+}
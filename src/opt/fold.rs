@@ -0,0 +1,320 @@
+//! Constant folding and algebraic simplification.
+//!
+//! Arithmetic and comparisons on integer constants are done at compile time.
+//! Arithmetic that overflows its type wraps or saturates if it would at run
+//! time, and if it would trap, that's reported as an error instead. Floats
+//! are left alone, to be computed exactly as the back-ends do.
+//!
+//! Identities are simplified where one operand is constant:
+//!
+//! - `x + 0`, `0 + x`, `x - 0`, `x * 1` and `1 * x` become `x`;
+//! - `x * 0` and `0 * x` become `0`.
+//!
+//! (`x` itself is still computed in the last case, since it may be a call.)
+//!
+//! Constants (including strings) left unused by folding are removed.
+
+use std::collections::{ HashMap, HashSet };
+
+use ir::{ self, BinaryOp, InstructionKind, Overflow, Type, Value };
+
+pub fn fold(module: &mut ir::Module) -> Result<(), String> {
+    for function in &mut module.functions {
+        fold_function(function).map_err(
+            |message| format!("in function `{}`: {}", function.name, message)
+        )?;
+    }
+    Ok(())
+}
+
+/// What an instruction simplifies to.
+///
+enum Folded {
+    Constant(i128),
+    Alias(Value),
+}
+
+fn fold_function(function: &mut ir::Function) -> Result<(), String> {
+    let mut constants = HashMap::new();
+    let mut replacements = HashMap::new();
+
+    // The type of every value, since blocks are borrowed mutably below.
+    let types = function.values.clone();
+
+    // In reverse post-order, every value's definition is seen before its
+    // uses (other than as block arguments), so one pass gets everything.
+    for block in function.reverse_post_order() {
+        let instructions = &mut function.block_mut(block).instructions;
+
+        let mut index = 0;
+        while index < instructions.len() {
+            let instruction = &mut instructions[index];
+            instruction.map_operands(|value| resolve(&replacements, value));
+
+            let folded = match instruction.kind {
+                InstructionKind::Const(value) => {
+                    constants.insert(instruction.result, value);
+                    None
+                },
+                InstructionKind::Binary(op, overflow, lhs, rhs) => binary(
+                    op, overflow, types[lhs.0], lhs, rhs,
+                    constants.get(&lhs).cloned(), constants.get(&rhs).cloned(),
+                )?,
+                InstructionKind::Float(_) |
+                InstructionKind::Str(_) |
+                InstructionKind::Convert(_) |
+                InstructionKind::Call(..) |
+                InstructionKind::Print(_) |
+                InstructionKind::Slot(..) |
+                InstructionKind::Load(..) |
+                InstructionKind::Store(..) |
+                InstructionKind::Copy(..) |
+                InstructionKind::Element(..) => None,
+            };
+
+            match folded {
+                Some(Folded::Constant(value)) => {
+                    instruction.kind = InstructionKind::Const(value);
+                    constants.insert(instruction.result, value);
+                    index += 1;
+                },
+                Some(Folded::Alias(value)) => {
+                    replacements.insert(instruction.result, value);
+                    instructions.remove(index);
+                },
+                None => index += 1,
+            }
+        }
+    }
+
+    // This also catches uses as block arguments, and in unreachable blocks.
+    function.replace_uses(&replacements);
+
+    let mut used = HashSet::new();
+    for data in &function.blocks {
+        for instruction in &data.instructions {
+            used.extend(instruction.operands());
+        }
+        used.extend(data.terminator.as_ref().unwrap().operands());
+    }
+    for data in &mut function.blocks {
+        data.instructions.retain(|instruction| match instruction.kind {
+            InstructionKind::Const(_) |
+            InstructionKind::Float(_) |
+            InstructionKind::Str(_) => used.contains(&instruction.result),
+            _ => true,
+        });
+    }
+
+    Ok(())
+}
+
+/// Fold `lhs op rhs`, where the operands are of type `ty`.
+///
+fn binary(
+    op: BinaryOp,
+    overflow: Overflow,
+    ty: Type,
+    lhs: Value,
+    rhs: Value,
+    lhs_constant: Option<i128>,
+    rhs_constant: Option<i128>,
+) -> Result<Option<Folded>, String> {
+    // Only integers and chars are ever constants here, so both operands are,
+    // and chars are only compared. Constants hold their exact values, so
+    // comparisons are right whether or not the type is signed. Only the
+    // product of two large `u64`s is too big for an `i128`, and saturating
+    // that is still out of range for the type.
+    if let (Some(a), Some(b)) = (lhs_constant, rhs_constant) {
+        let (result, symbol) = match op {
+            BinaryOp::Add => (a + b, "+"),
+            BinaryOp::Sub => (a - b, "-"),
+            BinaryOp::Mul => (a.saturating_mul(b), "*"),
+            BinaryOp::Eq => ((a == b) as i128, "=="),
+            BinaryOp::Ne => ((a != b) as i128, "!="),
+            BinaryOp::Lt => ((a < b) as i128, "<"),
+            BinaryOp::Le => ((a <= b) as i128, "<="),
+            BinaryOp::Gt => ((a > b) as i128, ">"),
+            BinaryOp::Ge => ((a >= b) as i128, ">="),
+        };
+
+        if op.is_comparison() || ty.contains(result) {
+            return Ok(Some(Folded::Constant(result)));
+        }
+        let (min, max) = ty.range();
+        return match overflow {
+            Overflow::Wrap if op == BinaryOp::Mul => {
+                Ok(Some(Folded::Constant(ty.wrap(a.wrapping_mul(b)))))
+            },
+            Overflow::Wrap => Ok(Some(Folded::Constant(ty.wrap(result)))),
+            Overflow::Saturate => Ok(Some(Folded::Constant(result.clamp(min, max)))),
+            Overflow::Trap => Err(format!("`{} {} {}` overflows {}", a, symbol, b, ty)),
+        };
+    }
+
+    Ok(match (op, lhs_constant, rhs_constant) {
+        (BinaryOp::Add, _, Some(0)) |
+        (BinaryOp::Sub, _, Some(0)) |
+        (BinaryOp::Mul, _, Some(1)) => Some(Folded::Alias(lhs)),
+
+        (BinaryOp::Add, Some(0), _) |
+        (BinaryOp::Mul, Some(1), _) => Some(Folded::Alias(rhs)),
+
+        (BinaryOp::Mul, _, Some(0)) |
+        (BinaryOp::Mul, Some(0), _) => Some(Folded::Constant(0)),
+
+        _ => None,
+    })
+}
+
+fn resolve(replacements: &HashMap<Value, Value>, mut value: Value) -> Value {
+    while let Some(replacement) = replacements.get(&value) {
+        value = *replacement;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold;
+    use ir::verify::verify;
+    use ir::{ BinaryOp, Function, InstructionKind, Module, Overflow, Terminator, Type, Value };
+
+    /// `f() -> ty`, returning `a op b` for constants `a` and `b` of type `ty`,
+    /// or the comparison's result as an `i32`.
+    ///
+    fn constants(op: BinaryOp, overflow: Overflow, ty: Type, a: i128, b: i128) -> Module {
+        let returns = if op.is_comparison() { Type::I32 } else { ty };
+        let mut function = Function::new("f".to_string(), vec![returns]);
+        let entry = function.new_block();
+        let lhs = function.push(entry, ty, InstructionKind::Const(a));
+        let rhs = function.push(entry, ty, InstructionKind::Const(b));
+        let result = function.push(
+            entry, returns, InstructionKind::Binary(op, overflow, lhs, rhs),
+        );
+        function.terminate(entry, Terminator::Return(vec![result]));
+        Module { functions: vec![function] }
+    }
+
+    /// `f(x: i32) -> i32`, returning `x op k`, or `k op x` if `constant_first`,
+    /// trapping on overflow if `op` can.
+    ///
+    fn with_parameter(op: BinaryOp, k: i128, constant_first: bool) -> (Module, Value) {
+        let overflow = if op.is_comparison() { Overflow::Wrap } else { Overflow::Trap };
+        let mut function = Function::new("f".to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let x = function.add_parameter(entry, Type::I32);
+        let k = function.push(entry, Type::I32, InstructionKind::Const(k));
+        let (lhs, rhs) = if constant_first { (k, x) } else { (x, k) };
+        let result = function.push(
+            entry, Type::I32, InstructionKind::Binary(op, overflow, lhs, rhs),
+        );
+        function.terminate(entry, Terminator::Return(vec![result]));
+        (Module { functions: vec![function] }, x)
+    }
+
+    /// The entry block's instructions, and the values it returns, once folded
+    /// (which must leave the module well formed).
+    ///
+    fn folded(mut module: Module) -> (Vec<InstructionKind>, Vec<Value>) {
+        verify(&module).unwrap();
+        fold(&mut module).unwrap();
+        verify(&module).unwrap();
+        let entry = &module.functions[0].blocks[0];
+        let kinds = entry.instructions.iter().map(|instruction| instruction.kind.clone());
+        match entry.terminator {
+            Some(Terminator::Return(ref values)) => (kinds.collect(), values.clone()),
+            ref terminator => panic!("expected a return but found {:?}", terminator),
+        }
+    }
+
+    #[test]
+    fn folds_arithmetic_on_constants() {
+        for &(op, overflow, ty, a, b, expected) in &[
+            (BinaryOp::Add, Overflow::Trap, Type::I32, 2, 3, 5),
+            (BinaryOp::Sub, Overflow::Trap, Type::I32, 2, 3, -1),
+            (BinaryOp::Mul, Overflow::Trap, Type::I64, -4, 3, -12),
+            (BinaryOp::Add, Overflow::Wrap, Type::I32, 2147483647, 1, -2147483648),
+            (BinaryOp::Sub, Overflow::Wrap, Type::U8, 0, 1, 255),
+            (BinaryOp::Mul, Overflow::Wrap, Type::U8, 16, 17, 16),
+            (BinaryOp::Mul, Overflow::Wrap, Type::U64, 1 << 63, 1 << 63, 0),
+            (BinaryOp::Add, Overflow::Saturate, Type::I8, 100, 100, 127),
+            (BinaryOp::Sub, Overflow::Saturate, Type::I8, -100, 100, -128),
+            (BinaryOp::Sub, Overflow::Saturate, Type::U16, 1, 2, 0),
+            (BinaryOp::Mul, Overflow::Saturate, Type::U64, 1 << 63, 1 << 63, u64::MAX as i128),
+        ] {
+            let (instructions, _) = folded(constants(op, overflow, ty, a, b));
+            assert_eq!(
+                instructions, vec![InstructionKind::Const(expected)],
+                "{} {:?} {} on {}", a, op, b, ty,
+            );
+        }
+    }
+
+    #[test]
+    fn folds_comparisons_on_constants() {
+        for &(op, ty, a, b, expected) in &[
+            (BinaryOp::Eq, Type::I32, 1, 1, 1),
+            (BinaryOp::Ne, Type::I32, 1, 1, 0),
+            (BinaryOp::Lt, Type::I64, -1, 0, 1),
+            (BinaryOp::Le, Type::U64, u64::MAX as i128, 0, 0),
+            (BinaryOp::Gt, Type::Char, 'b' as i128, 'a' as i128, 1),
+            (BinaryOp::Ge, Type::U8, 7, 7, 1),
+        ] {
+            let (instructions, _) = folded(constants(op, Overflow::Wrap, ty, a, b));
+            assert_eq!(
+                instructions, vec![InstructionKind::Const(expected)],
+                "{} {:?} {} on {}", a, op, b, ty,
+            );
+        }
+    }
+
+    #[test]
+    fn overflow_that_would_trap_is_an_error() {
+        for &(op, ty, a, b, expected) in &[
+            (BinaryOp::Add, Type::I32, 2147483647, 1, "`2147483647 + 1` overflows i32"),
+            (BinaryOp::Sub, Type::U8, 0, 1, "`0 - 1` overflows u8"),
+            (BinaryOp::Mul, Type::I16, 256, 128, "`256 * 128` overflows i16"),
+        ] {
+            let mut module = constants(op, Overflow::Trap, ty, a, b);
+            assert_eq!(fold(&mut module), Err(format!("in function `f`: {}", expected)));
+        }
+    }
+
+    #[test]
+    fn identities_become_the_other_operand() {
+        for &(op, k, constant_first) in &[
+            (BinaryOp::Add, 0, false),
+            (BinaryOp::Add, 0, true),
+            (BinaryOp::Sub, 0, false),
+            (BinaryOp::Mul, 1, false),
+            (BinaryOp::Mul, 1, true),
+        ] {
+            let (module, x) = with_parameter(op, k, constant_first);
+            assert_eq!(folded(module), (Vec::new(), vec![x]), "{:?} by {}", op, k);
+        }
+    }
+
+    #[test]
+    fn multiplying_by_zero_is_zero() {
+        for &constant_first in &[false, true] {
+            let (module, _) = with_parameter(BinaryOp::Mul, 0, constant_first);
+            let (instructions, _) = folded(module);
+            assert_eq!(instructions, vec![InstructionKind::Const(0)]);
+        }
+    }
+
+    #[test]
+    fn leaves_other_operations_alone() {
+        for &(op, k, constant_first) in &[
+            (BinaryOp::Sub, 0, true),
+            (BinaryOp::Add, 1, false),
+            (BinaryOp::Mul, 2, true),
+            (BinaryOp::Eq, 0, false),
+        ] {
+            let (module, _) = with_parameter(op, k, constant_first);
+            let (instructions, _) = folded(module);
+            assert_eq!(instructions.len(), 2, "{:?} by {}", op, k);
+        }
+    }
+}
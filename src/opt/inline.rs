@@ -0,0 +1,285 @@
+//! Inlining of small, non-recursive functions.
+//!
+//! A call is replaced by a copy of the callee's blocks: the calling block is
+//! split at the call, branches into the copy of the callee's entry, and every
+//! `return` in the copy becomes a branch to the rest of the calling block,
+//! passing the returned values as its parameters.
+//!
+//! Only callees of at most `THRESHOLD` instructions are inlined, and none that
+//! can reach themselves through calls, so inlining always terminates. Callees
+//! are kept, since they may still be called from elsewhere (or exported).
+
+use std::collections::{ HashMap, HashSet };
+
+use ir::{ self, Block, BlockData, Instruction, InstructionKind, Target, Terminator, Value };
+
+/// The most instructions a function may have and still be inlined.
+///
+const THRESHOLD: usize = 16;
+
+pub fn inline(module: &mut ir::Module) {
+    let candidates = candidates(module);
+    if candidates.is_empty() {
+        return;
+    }
+
+    // Callees are copied as they were before anything was inlined into them.
+    let originals: HashMap<String, ir::Function> = module.functions.iter()
+        .filter(|function| candidates.contains(&function.name))
+        .map(|function| (function.name.clone(), function.clone()))
+        .collect();
+
+    for function in &mut module.functions {
+        // Calls in inlined copies are found too, as later blocks; there are
+        // only finitely many, since no candidate can reach itself.
+        let mut index = 0;
+        while index < function.blocks.len() {
+            let call = function.blocks[index].instructions.iter().position(|instruction| {
+                match instruction.kind {
+                    InstructionKind::Call(ref name, ..) => originals.contains_key(name),
+                    _ => false,
+                }
+            });
+
+            match call {
+                Some(position) => inline_call(function, Block(index), position, &originals),
+                None => index += 1,
+            }
+        }
+    }
+}
+
+/// The functions small enough to inline, and not recursive.
+///
+fn candidates(module: &ir::Module) -> HashSet<String> {
+    let mut calls: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for function in &module.functions {
+        let callees = calls.entry(function.name.as_str()).or_default();
+        for data in &function.blocks {
+            for instruction in &data.instructions {
+                if let InstructionKind::Call(ref name, ..) = instruction.kind {
+                    callees.insert(name.as_str());
+                }
+            }
+        }
+    }
+
+    let reaches_itself = |start: &str| {
+        let mut visited = HashSet::new();
+        let mut pending: Vec<&str> = calls[start].iter().cloned().collect();
+        while let Some(name) = pending.pop() {
+            if name == start {
+                return true;
+            }
+            if visited.insert(name) {
+                if let Some(callees) = calls.get(name) {
+                    pending.extend(callees.iter().cloned());
+                }
+            }
+        }
+        false
+    };
+
+    module.functions.iter()
+        .filter(|function| {
+            let size: usize = function.blocks.iter()
+                .map(|data| data.instructions.len())
+                .sum();
+            size <= THRESHOLD && !reaches_itself(&function.name)
+        })
+        .map(|function| function.name.clone())
+        .collect()
+}
+
+/// Inline the call at `position` in `block`.
+///
+fn inline_call(
+    function: &mut ir::Function,
+    block: Block,
+    position: usize,
+    originals: &HashMap<String, ir::Function>,
+) {
+    // Split the block: the call's results become the parameters of the rest.
+    let continuation = function.new_block();
+    let (call, rest, terminator) = {
+        let data = function.block_mut(block);
+        let rest = data.instructions.split_off(position + 1);
+        let call = data.instructions.pop().unwrap();
+        (call, rest, data.terminator.take())
+    };
+    {
+        let data = function.block_mut(continuation);
+        data.parameters.extend(call.results());
+        data.instructions = rest;
+        data.terminator = terminator;
+    }
+
+    let (name, arguments) = match call.kind {
+        InstructionKind::Call(name, arguments, _) => (name, arguments),
+        _ => unreachable!(),
+    };
+    let callee = &originals[&name];
+
+    // The callee's parameters are just the arguments; every other value and
+    // every block gets a fresh copy.
+    let mut values: HashMap<Value, Value> = callee.parameters().iter().cloned()
+        .zip(arguments)
+        .collect();
+    let base = function.blocks.len();
+
+    for (index, data) in callee.blocks.iter().enumerate() {
+        let mut copy = |value: Value| {
+            if let Some(copied) = values.get(&value) {
+                return *copied;
+            }
+            let copied = function.new_value(callee.type_of(value));
+            values.insert(value, copied);
+            copied
+        };
+
+        let parameters = match index {
+            0 => Vec::new(),
+            _ => data.parameters.iter().map(|parameter| copy(*parameter)).collect(),
+        };
+        let instructions = data.instructions.iter().map(|instruction| {
+            let mut instruction = instruction.clone();
+            instruction.map_operands(&mut copy);
+            if let InstructionKind::Call(_, _, ref mut more) = instruction.kind {
+                for value in more {
+                    *value = copy(*value);
+                }
+            }
+            Instruction {
+                result: copy(instruction.result),
+                kind: instruction.kind,
+            }
+        }).collect();
+
+        let mut terminator = data.terminator.clone().unwrap();
+        terminator.map_operands(&mut copy);
+        for target in terminator.targets_mut() {
+            target.block = Block(base + target.block.0);
+        }
+        if let Terminator::Return(values) = terminator {
+            terminator = Terminator::Branch(Target {
+                block: continuation,
+                arguments: values,
+            });
+        }
+
+        function.blocks.push(BlockData {
+            parameters,
+            instructions,
+            terminator: Some(terminator),
+        });
+    }
+
+    function.terminate(block, Terminator::Branch(Target::new(Block(base))));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ inline, THRESHOLD };
+    use ir::verify::verify;
+    use ir::{ BinaryOp, Function, InstructionKind, Module, Overflow, Terminator, Type };
+
+    /// `name(x: i32) -> i32`, which returns `x + x` after calling each of
+    /// `callees` with `x`, and has at least `size` instructions.
+    ///
+    fn function(name: &str, callees: &[&str], size: usize) -> Function {
+        let mut function = Function::new(name.to_string(), vec![Type::I32]);
+        let entry = function.new_block();
+        let x = function.add_parameter(entry, Type::I32);
+        for callee in callees {
+            function.push(entry, Type::I32, InstructionKind::Call(
+                callee.to_string(), vec![x], Vec::new(),
+            ));
+        }
+        while function.block(entry).instructions.len() + 1 < size {
+            function.push(entry, Type::I32, InstructionKind::Print(x));
+        }
+        let sum = function.push(
+            entry, Type::I32, InstructionKind::Binary(BinaryOp::Add, Overflow::Trap, x, x),
+        );
+        function.terminate(entry, Terminator::Return(vec![sum]));
+        function
+    }
+
+    /// Inline calls in `functions`, checking the module is well formed before
+    /// and after, and give what each calls afterwards.
+    ///
+    fn calls_after_inlining(functions: Vec<Function>) -> Vec<(String, Vec<String>)> {
+        let mut module = Module { functions };
+        verify(&module).unwrap();
+        inline(&mut module);
+        verify(&module).unwrap();
+        module.functions.iter().map(|function| {
+            let calls = function.blocks.iter()
+                .flat_map(|data| data.instructions.iter())
+                .filter_map(|instruction| match instruction.kind {
+                    InstructionKind::Call(ref name, ..) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            (function.name.clone(), calls)
+        }).collect()
+    }
+
+    fn calls(name: &str, callees: &[&str]) -> (String, Vec<String>) {
+        (name.to_string(), callees.iter().map(|callee| callee.to_string()).collect())
+    }
+
+    #[test]
+    fn small_callees_are_inlined() {
+        assert_eq!(
+            calls_after_inlining(vec![
+                function("f", &["g", "g"], 0),
+                function("g", &["h"], 0),
+                function("h", &[], THRESHOLD),
+            ]),
+            vec![calls("f", &[]), calls("g", &[]), calls("h", &[])],
+        );
+    }
+
+    #[test]
+    fn the_callee_is_copied_into_the_caller() {
+        let mut module = Module {
+            functions: vec![function("f", &["g"], 0), function("g", &[], 0)],
+        };
+        inline(&mut module);
+        verify(&module).unwrap();
+
+        // The call's block branches to a copy of `g`, which branches on to
+        // the rest of the block, with `x + x` as its parameter.
+        let f = module.function("f").unwrap();
+        let x = f.parameters()[0];
+        assert_eq!(f.blocks.len(), 3);
+        let copied = &f.blocks[2].instructions;
+        assert_eq!(copied.len(), 1);
+        assert_eq!(copied[0].kind, InstructionKind::Binary(BinaryOp::Add, Overflow::Trap, x, x));
+        assert_eq!(f.blocks[1].parameters.len(), 1);
+    }
+
+    #[test]
+    fn large_callees_are_not_inlined() {
+        assert_eq!(
+            calls_after_inlining(vec![
+                function("f", &["g"], 0),
+                function("g", &[], THRESHOLD + 1),
+            ]),
+            vec![calls("f", &["g"]), calls("g", &[])],
+        );
+    }
+
+    #[test]
+    fn recursive_callees_are_not_inlined() {
+        assert_eq!(
+            calls_after_inlining(vec![
+                function("f", &["f", "g"], 0),
+                function("g", &["h"], 0),
+                function("h", &["g"], 0),
+            ]),
+            vec![calls("f", &["f", "g"]), calls("g", &["h"]), calls("h", &["g"])],
+        );
+    }
+}
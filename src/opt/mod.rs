@@ -0,0 +1,125 @@
+//! Optimisation passes over IR.
+//!
+//! Each pass takes a `Module` that passes `ir::verify` and leaves it so.
+//! `optimise` runs the passes chosen by an optimisation level.
+
+use ir;
+
+pub mod fold;
+pub mod cse;
+pub mod dce;
+pub mod inline;
+
+/// How hard to optimise, as chosen by `-O0`, `-O1` or `-O2`.
+///
+#[derive (Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// No optimisation, so the IR is as lowered.
+    O0,
+    /// Constant folding, common subexpression and dead code elimination.
+    O1,
+    /// As `O1`, after inlining small functions, and repeated for as long as
+    /// it makes a difference.
+    O2,
+}
+
+pub fn optimise(module: &mut ir::Module, level: Level) -> Result<(), String> {
+    if level >= Level::O2 {
+        inline::inline(module);
+    }
+    if level >= Level::O1 {
+        loop {
+            let before = match level {
+                Level::O2 => Some(module.clone()),
+                _ => None,
+            };
+
+            fold::fold(module)?;
+            cse::cse(module);
+            dce::dce(module);
+
+            // Merging blocks may leave more to fold, and so on.
+            if before.is_none_or(|before| before == *module) {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ optimise, Level };
+    use ir::{ self, InstructionKind, Overflow, Terminator };
+    use ir::lower::lower;
+    use ir::verify::verify;
+    use lex::Lexer;
+    use parse::Parser;
+
+    fn lowered(source: &str) -> ir::Module {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let tree = Parser::new().parse(tokens).unwrap();
+        lower(&tree, Overflow::Trap).unwrap()
+    }
+
+    const PROGRAMS: &[&str] = &[
+        "double(x: i32) -> i32 { return x + x; } \
+         main() -> i32 { return double(3) + 1; }",
+        "f(a: i32, b: i32) -> i32 { \
+             x = a + b; y = b + a; \
+             if a > 0 { z = a + b; return z * y; } \
+             return x * y; \
+         } \
+         main() -> i32 { return f(2, 3); }",
+        "sum(a: [i32; 4]) -> i32 { return a[0] + a[1] + a[2] + a[3]; } \
+         main() -> i32 { a = [1, 2, 3, 4]; a[2] = 10; return sum(a); }",
+        "sign(x: i64) -> i32 { match x { 0 => { return 0; }, _ => { return 1; } } } \
+         main() -> i32 { print(sign(7)); return sign(0); }",
+        "struct P { x: i32, y: i32 } \
+         get(p: &P) -> &i32 { return &p.y; } \
+         main() -> i32 { p = P { x: 1, y: 2 }; return *get(&p) + p.x; }",
+    ];
+
+    #[test]
+    fn every_level_leaves_the_module_well_formed() {
+        for source in PROGRAMS {
+            for &level in &[Level::O0, Level::O1, Level::O2] {
+                let mut module = lowered(source);
+                optimise(&mut module, level).unwrap();
+                if let Err(message) = verify(&module) {
+                    panic!("at {:?}, {} for:\n{}", level, message, source);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn o0_leaves_the_module_as_lowered() {
+        for source in PROGRAMS {
+            let mut module = lowered(source);
+            optimise(&mut module, Level::O0).unwrap();
+            assert_eq!(module, lowered(source));
+        }
+    }
+
+    #[test]
+    fn o2_folds_through_inlined_calls() {
+        let mut module = lowered(PROGRAMS[0]);
+        optimise(&mut module, Level::O2).unwrap();
+        let main = module.function("main").unwrap();
+        assert_eq!(main.blocks.len(), 1);
+        let entry = &main.blocks[0];
+        assert_eq!(entry.instructions.len(), 1);
+        assert_eq!(entry.instructions[0].kind, InstructionKind::Const(7));
+        assert_eq!(entry.terminator, Some(Terminator::Return(vec![entry.instructions[0].result])));
+
+        // Without inlining, the call is kept.
+        let mut module = lowered(PROGRAMS[0]);
+        optimise(&mut module, Level::O1).unwrap();
+        let calls = module.function("main").unwrap().blocks.iter()
+            .flat_map(|data| data.instructions.iter())
+            .filter(|instruction| matches!(instruction.kind, InstructionKind::Call(..)))
+            .count();
+        assert_eq!(calls, 1);
+    }
+}
@@ -1,16 +1,34 @@
 use lex;
 
-mod syntax {
+pub mod syntax {
     pub mod function;
     pub mod signature;
+    pub mod parameter;
     pub mod block;
     pub mod statement;
     pub mod expression;
     pub mod binary;
+    pub mod cast;
     pub mod init;
     pub mod call;
     pub mod name;
     pub mod literal;
+    pub mod ret;
+    pub mod conditional;
+    pub mod structure;
+    pub mod construct;
+    pub mod field;
+    pub mod assign;
+    pub mod enumeration;
+    pub mod variant;
+    pub mod matching;
+    pub mod pattern;
+    pub mod ty;
+    pub mod array;
+    pub mod index;
+    pub mod reference;
+    pub mod dereference;
+    pub mod tuple;
 
     /// A node in the abstract syntax tree.
     ///
@@ -24,17 +42,29 @@ mod syntax {
 
         // std::any is used for down-casting
 
-        fn any(&self) -> Option<&std::any::Any>;
-        fn any_mut(&mut self) -> Option<&mut std::any::Any>;
+        fn any(&self) -> Option<&dyn std::any::Any>;
+        fn any_mut(&mut self) -> Option<&mut dyn std::any::Any>;
+    }
+
+    /// Down-cast a `dyn Syntax` to a concrete `Syntax`, if it is one.
+    ///
+    /// This is the free-standing, generic counterpart of e.g.
+    /// `binary::Syntax::binary_mut()`, for use outside of `parse`.
+    ///
+    pub fn downcast<T: 'static>(syntax: &dyn Syntax) -> Option<&T> {
+        syntax.any()?.downcast_ref::<T>()
     }
 }
 
 #[derive (Debug)]
 pub struct Tree {
     // @TODO dedicated top-level expression
-    pub function: syntax::function::Syntax,
+    pub functions: Vec<syntax::function::Syntax>,
+    pub structs: Vec<syntax::structure::Syntax>,
+    pub enums: Vec<syntax::enumeration::Syntax>,
 }
 
+#[derive (Default)]
 pub struct Parser {}
 
 impl Parser {
@@ -44,10 +74,29 @@ impl Parser {
         &self,
         tokens: Vec<lex::Token<'a>>
     ) -> Option<Tree> {
+        let mut tokens = TokenIter::new(tokens.iter());
+
+        // Functions, structs and enums are all there is at the top level, for
+        // now.
+        let mut functions = Vec::new();
+        let mut structs = Vec::new();
+        let mut enums = Vec::new();
+        while let Some(token) = tokens.peek() {
+            match token.kind {
+                lex::TokenKind::KeyStruct => {
+                    structs.push(syntax::structure::Syntax::parse(&mut tokens)?);
+                },
+                lex::TokenKind::KeyEnum => {
+                    enums.push(syntax::enumeration::Syntax::parse(&mut tokens)?);
+                },
+                _ => functions.push(syntax::function::Syntax::parse(&mut tokens)?),
+            }
+        }
+
         Some(Tree {
-            function: syntax::function::Syntax::parse(
-                &mut TokenIter::new(tokens.iter())
-            )?,
+            functions,
+            structs,
+            enums,
         })
     }
 }
@@ -60,6 +109,10 @@ impl Parser {
 /// Not to be confused with `lex::TokenIter`.
 pub struct TokenIter<'a, 'b: 'a, I> where I: Iterator<Item=&'a lex::Token<'b>> {
     tokens: std::iter::Peekable<I>,
+
+    /// Whether a name followed by `{` starts a struct literal. In an `if`'s
+    /// condition it doesn't, since the `{` starts the block.
+    pub structs: bool,
 }
 
 impl <'a, 'b, I> TokenIter<'a, 'b, I>
@@ -67,6 +120,7 @@ where I: Iterator<Item=&'a lex::Token<'b>> {
     pub fn new(tokens: I) -> Self {
         Self {
             tokens: tokens.peekable(),
+            structs: true,
         }
     }
 
@@ -76,7 +130,7 @@ where I: Iterator<Item=&'a lex::Token<'b>> {
     pub fn eat(
         &mut self,
         expected: lex::TokenKind,
-    ) -> Option<&lex::Token> {
+    ) -> Option<&lex::Token<'_>> {
         match self.tokens.peek()? {
             lex::Token { kind, .. } if *kind == expected => (),
             _ => return None,
@@ -0,0 +1,56 @@
+//! `Syntax` for an array literal, `[element, ...]`.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub elements: Vec<Box<dyn syntax::Syntax>>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::BracketOpen)?;
+
+        // The brackets delimit the elements, so they may be struct literals
+        // even in an `if`'s condition.
+        let structs = tokens.structs;
+        tokens.structs = true;
+
+        let mut elements = Vec::new();
+
+        if tokens.peek()?.kind != TokenKind::BracketClose {
+            loop {
+                elements.push(Box::new(
+                    syntax::expression::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>);
+
+                // Elements must be delimited by commas
+                match tokens.peek()?.kind {
+                    TokenKind::GramComma => {
+                        tokens.eat(TokenKind::GramComma)?;
+
+                        if tokens.peek()?.kind == TokenKind::BracketClose {
+                            break;
+                        }
+                    },
+                    TokenKind::BracketClose => break,
+                    _ => return None,
+                }
+            }
+        }
+        tokens.eat(TokenKind::BracketClose)?;
+        tokens.structs = structs;
+
+        Some(Syntax {
+            elements,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -0,0 +1,39 @@
+//! `Syntax` for assignment to a part of a local, such as `a.b = c;` or
+//! `a[i] = c;`, or through a reference, `*a = c;`, rather than to the whole of
+//! a local, which `init::Syntax` is for.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    /// A `field::Syntax`, `index::Syntax` or `dereference::Syntax`.
+    pub place: Box<dyn syntax::Syntax>,
+    pub rhs: Box<dyn syntax::Syntax>,
+}
+
+impl Syntax {
+    /// Must be passed the `place`, already parsed, but started on the `=`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        place: Box<dyn syntax::Syntax>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::OpAssign)?;
+
+        let rhs = Box::new(
+            syntax::expression::Syntax::parse(tokens)?,
+        ) as Box<dyn syntax::Syntax>;
+
+        tokens.eat(TokenKind::GramSemicolon);
+
+        Some(Syntax {
+            place,
+            rhs,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
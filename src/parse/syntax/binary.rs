@@ -6,6 +6,12 @@ use parse::{ syntax, TokenIter };
 lazy_static! {
     static ref PRECEDENCE: enum_map::EnumMap<TokenKind, usize> = enum_map! {
         // Greater values mean greater precedence.
+        TokenKind::OpEq => 0,
+        TokenKind::OpNe => 0,
+        TokenKind::OpLt => 0,
+        TokenKind::OpLe => 0,
+        TokenKind::OpGt => 0,
+        TokenKind::OpGe => 0,
         TokenKind::OpAdd => 1,
         TokenKind::OpSub => 1,
         TokenKind::OpMul => 2,
@@ -17,28 +23,36 @@ lazy_static! {
 
 #[derive (Debug)]
 pub struct Syntax {
-    lhs: Box<syntax::Syntax>,
-    rhs: Box<syntax::Syntax>,
-    op: TokenKind,
+    pub lhs: Box<dyn syntax::Syntax>,
+    pub rhs: Box<dyn syntax::Syntax>,
+    pub op: TokenKind,
 }
 
 // @TODO handle associativity
 
+/// Whether `kind` is a binary operator.
+pub fn is_binary_op(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::OpAdd | TokenKind::OpSub | TokenKind::OpMul |
+        TokenKind::OpEq | TokenKind::OpNe |
+        TokenKind::OpLt | TokenKind::OpLe | TokenKind::OpGt | TokenKind::OpGe
+    )
+}
+
 impl Syntax {
     /// Must be passed an `lhs` but started on the binary op's token itself.
     pub fn parse<'a, 'b: 'a, I>(
         tokens: &mut TokenIter<'a, 'b, I>,
-        mut lhs: Box<syntax::Syntax>,
+        mut lhs: Box<dyn syntax::Syntax>,
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
         // peek() and then eat() is a circuitous way of doing next().
         //
         // @OPTION here and elsewhere it's irritating doing all of them. Only
         // have one Token, and then have helpers?
         //
-        let op = match tokens.peek()? {
-            Token { kind: TokenKind::OpAdd, .. } => TokenKind::OpAdd,
-            Token { kind: TokenKind::OpSub, .. } => TokenKind::OpSub,
-            Token { kind: TokenKind::OpMul, .. } => TokenKind::OpMul,
+        let op = match tokens.peek()?.kind {
+            op if is_binary_op(op) => op,
             _ => panic!("binary::parse() called on non-binary Token"),
         };
         tokens.eat(op).unwrap();
@@ -50,13 +64,12 @@ impl Syntax {
         // going to be binary::Syntax.
         //
         let mut rhs = Box::new(syntax::expression::Syntax::parse(tokens)?);
-        use parse::syntax::Syntax;
         if Self::is_binary(&*rhs.value) {
             let mut lh_op = op;
             let lh_op_precedes;
 
             {
-                let mut rhs_binary = Self::binary_mut(&mut *rhs.value).unwrap();
+                let rhs_binary = Self::binary_mut(&mut *rhs.value).unwrap();
 
                 lh_op_precedes = Self::precedes(lh_op, rhs_binary.op);
                 if lh_op_precedes {
@@ -129,24 +142,11 @@ impl Syntax {
     // These wrap std::any, for simplification.
     // They can't be put in the trait (it'd have to be generic).
 
-    fn binary(syntax: &syntax::Syntax) -> Option<&Self> {
-        use parse::syntax::Syntax;
-        use std::any::Any;
-
-        syntax.any()?.downcast_ref::<Self>()
-    }
-
-    fn binary_mut(syntax: &mut syntax::Syntax) -> Option<&mut Self> {
-        use parse::syntax::Syntax;
-        use std::any::Any;
-
+    fn binary_mut(syntax: &mut dyn syntax::Syntax) -> Option<&mut Self> {
         syntax.any_mut()?.downcast_mut::<Self>()
     }
 
-    fn is_binary(syntax: &syntax::Syntax) -> bool {
-        use parse::syntax::Syntax;
-        use std::any::Any;
-
+    fn is_binary(syntax: &dyn syntax::Syntax) -> bool {
         match syntax.any() {
             Some(any) => any.downcast_ref::<Self>().is_some(),
             None => false,
@@ -164,6 +164,6 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
@@ -3,7 +3,7 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    statements: Vec<Box<syntax::Syntax>>,
+    pub statements: Vec<Box<dyn syntax::Syntax>>,
 }
 
 impl Syntax {
@@ -14,12 +14,9 @@ impl Syntax {
 
         // Collect statements until a }.
         let mut statements = Vec::new();
-        while match tokens.peek()? {
-            Token { kind: TokenKind::BraceClose, .. } => false,
-            _ => true,
-        } {
+        while !matches!(tokens.peek()?, Token { kind: TokenKind::BraceClose, .. }) {
             statements.push(Box::new(
-                syntax::statement::Syntax::parse(tokens)?) as Box<syntax::Syntax>
+                syntax::statement::Syntax::parse(tokens)?) as Box<dyn syntax::Syntax>
             );
         }
         tokens.eat(TokenKind::BraceClose)?;
@@ -31,6 +28,6 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
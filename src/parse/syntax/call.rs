@@ -3,8 +3,8 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    name: syntax::name::Syntax,
-    arguments: Vec<Box<syntax::Syntax>>,
+    pub name: syntax::name::Syntax,
+    pub arguments: Vec<Box<dyn syntax::Syntax>>,
 }
 
 impl Syntax {
@@ -14,13 +14,18 @@ impl Syntax {
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
         tokens.eat(TokenKind::ParenOpen)?;
 
+        // The parentheses delimit the arguments, so they may be struct
+        // literals even in an `if`'s condition.
+        let structs = tokens.structs;
+        tokens.structs = true;
+
         let mut arguments = Vec::new();
 
         if tokens.peek()?.kind != TokenKind::ParenClose {
             loop {
                 arguments.push(Box::new(
-                    syntax::name::Syntax::parse(tokens)?
-                ) as Box<syntax::Syntax>);
+                    syntax::expression::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>);
 
                 // Arguments must be delimited by commas
                 match tokens.peek()?.kind {
@@ -38,6 +43,7 @@ impl Syntax {
             }
         }
         tokens.eat(TokenKind::ParenClose)?;
+        tokens.structs = structs;
 
         Some(Syntax {
             name,
@@ -47,6 +53,6 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
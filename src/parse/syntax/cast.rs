@@ -0,0 +1,31 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// An explicit conversion, `value as ty`.
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub value: Box<dyn syntax::Syntax>,
+    pub ty: syntax::ty::Syntax,
+}
+
+impl Syntax {
+    /// Must be passed the `value`, already parsed, but started on the `as`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        value: Box<dyn syntax::Syntax>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyAs)?;
+        let ty = syntax::ty::Syntax::parse(tokens)?;
+
+        Some(Syntax {
+            value,
+            ty,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -0,0 +1,59 @@
+//! `Syntax` for an `if`-clause, with optional `else` or `else if`.
+//!
+//! The condition is an integer; any value but 0 counts as true.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub condition: syntax::expression::Syntax,
+    pub then_block: syntax::block::Syntax,
+
+    /// Either a `block::Syntax`, or another `conditional::Syntax` for an
+    /// `else if`.
+    pub else_block: Option<Box<dyn syntax::Syntax>>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyIf)?;
+
+        let structs = tokens.structs;
+        tokens.structs = false;
+        let condition = syntax::expression::Syntax::parse(tokens)?;
+        tokens.structs = structs;
+        let then_block = syntax::block::Syntax::parse(tokens)?;
+
+        // Note the `if` might be the last thing in the file, so peek() may
+        // legitimately fail.
+        let else_block = match tokens.peek() {
+            Some(Token { kind: TokenKind::KeyElse, .. }) => {
+                tokens.eat(TokenKind::KeyElse)?;
+
+                match tokens.peek()?.kind {
+                    TokenKind::KeyIf => Some(Box::new(
+                        Self::parse(tokens)?
+                    ) as Box<dyn syntax::Syntax>),
+                    _ => Some(Box::new(
+                        syntax::block::Syntax::parse(tokens)?
+                    ) as Box<dyn syntax::Syntax>),
+                }
+            },
+            _ => None,
+        };
+
+        Some(Syntax {
+            condition,
+            then_block,
+            else_block,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
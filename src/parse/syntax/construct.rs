@@ -0,0 +1,64 @@
+//! `Syntax` for a struct literal, `Name { field: value, ... }`.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub name: syntax::name::Syntax,
+    /// Each field's name and value, in the order written.
+    pub fields: Vec<(syntax::name::Syntax, Box<dyn syntax::Syntax>)>,
+}
+
+impl Syntax {
+    /// Must be passed the `name`, already parsed, but started on the `{`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        name: syntax::name::Syntax,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::BraceOpen)?;
+
+        // The braces delimit the values, so they may be struct literals even
+        // in an `if`'s condition.
+        let structs = tokens.structs;
+        tokens.structs = true;
+
+        let mut fields = Vec::new();
+
+        if tokens.peek()?.kind != TokenKind::BraceClose {
+            loop {
+                let field = syntax::name::Syntax::parse(tokens)?;
+                tokens.eat(TokenKind::GramColon)?;
+                let value = Box::new(
+                    syntax::expression::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>;
+                fields.push((field, value));
+
+                // Fields must be delimited by commas
+                match tokens.peek()?.kind {
+                    TokenKind::GramComma => {
+                        tokens.eat(TokenKind::GramComma)?;
+
+                        if tokens.peek()?.kind == TokenKind::BraceClose {
+                            break;
+                        }
+                    },
+                    TokenKind::BraceClose => break,
+                    _ => return None,
+                }
+            }
+        }
+        tokens.eat(TokenKind::BraceClose)?;
+        tokens.structs = structs;
+
+        Some(Syntax {
+            name,
+            fields,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -0,0 +1,29 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// What a reference refers to, `*value`.
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub value: Box<dyn syntax::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::OpMul)?;
+
+        // As for `&`, `*a.b` is `*(a.b)`.
+        let value = syntax::expression::operand(tokens)?;
+
+        Some(Syntax {
+            value,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
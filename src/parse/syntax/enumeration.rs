@@ -0,0 +1,83 @@
+//! `Syntax` for an enum declaration, `enum Name { Variant(Type, ...), ... }`,
+//! where a variant without data has no parentheses. (`enum` itself is a
+//! key-word, hence the module name.)
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub name: syntax::name::Syntax,
+    pub variants: Vec<Variant>,
+}
+
+/// A variant's name and the types of its fields, which are numbered rather
+/// than named.
+///
+#[derive (Debug)]
+pub struct Variant {
+    pub name: syntax::name::Syntax,
+    pub fields: Vec<syntax::ty::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyEnum)?;
+        let name = syntax::name::Syntax::parse(tokens)?;
+        tokens.eat(TokenKind::BraceOpen)?;
+
+        let mut variants = Vec::new();
+
+        if tokens.peek()?.kind != TokenKind::BraceClose {
+            loop {
+                let name = syntax::name::Syntax::parse(tokens)?;
+                let mut fields = Vec::new();
+                if tokens.peek()?.kind == TokenKind::ParenOpen {
+                    tokens.eat(TokenKind::ParenOpen)?;
+                    while tokens.peek()?.kind != TokenKind::ParenClose {
+                        fields.push(syntax::ty::Syntax::parse(tokens)?);
+
+                        match tokens.peek()?.kind {
+                            TokenKind::GramComma => {
+                                tokens.eat(TokenKind::GramComma)?;
+                            },
+                            TokenKind::ParenClose => (),
+                            _ => return None,
+                        }
+                    }
+                    tokens.eat(TokenKind::ParenClose)?;
+                }
+                variants.push(Variant {
+                    name,
+                    fields,
+                });
+
+                // Variants must be delimited by commas
+                match tokens.peek()?.kind {
+                    TokenKind::GramComma => {
+                        tokens.eat(TokenKind::GramComma)?;
+
+                        if tokens.peek()?.kind == TokenKind::BraceClose {
+                            break;
+                        }
+                    },
+                    TokenKind::BraceClose => break,
+                    _ => return None,
+                }
+            }
+        }
+        tokens.eat(TokenKind::BraceClose)?;
+
+        Some(Syntax {
+            name,
+            variants,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -6,42 +6,23 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    pub value: Box<syntax::Syntax>,
+    pub value: Box<dyn syntax::Syntax>,
 }
 
 impl Syntax {
     pub fn parse<'a, 'b: 'a, I>(
         tokens: &mut TokenIter<'a, 'b, I>,
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
-        // First handle a primitive (literal, variable, call...). Then handle
-        // possible binary ops.
-
-        // Eat a primitive
-        let primitive = match tokens.peek()?.kind {
-            TokenKind::LitInteger => {
-                Box::new(
-                    syntax::literal::Syntax::parse(tokens)?
-                ) as Box<syntax::Syntax>
-            },
-
-            TokenKind::OthName => {
-                // Handle possible call
-                let name = syntax::name::Syntax::parse(tokens)?;
-
-                match tokens.peek()?.kind {
-                    TokenKind::ParenOpen => {
-                        Box::new(
-                            syntax::call::Syntax::parse(tokens, name)?
-                        ) as Box<syntax::Syntax>
-                    },
-                    _ => Box::new(name) as Box<syntax::Syntax>,
-                }
-            },
-
-            // @TODO Blocks and other flow constructs?
-
-            _ => return None,
-        };
+        // First handle a primitive (literal, variable, call...), with any
+        // field accesses and indexing, which bind tighter than anything else.
+        // Then casts bind tighter than any binary op, so take them now. Then
+        // handle possible binary ops.
+        let mut primitive = operand(tokens)?;
+        while tokens.peek()?.kind == TokenKind::KeyAs {
+            primitive = Box::new(
+                syntax::cast::Syntax::parse(tokens, primitive)?
+            ) as Box<dyn syntax::Syntax>;
+        }
 
         // Handle a possible binary op.
         //
@@ -51,9 +32,7 @@ impl Syntax {
         // @TODO where is validity of operands to be checked?
         //
         match tokens.peek()?.kind {
-            TokenKind::OpAdd |
-            TokenKind::OpSub |
-            TokenKind::OpMul => {
+            op if syntax::binary::is_binary_op(op) => {
                 // @OPTION None-ing out is not helpful
                 let binary = syntax::binary::Syntax::parse(
                     tokens, primitive,
@@ -68,7 +47,105 @@ impl Syntax {
     }
 }
 
+/// Parse a primitive (literal, variable, call...), or a prefix operator and
+/// its operand, with any field accesses and indexing after it.
+///
+pub fn operand<'a, 'b: 'a, I>(
+    tokens: &mut TokenIter<'a, 'b, I>,
+) -> Option<Box<dyn syntax::Syntax>> where I: Iterator<Item=&'a Token<'b>> {
+    // Eat a primitive
+    let primitive = match tokens.peek()?.kind {
+        TokenKind::LitInteger |
+        TokenKind::LitFloat |
+        TokenKind::LitString |
+        TokenKind::LitChar => {
+            Box::new(
+                syntax::literal::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+
+        TokenKind::OthName => {
+            // Handle possible call, struct literal or enum variant
+            let name = syntax::name::Syntax::parse(tokens)?;
+
+            match tokens.peek()?.kind {
+                TokenKind::ParenOpen => {
+                    Box::new(
+                        syntax::call::Syntax::parse(tokens, name)?
+                    ) as Box<dyn syntax::Syntax>
+                },
+                TokenKind::GramPath => {
+                    Box::new(
+                        syntax::variant::Syntax::parse(tokens, name)?
+                    ) as Box<dyn syntax::Syntax>
+                },
+                TokenKind::BraceOpen if tokens.structs => {
+                    Box::new(
+                        syntax::construct::Syntax::parse(tokens, name)?
+                    ) as Box<dyn syntax::Syntax>
+                },
+                _ => Box::new(name) as Box<dyn syntax::Syntax>,
+            }
+        },
+
+        TokenKind::KeyMatch => {
+            Box::new(
+                syntax::matching::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+
+        TokenKind::BracketOpen => {
+            Box::new(
+                syntax::array::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+        TokenKind::ParenOpen => {
+            Box::new(
+                syntax::tuple::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+
+        TokenKind::OpAmp => {
+            Box::new(
+                syntax::reference::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+        TokenKind::OpMul => {
+            Box::new(
+                syntax::dereference::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>
+        },
+
+        // @TODO Blocks and other flow constructs?
+
+        _ => return None,
+    };
+
+    postfix(tokens, primitive)
+}
+
+/// Parse any field (or tuple element) accesses and indexing after `value`,
+/// already parsed.
+///
+pub fn postfix<'a, 'b: 'a, I>(
+    tokens: &mut TokenIter<'a, 'b, I>,
+    value: Box<dyn syntax::Syntax>,
+) -> Option<Box<dyn syntax::Syntax>> where I: Iterator<Item=&'a Token<'b>> {
+    let mut value = value;
+    loop {
+        value = match tokens.peek()?.kind {
+            TokenKind::GramDot => Box::new(
+                syntax::field::Syntax::parse(tokens, value)?
+            ) as Box<dyn syntax::Syntax>,
+            TokenKind::BracketOpen => Box::new(
+                syntax::index::Syntax::parse(tokens, value)?
+            ) as Box<dyn syntax::Syntax>,
+            _ => return Some(value),
+        };
+    }
+}
+
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
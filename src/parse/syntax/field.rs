@@ -0,0 +1,58 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// Access to a field of a struct, `value.name`, or to an element of a tuple,
+/// `value.0`, whose name is its index.
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub value: Box<dyn syntax::Syntax>,
+    pub name: syntax::name::Syntax,
+}
+
+impl Syntax {
+    /// Must be passed the `value`, already parsed, but started on the `.`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        value: Box<dyn syntax::Syntax>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::GramDot)?;
+        let name = match tokens.peek()?.kind {
+            TokenKind::LitInteger => index(tokens.eat(TokenKind::LitInteger)?.source)?,
+            // `a.0.1` is lexed as `a`, `.` and the float `0.1`.
+            TokenKind::LitFloat => {
+                let float = tokens.eat(TokenKind::LitFloat)?.source;
+                let (first, second) = float.split_once('.')?;
+                let value = Box::new(Syntax {
+                    value,
+                    name: index(first)?,
+                }) as Box<dyn syntax::Syntax>;
+
+                return Some(Syntax {
+                    value,
+                    name: index(second)?,
+                });
+            },
+            _ => syntax::name::Syntax::parse(tokens)?,
+        };
+
+        Some(Syntax {
+            value,
+            name,
+        })
+    }
+}
+
+/// The name of a tuple's element, if `source` is its index: just digits.
+///
+fn index(source: &str) -> Option<syntax::name::Syntax> {
+    match !source.is_empty() && source.bytes().all(|byte| byte.is_ascii_digit()) {
+        true  => Some(syntax::name::Syntax { string: source.to_string() }),
+        false => None,
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -1,20 +1,24 @@
-use lex::{ Token, TokenKind };
+use lex::Token;
 use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    signature: syntax::signature::Syntax,
-    block: syntax::block::Syntax,
+    /// The lines of the doc comment before the function, if any.
+    pub doc: Vec<String>,
+    pub signature: syntax::signature::Syntax,
+    pub block: syntax::block::Syntax,
 }
 
 impl Syntax {
     pub fn parse<'a, 'b: 'a, I>(
         tokens: &mut TokenIter<'a, 'b, I>,
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        let doc = tokens.peek()?.doc.iter().map(|line| line.to_string()).collect();
         let signature = syntax::signature::Syntax::parse(tokens)?;
         let block = syntax::block::Syntax::parse(tokens)?;
 
         Some(Syntax {
+            doc,
             signature,
             block,
         })
@@ -22,6 +26,31 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
-}
\ No newline at end of file
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
+#[cfg(test)]
+mod tests {
+    use lex::Lexer;
+    use parse::Parser;
+
+    #[test]
+    fn doc_comments_are_attached_to_functions() {
+        let source = "\
+            ## Add one.\n\
+            ##\n\
+            ## Or wrap.\n\
+            inc(x: i32) -> i32 { return x + 1; }\n\
+            # Not a doc comment.\n\
+            main() -> i32 {\n\
+            \x20   ## Nor is this the function's.\n\
+            \x20   return inc(1);\n\
+            }\n";
+        let tokens = Lexer::new().lex(source).unwrap();
+        let tree = Parser::new().parse(tokens).unwrap();
+        let docs: Vec<&[String]> = tree.functions.iter()
+            .map(|function| function.doc.as_slice())
+            .collect();
+        assert_eq!(docs, [&["Add one.", "", "Or wrap."][..], &[]]);
+    }
+}
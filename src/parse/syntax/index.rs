@@ -0,0 +1,39 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// Access to an element of an array, `value[index]`.
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub value: Box<dyn syntax::Syntax>,
+    pub index: Box<dyn syntax::Syntax>,
+}
+
+impl Syntax {
+    /// Must be passed the `value`, already parsed, but started on the `[`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        value: Box<dyn syntax::Syntax>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::BracketOpen)?;
+
+        // As for a call's arguments, the brackets delimit the index.
+        let structs = tokens.structs;
+        tokens.structs = true;
+        let index = Box::new(
+            syntax::expression::Syntax::parse(tokens)?
+        ) as Box<dyn syntax::Syntax>;
+        tokens.eat(TokenKind::BracketClose)?;
+        tokens.structs = structs;
+
+        Some(Syntax {
+            value,
+            index,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
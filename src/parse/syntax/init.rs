@@ -3,19 +3,40 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    lhs: syntax::name::Syntax,
-    lhs_type: Option<syntax::name::Syntax>,
-    rhs: Box<syntax::Syntax>,
+    pub lhs: Lhs,
+    pub lhs_type: Option<syntax::ty::Syntax>,
+    pub rhs: Box<dyn syntax::Syntax>,
+}
+
+/// What's assigned: a name, or names for each element of a tuple, as in
+/// `(q, r) := divmod(a, b);`, or `None` for those left out with `_`.
+///
+#[derive (Debug)]
+pub enum Lhs {
+    Name(syntax::name::Syntax),
+    Tuple(Vec<Option<syntax::name::Syntax>>),
+}
+
+impl Lhs {
+    /// Every name assigned.
+    ///
+    pub fn names(&self) -> Vec<&syntax::name::Syntax> {
+        match *self {
+            Lhs::Name(ref name) => vec![name],
+            Lhs::Tuple(ref names) => names.iter().flatten().collect(),
+        }
+    }
 }
 
 impl Syntax {
+    /// Must be passed the `lhs`, already parsed, but started after it.
     pub fn parse<'a, 'b: 'a, I>(
         tokens: &mut TokenIter<'a, 'b, I>,
+        lhs: Lhs,
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
-        let lhs = syntax::name::Syntax::parse(tokens)?;
-
         // a := b
         // a: int = b
+        // (a, b) := c
 
         tokens.eat(TokenKind::GramColon);
         // @TODO there should be a space between : and =, only if the name after
@@ -29,8 +50,11 @@ impl Syntax {
             Token { kind: TokenKind::OpAssign, .. } => {
                 None
             },
-            Token { kind: TokenKind::OthName, .. } => {
-                Some(syntax::name::Syntax::parse(tokens)?)
+            Token { kind: TokenKind::OthName, .. } |
+            Token { kind: TokenKind::BracketOpen, .. } |
+            Token { kind: TokenKind::ParenOpen, .. } |
+            Token { kind: TokenKind::OpAmp, .. } => {
+                Some(syntax::ty::Syntax::parse(tokens)?)
             },
             _ => return None,
         };
@@ -38,7 +62,7 @@ impl Syntax {
 
         let rhs = Box::new(
             syntax::expression::Syntax::parse(tokens)?,
-        ) as Box<syntax::Syntax>;
+        ) as Box<dyn syntax::Syntax>;
 
         tokens.eat(TokenKind::GramSemicolon);
 
@@ -48,9 +72,45 @@ impl Syntax {
             rhs,
         })
     }
+
+    /// Parse an init of a tuple's elements, started on the `(`.
+    pub fn parse_tuple<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::ParenOpen)?;
+
+        let mut names = Vec::new();
+        loop {
+            names.push(match tokens.peek()?.kind {
+                TokenKind::GramUnderscore => {
+                    tokens.eat(TokenKind::GramUnderscore)?;
+                    None
+                },
+                _ => Some(syntax::name::Syntax::parse(tokens)?),
+            });
+
+            match tokens.peek()?.kind {
+                TokenKind::GramComma => {
+                    tokens.eat(TokenKind::GramComma)?;
+
+                    if tokens.peek()?.kind == TokenKind::ParenClose {
+                        break;
+                    }
+                },
+                TokenKind::ParenClose => break,
+                _ => return None,
+            }
+        }
+        tokens.eat(TokenKind::ParenClose)?;
+
+        if names.len() < 2 {
+            return None;
+        }
+        Syntax::parse(tokens, Lhs::Tuple(names))
+    }
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
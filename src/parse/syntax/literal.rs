@@ -1,25 +1,43 @@
-use lex::{ Token, TokenKind, get_integer };
+use lex::{ Token, TokenKind, Float, Integer, get_char, get_float, get_integer, unescape };
 use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
-pub struct Syntax {
-    // @TODO other kinds of literal (make an enum)
-    value: i32,
+pub enum Syntax {
+    Integer(Integer),
+    Float(Float),
+    /// The string's contents, with escapes replaced.
+    String(String),
+    Char(char),
 }
 
 impl Syntax {
     pub fn parse<'a, 'b: 'a, I>(
         tokens: &mut TokenIter<'a, 'b, I>,
     ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
-        let lit = tokens.eat(TokenKind::LitInteger)?;
-
-        Some(Syntax {
-            value: get_integer(lit.source)?,
-        })
+        match tokens.peek()?.kind {
+            // The lexer has already reported any bad literals.
+            TokenKind::LitInteger => {
+                let lit = tokens.eat(TokenKind::LitInteger)?;
+                Some(Syntax::Integer(get_integer(lit.source).ok()?))
+            },
+            TokenKind::LitFloat => {
+                let lit = tokens.eat(TokenKind::LitFloat)?;
+                Some(Syntax::Float(get_float(lit.source).ok()?))
+            },
+            TokenKind::LitString => {
+                let lit = tokens.eat(TokenKind::LitString)?;
+                Some(Syntax::String(unescape(lit.source).ok()?))
+            },
+            TokenKind::LitChar => {
+                let lit = tokens.eat(TokenKind::LitChar)?;
+                Some(Syntax::Char(get_char(lit.source).ok()?))
+            },
+            _ => None,
+        }
     }
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
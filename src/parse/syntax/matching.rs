@@ -0,0 +1,78 @@
+//! `Syntax` for a `match`, `match value { pattern => body, ... }`, which runs
+//! the body of the first arm whose pattern the value matches. (`match` itself
+//! is a key-word, hence the module name.)
+//!
+//! A body is an expression, or a block; a block needn't be followed by a
+//! comma. Only a `match` whose bodies are all expressions has a value.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub value: syntax::expression::Syntax,
+    pub arms: Vec<Arm>,
+}
+
+#[derive (Debug)]
+pub struct Arm {
+    pub pattern: syntax::pattern::Syntax,
+    /// Either an `expression::Syntax` or a `block::Syntax`.
+    pub body: Box<dyn syntax::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyMatch)?;
+
+        // As for an `if`'s condition, a `{` after the value starts the arms.
+        let structs = tokens.structs;
+        tokens.structs = false;
+        let value = syntax::expression::Syntax::parse(tokens)?;
+        tokens.structs = true;
+        tokens.eat(TokenKind::BraceOpen)?;
+
+        let mut arms = Vec::new();
+        while tokens.peek()?.kind != TokenKind::BraceClose {
+            let pattern = syntax::pattern::Syntax::parse(tokens)?;
+            tokens.eat(TokenKind::GramFatArrow)?;
+
+            let (body, is_block) = match tokens.peek()?.kind {
+                TokenKind::BraceOpen => (Box::new(
+                    syntax::block::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>, true),
+                _ => (Box::new(
+                    syntax::expression::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>, false),
+            };
+            arms.push(Arm {
+                pattern,
+                body,
+            });
+
+            // Arms must be delimited by commas, except after a block.
+            match tokens.peek()?.kind {
+                TokenKind::GramComma => {
+                    tokens.eat(TokenKind::GramComma)?;
+                },
+                TokenKind::BraceClose => (),
+                _ if is_block => (),
+                _ => return None,
+            }
+        }
+        tokens.eat(TokenKind::BraceClose)?;
+        tokens.structs = structs;
+
+        Some(Syntax {
+            value,
+            arms,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -5,7 +5,7 @@ use parse::{ syntax, TokenIter };
 pub struct Syntax {
     // @OPTION eventually Syntaxs will probably have &Tokens, at which point you
     // already have the name
-    string: String,
+    pub string: String,
 }
 
 impl Syntax {
@@ -22,6 +22,6 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
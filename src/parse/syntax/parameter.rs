@@ -0,0 +1,36 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// A function parameter, with its type if one is given (`i32` if not).
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub name: syntax::name::Syntax,
+    pub ty: Option<syntax::ty::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        let name = syntax::name::Syntax::parse(tokens)?;
+
+        let ty = match tokens.peek()?.kind {
+            TokenKind::GramColon => {
+                tokens.eat(TokenKind::GramColon)?;
+                Some(syntax::ty::Syntax::parse(tokens)?)
+            },
+            _ => None,
+        };
+
+        Some(Syntax {
+            name,
+            ty,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -0,0 +1,72 @@
+//! `Syntax` for a `match` arm's pattern: `_`, a literal, or an enum's variant,
+//! `Name::Variant(field, ...)`, which names its fields, or leaves them out
+//! with `_`.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub enum Syntax {
+    Wildcard,
+    Literal(syntax::literal::Syntax),
+    Variant {
+        /// The enum's name.
+        name: syntax::name::Syntax,
+        variant: syntax::name::Syntax,
+        /// The name to bind each field to, or `None` for `_`.
+        fields: Vec<Option<syntax::name::Syntax>>,
+    },
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        match tokens.peek()?.kind {
+            TokenKind::GramUnderscore => {
+                tokens.eat(TokenKind::GramUnderscore)?;
+                Some(Syntax::Wildcard)
+            },
+            TokenKind::OthName => {
+                let name = syntax::name::Syntax::parse(tokens)?;
+                tokens.eat(TokenKind::GramPath)?;
+                let variant = syntax::name::Syntax::parse(tokens)?;
+
+                let mut fields = Vec::new();
+                if tokens.peek()?.kind == TokenKind::ParenOpen {
+                    tokens.eat(TokenKind::ParenOpen)?;
+                    while tokens.peek()?.kind != TokenKind::ParenClose {
+                        fields.push(match tokens.peek()?.kind {
+                            TokenKind::GramUnderscore => {
+                                tokens.eat(TokenKind::GramUnderscore)?;
+                                None
+                            },
+                            _ => Some(syntax::name::Syntax::parse(tokens)?),
+                        });
+
+                        match tokens.peek()?.kind {
+                            TokenKind::GramComma => {
+                                tokens.eat(TokenKind::GramComma)?;
+                            },
+                            TokenKind::ParenClose => (),
+                            _ => return None,
+                        }
+                    }
+                    tokens.eat(TokenKind::ParenClose)?;
+                }
+
+                Some(Syntax::Variant {
+                    name,
+                    variant,
+                    fields,
+                })
+            },
+            _ => Some(Syntax::Literal(syntax::literal::Syntax::parse(tokens)?)),
+        }
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
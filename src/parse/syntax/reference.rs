@@ -0,0 +1,36 @@
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+/// Taking a reference to a place, `&value` or `&mut value`.
+///
+#[derive (Debug)]
+pub struct Syntax {
+    pub mutable: bool,
+    pub value: Box<dyn syntax::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::OpAmp)?;
+        let mutable = tokens.peek()?.kind == TokenKind::KeyMut;
+        if mutable {
+            tokens.eat(TokenKind::KeyMut)?;
+        }
+
+        // This binds tighter than a cast or binary op, but not than a field
+        // access or indexing: `&a.b` is `&(a.b)`.
+        let value = syntax::expression::operand(tokens)?;
+
+        Some(Syntax {
+            mutable,
+            value,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
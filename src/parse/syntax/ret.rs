@@ -0,0 +1,35 @@
+//! `Syntax` for a `return` statement. (`return` itself is a key-word, hence
+//! the module name.)
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    /// `None` for a bare `return;`.
+    pub value: Option<syntax::expression::Syntax>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyReturn)?;
+
+        let value = match tokens.peek()?.kind {
+            TokenKind::GramSemicolon => None,
+            _ => Some(syntax::expression::Syntax::parse(tokens)?),
+        };
+
+        tokens.eat(TokenKind::GramSemicolon)?;
+
+        Some(Syntax {
+            value,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
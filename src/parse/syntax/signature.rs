@@ -3,8 +3,10 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    name: syntax::name::Syntax,
-    parameters: Vec<Box<syntax::Syntax>>,
+    pub name: syntax::name::Syntax,
+    pub parameters: Vec<Box<dyn syntax::Syntax>>,
+    /// The type after `->`, if any (`i32` if not).
+    pub return_type: Option<syntax::ty::Syntax>,
 }
 
 impl Syntax {
@@ -18,12 +20,9 @@ impl Syntax {
 
         if tokens.peek()?.kind != TokenKind::ParenClose {
             loop {
-                // @TODO type specification (or change from C-style signature to
-                // Haskell style)
-
                 parameters.push(Box::new(
-                    syntax::name::Syntax::parse(tokens)?
-                ) as Box<syntax::Syntax>);
+                    syntax::parameter::Syntax::parse(tokens)?
+                ) as Box<dyn syntax::Syntax>);
 
                 // Arguments must be delimited by commas
                 match tokens.peek()?.kind {
@@ -43,14 +42,23 @@ impl Syntax {
 
         tokens.eat(TokenKind::ParenClose)?;
 
+        let return_type = match tokens.peek()?.kind {
+            TokenKind::GramArrow => {
+                tokens.eat(TokenKind::GramArrow)?;
+                Some(syntax::ty::Syntax::parse(tokens)?)
+            },
+            _ => None,
+        };
+
         Some(Syntax {
             name,
             parameters,
+            return_type,
         })
     }
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
@@ -11,7 +11,7 @@ use parse::{ syntax, TokenIter };
 
 #[derive (Debug)]
 pub struct Syntax {
-    block: Box<syntax::Syntax>,
+    pub block: Box<dyn syntax::Syntax>,
 }
 
 impl Syntax {
@@ -22,18 +22,72 @@ impl Syntax {
             Token { kind: TokenKind::BraceOpen, .. } => {
                 Box::new(
                     syntax::block::Syntax::parse(tokens)?,
-                ) as Box<syntax::Syntax>
+                ) as Box<dyn syntax::Syntax>
             },
 
             // @TODO it should eventually be valid to leave a name at the end of
-            // a block as its return (here I am only allowing inits)
+            // a block as its return (here I am only allowing inits and calls)
             Token { kind: TokenKind::OthName, .. } => {
+                let name = syntax::name::Syntax::parse(tokens)?;
+
+                match tokens.peek()?.kind {
+                    TokenKind::ParenOpen => {
+                        let call = syntax::call::Syntax::parse(tokens, name)?;
+                        tokens.eat(TokenKind::GramSemicolon);
+
+                        Box::new(call) as Box<dyn syntax::Syntax>
+                    },
+                    TokenKind::GramDot | TokenKind::BracketOpen => {
+                        let place = syntax::expression::postfix(
+                            tokens, Box::new(name) as Box<dyn syntax::Syntax>,
+                        )?;
+
+                        Box::new(
+                            syntax::assign::Syntax::parse(tokens, place)?,
+                        ) as Box<dyn syntax::Syntax>
+                    },
+                    _ => Box::new(
+                        syntax::init::Syntax::parse(tokens, syntax::init::Lhs::Name(name))?,
+                    ) as Box<dyn syntax::Syntax>,
+                }
+            },
+
+            // Destructuring a tuple, `(a, b) := c;`.
+            Token { kind: TokenKind::ParenOpen, .. } => {
+                Box::new(
+                    syntax::init::Syntax::parse_tuple(tokens)?,
+                ) as Box<dyn syntax::Syntax>
+            },
+
+            // An assignment through a reference, `*a = b;`.
+            Token { kind: TokenKind::OpMul, .. } => {
+                let place = syntax::dereference::Syntax::parse(tokens)?;
+
                 Box::new(
-                    syntax::init::Syntax::parse(tokens)?,
-                ) as Box<syntax::Syntax>
+                    syntax::assign::Syntax::parse(tokens, Box::new(place))?,
+                ) as Box<dyn syntax::Syntax>
+            },
+
+            Token { kind: TokenKind::KeyReturn, .. } => {
+                Box::new(
+                    syntax::ret::Syntax::parse(tokens)?,
+                ) as Box<dyn syntax::Syntax>
+            },
+
+            Token { kind: TokenKind::KeyIf, .. } => {
+                Box::new(
+                    syntax::conditional::Syntax::parse(tokens)?,
+                ) as Box<dyn syntax::Syntax>
+            },
+
+            Token { kind: TokenKind::KeyMatch, .. } => {
+                let matching = syntax::matching::Syntax::parse(tokens)?;
+                tokens.eat(TokenKind::GramSemicolon);
+
+                Box::new(matching) as Box<dyn syntax::Syntax>
             },
 
-            // @TODO other cases: if-clauses, etc.
+            // @TODO other cases
             // @OPTION in cases where it's ambiguous, may want to do the
             // switching on parse() returns here
 
@@ -45,6 +99,6 @@ impl Syntax {
 }
 
 impl syntax::Syntax for Syntax {
-    fn any(&self) -> Option<&std::any::Any> { Some(self) }
-    fn any_mut(&mut self) -> Option<&mut std::any::Any> { Some(self) }
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
 }
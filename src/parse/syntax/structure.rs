@@ -0,0 +1,67 @@
+//! `Syntax` for a struct declaration, `struct Name { field: Type, ... }`.
+//! (`struct` itself is a key-word, hence the module name.)
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub name: syntax::name::Syntax,
+    pub fields: Vec<Field>,
+}
+
+/// A field's name and its type.
+///
+#[derive (Debug)]
+pub struct Field {
+    pub name: syntax::name::Syntax,
+    pub ty: syntax::ty::Syntax,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::KeyStruct)?;
+        let name = syntax::name::Syntax::parse(tokens)?;
+        tokens.eat(TokenKind::BraceOpen)?;
+
+        let mut fields = Vec::new();
+
+        if tokens.peek()?.kind != TokenKind::BraceClose {
+            loop {
+                let name = syntax::name::Syntax::parse(tokens)?;
+                tokens.eat(TokenKind::GramColon)?;
+                let ty = syntax::ty::Syntax::parse(tokens)?;
+                fields.push(Field {
+                    name,
+                    ty,
+                });
+
+                // Fields must be delimited by commas
+                match tokens.peek()?.kind {
+                    TokenKind::GramComma => {
+                        tokens.eat(TokenKind::GramComma)?;
+
+                        if tokens.peek()?.kind == TokenKind::BraceClose {
+                            break;
+                        }
+                    },
+                    TokenKind::BraceClose => break,
+                    _ => return None,
+                }
+            }
+        }
+        tokens.eat(TokenKind::BraceClose)?;
+
+        Some(Syntax {
+            name,
+            fields,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
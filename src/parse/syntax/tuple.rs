@@ -0,0 +1,58 @@
+//! `Syntax` for a tuple literal, `(element, ...)`, of at least two elements,
+//! since `(a)` isn't a tuple.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    pub elements: Vec<Box<dyn syntax::Syntax>>,
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::ParenOpen)?;
+
+        // As for an array's elements, the parentheses delimit the elements.
+        let structs = tokens.structs;
+        tokens.structs = true;
+
+        let mut elements = Vec::new();
+
+        loop {
+            elements.push(Box::new(
+                syntax::expression::Syntax::parse(tokens)?
+            ) as Box<dyn syntax::Syntax>);
+
+            // Elements must be delimited by commas
+            match tokens.peek()?.kind {
+                TokenKind::GramComma => {
+                    tokens.eat(TokenKind::GramComma)?;
+
+                    if tokens.peek()?.kind == TokenKind::ParenClose {
+                        break;
+                    }
+                },
+                TokenKind::ParenClose => break,
+                _ => return None,
+            }
+        }
+        tokens.eat(TokenKind::ParenClose)?;
+        tokens.structs = structs;
+
+        if elements.len() < 2 {
+            return None;
+        }
+
+        Some(Syntax {
+            elements,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
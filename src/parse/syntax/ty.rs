@@ -0,0 +1,100 @@
+//! `Syntax` for a type: a name; an array type, `[T; N]`, of `N` elements of
+//! type `T`; a tuple type, `(T, U, ...)`, of at least two; a reference, `&T`
+//! or `&mut T`; or a slice, `[T]`, of any number of `T`s, which is only a type
+//! behind a reference. (`type` is a key-word in Rust, hence the module name.)
+
+use std::fmt;
+
+use lex::{ Token, TokenKind, get_integer };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub enum Syntax {
+    Named(syntax::name::Syntax),
+    Array(Box<Syntax>, u64),
+    Slice(Box<Syntax>),
+    Tuple(Vec<Syntax>),
+    /// Whether the reference is `&mut`, then what it refers to.
+    Reference(bool, Box<Syntax>),
+}
+
+impl Syntax {
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        match tokens.peek()?.kind {
+            TokenKind::BracketOpen => {
+                tokens.eat(TokenKind::BracketOpen)?;
+                let element = Syntax::parse(tokens)?;
+                if tokens.peek()?.kind == TokenKind::BracketClose {
+                    tokens.eat(TokenKind::BracketClose)?;
+                    return Some(Syntax::Slice(Box::new(element)));
+                }
+                tokens.eat(TokenKind::GramSemicolon)?;
+                // The lexer has already reported any bad literals.
+                let length = tokens.eat(TokenKind::LitInteger)?;
+                let length = get_integer(length.source).ok()?.value;
+                tokens.eat(TokenKind::BracketClose)?;
+
+                Some(Syntax::Array(Box::new(element), length))
+            },
+            TokenKind::ParenOpen => {
+                tokens.eat(TokenKind::ParenOpen)?;
+                let mut elements = Vec::new();
+                loop {
+                    elements.push(Syntax::parse(tokens)?);
+                    match tokens.peek()?.kind {
+                        TokenKind::GramComma => {
+                            tokens.eat(TokenKind::GramComma)?;
+
+                            if tokens.peek()?.kind == TokenKind::ParenClose {
+                                break;
+                            }
+                        },
+                        TokenKind::ParenClose => break,
+                        _ => return None,
+                    }
+                }
+                tokens.eat(TokenKind::ParenClose)?;
+
+                if elements.len() < 2 {
+                    return None;
+                }
+                Some(Syntax::Tuple(elements))
+            },
+            TokenKind::OpAmp => {
+                tokens.eat(TokenKind::OpAmp)?;
+                let mutable = tokens.peek()?.kind == TokenKind::KeyMut;
+                if mutable {
+                    tokens.eat(TokenKind::KeyMut)?;
+                }
+
+                Some(Syntax::Reference(mutable, Box::new(Syntax::parse(tokens)?)))
+            },
+            _ => Some(Syntax::Named(syntax::name::Syntax::parse(tokens)?)),
+        }
+    }
+}
+
+/// As it would be written.
+///
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Syntax::Named(ref name) => write!(f, "{}", name.string),
+            Syntax::Array(ref element, length) => write!(f, "[{}; {}]", element, length),
+            Syntax::Slice(ref element) => write!(f, "[{}]", element),
+            Syntax::Tuple(ref elements) => {
+                let elements: Vec<String> = elements.iter().map(Syntax::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            },
+            Syntax::Reference(false, ref ty) => write!(f, "&{}", ty),
+            Syntax::Reference(true, ref ty) => write!(f, "&mut {}", ty),
+        }
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
@@ -0,0 +1,45 @@
+//! `Syntax` for making a value of an enum, `Name::Variant(value, ...)`, or
+//! just `Name::Variant` for a variant without data.
+
+use lex::{ Token, TokenKind };
+use parse::{ syntax, TokenIter };
+
+#[derive (Debug)]
+pub struct Syntax {
+    /// The enum's name.
+    pub name: syntax::name::Syntax,
+    pub variant: syntax::name::Syntax,
+    pub arguments: Vec<Box<dyn syntax::Syntax>>,
+}
+
+impl Syntax {
+    /// Must be passed the enum's `name`, already parsed, but started on the
+    /// `::`.
+    pub fn parse<'a, 'b: 'a, I>(
+        tokens: &mut TokenIter<'a, 'b, I>,
+        name: syntax::name::Syntax,
+    ) -> Option<Self> where I: Iterator<Item=&'a Token<'b>> {
+        tokens.eat(TokenKind::GramPath)?;
+        let variant = syntax::name::Syntax::parse(tokens)?;
+
+        // The values are written just like a call's arguments.
+        let (variant, arguments) = match tokens.peek()?.kind {
+            TokenKind::ParenOpen => {
+                let call = syntax::call::Syntax::parse(tokens, variant)?;
+                (call.name, call.arguments)
+            },
+            _ => (variant, Vec::new()),
+        };
+
+        Some(Syntax {
+            name,
+            variant,
+            arguments,
+        })
+    }
+}
+
+impl syntax::Syntax for Syntax {
+    fn any(&self) -> Option<&dyn std::any::Any> { Some(self) }
+    fn any_mut(&mut self) -> Option<&mut dyn std::any::Any> { Some(self) }
+}
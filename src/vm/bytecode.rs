@@ -0,0 +1,554 @@
+//! The bytecode format.
+//!
+//! A `Program` is a list of `Function`s, each with its own pools of constants,
+//! strings and jump tables, and code. Code is a byte string of instructions: a one-byte
+//! `Opcode`, followed by its operand, if any, in little-endian.
+//!
+//! | Opcode          | Operand | Stack effect                                 |
+//! |-----------------|---------|----------------------------------------------|
+//! | `Const`         | `u16`   | push `constants[operand]`                    |
+//! | `Str`           | `u16`   | push `operand`, for `strings[operand]`       |
+//! | `Load`          | `u16`   | push `locals[operand]`                       |
+//! | `Store`         | `u16`   | pop into `locals[operand]`                   |
+//! | `Pop`           |         | pop and discard                              |
+//! | `Add`           |         | pop `b`, pop `a`, push `a + b`               |
+//! | `Sub`           |         | pop `b`, pop `a`, push `a - b`               |
+//! | `Mul`           |         | pop `b`, pop `a`, push `a * b`               |
+//! | `CheckedAdd`    |         | as `Add` on integers, stopping with an error |
+//! |                 |         | on overflow                                  |
+//! | `CheckedSub`    |         | likewise as `Sub`                            |
+//! | `CheckedMul`    |         | likewise as `Mul`                            |
+//! | `SaturatingAdd` |         | as `Add` on integers, saturating on overflow |
+//! | `SaturatingSub` |         | likewise as `Sub`                            |
+//! | `SaturatingMul` |         | likewise as `Mul`                            |
+//! | `Eq`            |         | pop `b`, pop `a`, push 1 if `a == b`, else 0 |
+//! | `Ne`            |         | likewise for `a != b`                        |
+//! | `Lt`            |         | likewise for `a < b`                         |
+//! | `Le`            |         | likewise for `a <= b`                        |
+//! | `Gt`            |         | likewise for `a > b`                         |
+//! | `Ge`            |         | likewise for `a >= b`                        |
+//! | `ToI8`          |         | pop, push it converted to `i8`               |
+//! | `ToI16`         |         | likewise to `i16`                            |
+//! | `ToI32`         |         | likewise to `i32`                            |
+//! | `ToI64`         |         | likewise to `i64`                            |
+//! | `ToU8`          |         | likewise to `u8`                             |
+//! | `ToU16`         |         | likewise to `u16`                            |
+//! | `ToU32`         |         | likewise to `u32`                            |
+//! | `ToU64`         |         | likewise to `u64`                            |
+//! | `ToF32`         |         | likewise to `f32`                            |
+//! | `ToF64`         |         | likewise to `f64`                            |
+//! | `ToChar`        |         | pop an integer, push it converted to `char`, |
+//! |                 |         | or U+FFFD if it isn't a scalar value         |
+//! | `Call`          | `u16`   | call `functions[operand]`, whose arguments   |
+//! |                 |         | are on the stack, first pushed first; push   |
+//! |                 |         | its results, likewise                        |
+//! | `Return`        |         | pop the function's `results` and return them |
+//! | `Jump`          | `u32`   | continue at code offset `operand`            |
+//! | `JumpIfZero`    | `u32`   | pop; if it was 0, continue at `operand`      |
+//! | `PrintNumber`   |         | pop and print it                             |
+//! | `PrintStr`      |         | pop `s`, print `strings[s]`                  |
+//! | `Slot`          | `u32`   | push the address of the frame's memory, plus |
+//! |                 |         | `operand`                                    |
+//! | `ReadMemory`    | `u32`   | pop an address `p`, push the value at        |
+//! |                 |         | `p + operand`                                |
+//! | `WriteMemory`   | `u32`   | pop `v`, pop an address `p`, store `v` at    |
+//! |                 |         | `p + operand`                                |
+//! | `Offset`        | `u32`   | pop an address `p`, push `p + operand`       |
+//! | `CopyMemory`    | `u32`   | pop an address `q`, pop an address `p`, copy |
+//! |                 |         | `operand` bytes from `q` to `p`              |
+//! | `Switch`        | `u16`   | pop an integer or char `v`, continue at the  |
+//! |                 |         | offset `tables[operand]` gives for `v`       |
+//! | `Element`       | `u32`   | pop a `u64` `i`, pop an address `p`, push    |
+//! |                 |         | `p + i * operand`                            |
+//! | `Trap`          |         | stop with an error                           |
+//!
+//! Every value is a `Scalar`, tagged with its type; arithmetic and
+//! comparisons need two of the same type. A `str` is an `i32`, the index of
+//! the string in its function's pool.
+//!
+//! A function's parameters are its first locals.
+//!
+//! Each call also gets `memory` bytes of memory, which `Slot` finds, freed
+//! when it returns. Memory is addressed by the byte, but holds whole
+//! `Scalar`s: each is kept at the address it was stored to, and reading an
+//! address nothing was stored to is an error.
+
+use std::fmt::{ self, Write };
+
+use ir::Type;
+
+/// A value on the stack, or in a local or the constant pool.
+///
+#[derive (Debug, Copy, Clone, PartialEq)]
+pub enum Scalar {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    /// An address in the machine's memory.
+    Ptr(u32),
+}
+
+impl Scalar {
+    /// The `Scalar` of integer type `ty` holding `value`, which must fit; or
+    /// of `char`, if `value` is a Unicode scalar value.
+    ///
+    pub fn integer(ty: Type, value: i128) -> Self {
+        match ty {
+            Type::I8 => Scalar::I8(value as i8),
+            Type::I16 => Scalar::I16(value as i16),
+            Type::I32 => Scalar::I32(value as i32),
+            Type::I64 => Scalar::I64(value as i64),
+            Type::U8 => Scalar::U8(value as u8),
+            Type::U16 => Scalar::U16(value as u16),
+            Type::U32 => Scalar::U32(value as u32),
+            Type::U64 => Scalar::U64(value as u64),
+            Type::Char => Scalar::Char(
+                std::char::from_u32(value as u32).expect("not a Unicode scalar value")
+            ),
+            _ => panic!("{} is not an integer type", ty),
+        }
+    }
+
+    /// Whether `self` and `other` are the same value of the same type; unlike
+    /// `==`, this tells `0.0` from `-0.0`, and NaNs apart.
+    ///
+    pub fn identical(self, other: Scalar) -> bool {
+        match (self, other) {
+            (Scalar::F32(a), Scalar::F32(b)) => a.to_bits() == b.to_bits(),
+            (Scalar::F64(a), Scalar::F64(b)) => a.to_bits() == b.to_bits(),
+            (Scalar::F32(_), _) | (Scalar::F64(_), _) => false,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Scalar::I8(value) => write!(f, "{}i8", value),
+            Scalar::I16(value) => write!(f, "{}i16", value),
+            Scalar::I32(value) => write!(f, "{}", value),
+            Scalar::I64(value) => write!(f, "{}i64", value),
+            Scalar::U8(value) => write!(f, "{}u8", value),
+            Scalar::U16(value) => write!(f, "{}u16", value),
+            Scalar::U32(value) => write!(f, "{}u32", value),
+            Scalar::U64(value) => write!(f, "{}u64", value),
+            Scalar::F32(value) => write!(f, "{:?}f32", value),
+            Scalar::F64(value) => write!(f, "{:?}", value),
+            Scalar::Char(value) => write!(f, "{:?}", value),
+            Scalar::Ptr(value) => write!(f, "ptr {}", value),
+        }
+    }
+}
+
+#[derive (Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum Opcode {
+    Const,
+    Load,
+    Store,
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Call,
+    Return,
+    Jump,
+    JumpIfZero,
+    Str,
+    PrintNumber,
+    PrintStr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    ToI32,
+    ToF32,
+    ToF64,
+    ToI8,
+    ToI16,
+    ToI64,
+    ToU8,
+    ToU16,
+    ToU32,
+    ToU64,
+    CheckedAdd,
+    CheckedSub,
+    CheckedMul,
+    SaturatingAdd,
+    SaturatingSub,
+    SaturatingMul,
+    ToChar,
+    Slot,
+    ReadMemory,
+    WriteMemory,
+    Offset,
+    CopyMemory,
+    Switch,
+    Element,
+    Trap,
+}
+
+/// The width of an `Opcode`'s operand, in bytes.
+///
+#[derive (Debug, Copy, Clone, PartialEq)]
+pub enum Operand {
+    None,
+    U16,
+    U32,
+}
+
+impl Opcode {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        use self::Opcode::*;
+
+        Some(match byte {
+            0  => Const,
+            1  => Load,
+            2  => Store,
+            3  => Pop,
+            4  => Add,
+            5  => Sub,
+            6  => Mul,
+            7  => Call,
+            8  => Return,
+            9  => Jump,
+            10 => JumpIfZero,
+            11 => Str,
+            12 => PrintNumber,
+            13 => PrintStr,
+            14 => Eq,
+            15 => Ne,
+            16 => Lt,
+            17 => Le,
+            18 => Gt,
+            19 => Ge,
+            20 => ToI32,
+            21 => ToF32,
+            22 => ToF64,
+            23 => ToI8,
+            24 => ToI16,
+            25 => ToI64,
+            26 => ToU8,
+            27 => ToU16,
+            28 => ToU32,
+            29 => ToU64,
+            30 => CheckedAdd,
+            31 => CheckedSub,
+            32 => CheckedMul,
+            33 => SaturatingAdd,
+            34 => SaturatingSub,
+            35 => SaturatingMul,
+            36 => ToChar,
+            37 => Slot,
+            38 => ReadMemory,
+            39 => WriteMemory,
+            40 => Offset,
+            41 => CopyMemory,
+            42 => Switch,
+            43 => Element,
+            44 => Trap,
+            _  => return None,
+        })
+    }
+
+    pub fn operand(self) -> Operand {
+        use self::Opcode::*;
+
+        match self {
+            Const | Load | Store | Call | Str | Switch => Operand::U16,
+            Jump | JumpIfZero | Slot | ReadMemory | WriteMemory |
+            Offset | CopyMemory | Element => Operand::U32,
+            Pop | Add | Sub | Mul | Eq | Ne | Lt | Le | Gt | Ge |
+            CheckedAdd | CheckedSub | CheckedMul |
+            SaturatingAdd | SaturatingSub | SaturatingMul |
+            ToI8 | ToI16 | ToI32 | ToI64 | ToU8 | ToU16 | ToU32 | ToU64 |
+            ToF32 | ToF64 | ToChar | Return | PrintNumber | PrintStr | Trap => Operand::None,
+        }
+    }
+
+    fn mnemonic(self) -> &'static str {
+        use self::Opcode::*;
+
+        match self {
+            Const         => "const",
+            Load          => "load",
+            Store         => "store",
+            Pop           => "pop",
+            Add           => "add",
+            Sub           => "sub",
+            Mul           => "mul",
+            Call          => "call",
+            Return        => "return",
+            Jump          => "jump",
+            JumpIfZero    => "jump_if_zero",
+            Str           => "str",
+            PrintNumber   => "print_number",
+            PrintStr      => "print_str",
+            Eq            => "eq",
+            Ne            => "ne",
+            Lt            => "lt",
+            Le            => "le",
+            Gt            => "gt",
+            Ge            => "ge",
+            ToI32         => "to_i32",
+            ToF32         => "to_f32",
+            ToF64         => "to_f64",
+            ToI8          => "to_i8",
+            ToI16         => "to_i16",
+            ToI64         => "to_i64",
+            ToU8          => "to_u8",
+            ToU16         => "to_u16",
+            ToU32         => "to_u32",
+            ToU64         => "to_u64",
+            CheckedAdd    => "checked_add",
+            CheckedSub    => "checked_sub",
+            CheckedMul    => "checked_mul",
+            SaturatingAdd => "saturating_add",
+            SaturatingSub => "saturating_sub",
+            SaturatingMul => "saturating_mul",
+            ToChar        => "to_char",
+            Slot          => "slot",
+            ReadMemory    => "read_memory",
+            WriteMemory   => "write_memory",
+            Offset        => "offset",
+            CopyMemory    => "copy_memory",
+            Switch        => "switch",
+            Element       => "element",
+            Trap          => "trap",
+        }
+    }
+}
+
+#[derive (Debug)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn function(&self, name: &str) -> Option<usize> {
+        self.functions.iter().position(|function| function.name == name)
+    }
+}
+
+/// Where a `Switch` continues: at the offset of the case equal to the value,
+/// if any, or else at `default`.
+///
+#[derive (Debug)]
+pub struct Table {
+    /// Sorted by value, so they can be searched.
+    pub cases: Vec<(i128, u32)>,
+    pub default: u32,
+}
+
+impl Table {
+    pub fn target(&self, value: i128) -> u32 {
+        match self.cases.binary_search_by_key(&value, |&(case, _)| case) {
+            Ok(index) => self.cases[index].1,
+            Err(_) => self.default,
+        }
+    }
+}
+
+#[derive (Debug)]
+pub struct Function {
+    pub name: String,
+
+    /// The number of parameters.
+    pub arity: u16,
+    /// The number of values returned.
+    pub results: u16,
+    /// The number of locals, including parameters.
+    pub locals: u16,
+    /// The bytes of memory each call gets.
+    pub memory: u32,
+
+    pub constants: Vec<Scalar>,
+    pub strings: Vec<String>,
+    pub tables: Vec<Table>,
+    pub code: Vec<u8>,
+}
+
+impl Function {
+    pub fn new(name: String, arity: u16) -> Self {
+        Function {
+            name,
+            arity,
+            results: 1,
+            locals: arity,
+            memory: 0,
+            constants: Vec::new(),
+            strings: Vec::new(),
+            tables: Vec::new(),
+            code: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, opcode: Opcode) {
+        debug_assert_eq!(opcode.operand(), Operand::None);
+        self.code.push(opcode as u8);
+    }
+
+    pub fn push_u16(&mut self, opcode: Opcode, operand: u16) {
+        debug_assert_eq!(opcode.operand(), Operand::U16);
+        self.code.push(opcode as u8);
+        self.code.extend_from_slice(&operand.to_le_bytes());
+    }
+
+    /// Push an instruction with a `u32` operand; return the offset of the
+    /// operand, for patching.
+    ///
+    pub fn push_u32(&mut self, opcode: Opcode, operand: u32) -> usize {
+        debug_assert_eq!(opcode.operand(), Operand::U32);
+        self.code.push(opcode as u8);
+        self.code.extend_from_slice(&operand.to_le_bytes());
+        self.code.len() - 4
+    }
+
+    /// Overwrite the `u32` operand at `offset`, as returned by `push_u32()`.
+    ///
+    pub fn patch_u32(&mut self, offset: usize, operand: u32) {
+        self.code[offset..offset + 4].copy_from_slice(&operand.to_le_bytes());
+    }
+
+    /// Get the index of `value` in the constant pool, adding it if need be.
+    ///
+    pub fn constant(&mut self, value: Scalar) -> Result<u16, String> {
+        let index = match self.constants.iter().position(|c| c.identical(value)) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            },
+        };
+
+        if index > u16::MAX as usize {
+            return Err(format!("too many constants in `{}`", self.name));
+        }
+        Ok(index as u16)
+    }
+
+    /// Get the index of `string` in the string pool, adding it if need be.
+    ///
+    pub fn string(&mut self, string: &str) -> Result<u16, String> {
+        let index = match self.strings.iter().position(|s| s == string) {
+            Some(index) => index,
+            None => {
+                self.strings.push(string.to_string());
+                self.strings.len() - 1
+            },
+        };
+
+        if index > u16::MAX as usize {
+            return Err(format!("too many strings in `{}`", self.name));
+        }
+        Ok(index as u16)
+    }
+
+    /// Add `table` to the pool of jump tables; return its index.
+    ///
+    pub fn table(&mut self, table: Table) -> Result<u16, String> {
+        if self.tables.len() > u16::MAX as usize {
+            return Err(format!("too many jump tables in `{}`", self.name));
+        }
+        self.tables.push(table);
+        Ok((self.tables.len() - 1) as u16)
+    }
+
+    /// Decode the instruction at `offset`.
+    ///
+    /// Returns the opcode, its operand (0 if it has none), and the offset of
+    /// the next instruction.
+    ///
+    pub fn decode(&self, offset: usize) -> Option<(Opcode, u32, usize)> {
+        let opcode = Opcode::from_byte(*self.code.get(offset)?)?;
+        let start = offset + 1;
+
+        Some(match opcode.operand() {
+            Operand::None => (opcode, 0, start),
+            Operand::U16 => {
+                let bytes = self.code.get(start..start + 2)?;
+                (opcode, u16::from_le_bytes([bytes[0], bytes[1]]) as u32, start + 2)
+            },
+            Operand::U32 => {
+                let bytes = self.code.get(start..start + 4)?;
+                let operand = u32::from_le_bytes(
+                    [bytes[0], bytes[1], bytes[2], bytes[3]]
+                );
+                (opcode, operand, start + 4)
+            },
+        })
+    }
+}
+
+/// Produce a human-readable listing of `program`.
+///
+pub fn disassemble(program: &Program) -> String {
+    let mut out = String::new();
+
+    for (index, function) in program.functions.iter().enumerate() {
+        writeln!(
+            out, "function {} `{}` (arity {}, results {}, locals {}, memory {})",
+            index, function.name, function.arity, function.results, function.locals,
+            function.memory,
+        ).unwrap();
+
+        for (index, constant) in function.constants.iter().enumerate() {
+            writeln!(out, "  constant {}: {}", index, constant).unwrap();
+        }
+        for (index, string) in function.strings.iter().enumerate() {
+            writeln!(out, "  string {}: {:?}", index, string).unwrap();
+        }
+        for (index, table) in function.tables.iter().enumerate() {
+            let cases: Vec<String> = table.cases.iter().map(
+                |(case, offset)| format!("{} => {:04}", case, offset)
+            ).collect();
+            writeln!(
+                out, "  table {}: {}, _ => {:04}", index, cases.join(", "), table.default,
+            ).unwrap();
+        }
+
+        let mut offset = 0;
+        while offset < function.code.len() {
+            let (opcode, operand, next) = match function.decode(offset) {
+                Some(decoded) => decoded,
+                None => {
+                    writeln!(out, "  {:04}  <invalid>", offset).unwrap();
+                    break;
+                },
+            };
+
+            write!(out, "  {:04}  {}", offset, opcode.mnemonic()).unwrap();
+            match opcode {
+                Opcode::Const => write!(
+                    out, " {} ({})", operand, function.constants[operand as usize],
+                ).unwrap(),
+                Opcode::Str => write!(
+                    out, " {} ({:?})", operand, function.strings[operand as usize],
+                ).unwrap(),
+                Opcode::Call => write!(
+                    out, " {} (`{}`)", operand, program.functions[operand as usize].name,
+                ).unwrap(),
+                _ if opcode.operand() != Operand::None => {
+                    write!(out, " {}", operand).unwrap()
+                },
+                _ => (),
+            }
+            out.push('\n');
+
+            offset = next;
+        }
+    }
+
+    out
+}
@@ -0,0 +1,297 @@
+//! Compilation of IR to bytecode.
+//!
+//! Every IR value gets a local slot, with parameters first. Branches to a
+//! block store its arguments into its parameters' slots before jumping.
+//!
+//! A `str` value is the index of the string in its function's pool. Each
+//! `Slot` is at its offset in the function's memory.
+//!
+//! A `Switch` goes through a jump table to a branch for each case, which
+//! passes that target's arguments as any other branch does.
+
+use std::collections::HashMap;
+
+use ir::{ self, BinaryOp, Block, InstructionKind, Overflow, Terminator, Type, Value };
+use vm::bytecode::{ Function, Opcode, Program, Scalar, Table };
+
+pub fn compile(module: &ir::Module) -> Result<Program, String> {
+    let mut indices = HashMap::new();
+    for (index, function) in module.functions.iter().enumerate() {
+        indices.insert(function.name.as_str(), index as u16);
+    }
+
+    let mut functions = Vec::new();
+    for function in &module.functions {
+        functions.push(Compiler::new(&indices, function)?.compile()?);
+    }
+
+    Ok(Program {
+        functions,
+    })
+}
+
+/// The state of compiling a single function.
+///
+struct Compiler<'a> {
+    /// Function names to their index.
+    functions: &'a HashMap<&'a str, u16>,
+
+    source: &'a ir::Function,
+    /// The slot of each value.
+    slots: Vec<u16>,
+    /// The offset of each `Slot`'s memory.
+    memory: HashMap<Value, u32>,
+
+    function: Function,
+    /// The code offset of each block, once it's been compiled.
+    offsets: Vec<u32>,
+    /// Operands to patch with the offset of a block, once it's known.
+    fixups: Vec<(usize, Block)>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(
+        functions: &'a HashMap<&'a str, u16>,
+        source: &'a ir::Function,
+    ) -> Result<Self, String> {
+        if source.values.len() > u16::MAX as usize {
+            return Err(format!("too many locals in `{}`", source.name));
+        }
+
+        let parameters = source.parameters();
+        let mut slots = vec![0; source.values.len()];
+        for (slot, parameter) in parameters.iter().enumerate() {
+            slots[parameter.0] = slot as u16;
+        }
+        let mut next = parameters.len() as u16;
+        for (index, slot) in slots.iter_mut().enumerate() {
+            if !parameters.contains(&ir::Value(index)) {
+                *slot = next;
+                next += 1;
+            }
+        }
+
+        let mut function = Function::new(source.name.clone(), parameters.len() as u16);
+        function.results = source.returns.len() as u16;
+        function.locals = next;
+        let (memory, size) = source.slots();
+        function.memory = size;
+
+        Ok(Compiler {
+            functions,
+            source,
+            slots,
+            memory,
+            function,
+            offsets: vec![0; source.blocks.len()],
+            fixups: Vec::new(),
+        })
+    }
+
+    fn compile(mut self) -> Result<Function, String> {
+        for index in 0..self.source.blocks.len() {
+            self.offsets[index] = self.function.code.len() as u32;
+            self.block(Block(index))?;
+        }
+
+        for (offset, block) in self.fixups.drain(..) {
+            self.function.patch_u32(offset, self.offsets[block.0]);
+        }
+
+        Ok(self.function)
+    }
+
+    fn block(&mut self, block: Block) -> Result<(), String> {
+        let source = self.source;
+        let data = source.block(block);
+
+        for instruction in &data.instructions {
+            match instruction.kind {
+                InstructionKind::Const(value) => {
+                    let ty = source.type_of(instruction.result);
+                    let index = self.function.constant(Scalar::integer(ty, value))?;
+                    self.function.push_u16(Opcode::Const, index);
+                },
+                InstructionKind::Float(value) => {
+                    let value = match source.type_of(instruction.result) {
+                        Type::F32 => Scalar::F32(value as f32),
+                        _ => Scalar::F64(value),
+                    };
+                    let index = self.function.constant(value)?;
+                    self.function.push_u16(Opcode::Const, index);
+                },
+                InstructionKind::Str(ref string) => {
+                    let index = self.function.string(string)?;
+                    self.function.push_u16(Opcode::Str, index);
+                },
+                InstructionKind::Binary(op, overflow, lhs, rhs) => {
+                    self.load(lhs);
+                    self.load(rhs);
+                    self.function.push(match (op, overflow) {
+                        (BinaryOp::Add, Overflow::Wrap) => Opcode::Add,
+                        (BinaryOp::Sub, Overflow::Wrap) => Opcode::Sub,
+                        (BinaryOp::Mul, Overflow::Wrap) => Opcode::Mul,
+                        (BinaryOp::Add, Overflow::Trap) => Opcode::CheckedAdd,
+                        (BinaryOp::Sub, Overflow::Trap) => Opcode::CheckedSub,
+                        (BinaryOp::Mul, Overflow::Trap) => Opcode::CheckedMul,
+                        (BinaryOp::Add, Overflow::Saturate) => Opcode::SaturatingAdd,
+                        (BinaryOp::Sub, Overflow::Saturate) => Opcode::SaturatingSub,
+                        (BinaryOp::Mul, Overflow::Saturate) => Opcode::SaturatingMul,
+                        (BinaryOp::Eq, _) => Opcode::Eq,
+                        (BinaryOp::Ne, _) => Opcode::Ne,
+                        (BinaryOp::Lt, _) => Opcode::Lt,
+                        (BinaryOp::Le, _) => Opcode::Le,
+                        (BinaryOp::Gt, _) => Opcode::Gt,
+                        (BinaryOp::Ge, _) => Opcode::Ge,
+                    });
+                },
+                InstructionKind::Convert(value) => {
+                    self.load(value);
+                    self.function.push(match source.type_of(instruction.result) {
+                        Type::I8 => Opcode::ToI8,
+                        Type::I16 => Opcode::ToI16,
+                        Type::I32 => Opcode::ToI32,
+                        Type::I64 => Opcode::ToI64,
+                        Type::U8 => Opcode::ToU8,
+                        Type::U16 => Opcode::ToU16,
+                        Type::U32 => Opcode::ToU32,
+                        Type::U64 => Opcode::ToU64,
+                        Type::F32 => Opcode::ToF32,
+                        Type::F64 => Opcode::ToF64,
+                        Type::Char => Opcode::ToChar,
+                        Type::Str => return Err("conversion to str".to_string()),
+                        Type::Ptr => return Err("conversion to ptr".to_string()),
+                    });
+                },
+                InstructionKind::Call(ref name, ref arguments, ref more) => {
+                    let index = *self.functions.get(name.as_str()).ok_or_else(
+                        || format!("call to undefined function `{}`", name)
+                    )?;
+                    for argument in arguments {
+                        self.load(*argument);
+                    }
+                    self.function.push_u16(Opcode::Call, index);
+                    // The last value returned is on top.
+                    for value in more.iter().rev() {
+                        self.store(*value);
+                    }
+                },
+                InstructionKind::Print(value) => {
+                    self.load(value);
+                    self.function.push(match source.type_of(value) {
+                        Type::Str => Opcode::PrintStr,
+                        _ => Opcode::PrintNumber,
+                    });
+
+                    let zero = self.function.constant(Scalar::I32(0))?;
+                    self.function.push_u16(Opcode::Const, zero);
+                },
+                InstructionKind::Slot(..) => {
+                    let offset = self.memory[&instruction.result];
+                    self.function.push_u32(Opcode::Slot, offset);
+                },
+                InstructionKind::Load(address, offset) => {
+                    self.load(address);
+                    self.function.push_u32(Opcode::ReadMemory, offset);
+                },
+                InstructionKind::Store(address, offset, value) => {
+                    self.load(address);
+                    self.load(value);
+                    self.function.push_u32(Opcode::WriteMemory, offset);
+
+                    let zero = self.function.constant(Scalar::I32(0))?;
+                    self.function.push_u16(Opcode::Const, zero);
+                },
+                InstructionKind::Copy(to, to_offset, from, from_offset, size) => {
+                    self.address(to, to_offset);
+                    self.address(from, from_offset);
+                    self.function.push_u32(Opcode::CopyMemory, size);
+
+                    let zero = self.function.constant(Scalar::I32(0))?;
+                    self.function.push_u16(Opcode::Const, zero);
+                },
+                InstructionKind::Element(address, index, size) => {
+                    self.load(address);
+                    self.load(index);
+                    self.function.push_u32(Opcode::Element, size);
+                },
+            }
+            self.store(instruction.result);
+        }
+
+        match *data.terminator.as_ref().unwrap() {
+            Terminator::Return(ref values) => {
+                for value in values {
+                    self.load(*value);
+                }
+                self.function.push(Opcode::Return);
+            },
+            Terminator::Branch(ref target) => self.branch(target),
+            Terminator::CondBranch(condition, ref then_target, ref else_target) => {
+                self.load(condition);
+                let to_else = self.function.push_u32(Opcode::JumpIfZero, 0);
+                self.branch(then_target);
+
+                let else_offset = self.function.code.len() as u32;
+                self.function.patch_u32(to_else, else_offset);
+                self.branch(else_target);
+            },
+            Terminator::Trap => {
+                self.function.push(Opcode::Trap);
+            },
+            Terminator::Switch(value, ref cases, ref default) => {
+                self.load(value);
+                let index = self.function.table(Table {
+                    cases: Vec::new(),
+                    default: 0,
+                })?;
+                self.function.push_u16(Opcode::Switch, index);
+
+                let mut table_cases = Vec::new();
+                for &(case, ref target) in cases {
+                    table_cases.push((case, self.function.code.len() as u32));
+                    self.branch(target);
+                }
+                table_cases.sort();
+                let table = &mut self.function.tables[index as usize];
+                table.cases = table_cases;
+                table.default = self.function.code.len() as u32;
+                self.branch(default);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn branch(&mut self, target: &ir::Target) {
+        // Load every argument before storing any parameter, since a parameter
+        // may also be an argument.
+        for argument in &target.arguments {
+            self.load(*argument);
+        }
+        let parameters = &self.source.block(target.block).parameters;
+        for parameter in parameters.iter().rev() {
+            self.store(*parameter);
+        }
+
+        let offset = self.function.push_u32(Opcode::Jump, 0);
+        self.fixups.push((offset, target.block));
+    }
+
+    /// Push `value`, an address, plus `offset`.
+    ///
+    fn address(&mut self, value: Value, offset: u32) {
+        self.load(value);
+        if offset != 0 {
+            self.function.push_u32(Opcode::Offset, offset);
+        }
+    }
+
+    fn load(&mut self, value: Value) {
+        self.function.push_u16(Opcode::Load, self.slots[value.0]);
+    }
+
+    fn store(&mut self, value: Value) {
+        self.function.push_u16(Opcode::Store, self.slots[value.0]);
+    }
+}
@@ -0,0 +1,694 @@
+//! The stack machine that executes bytecode.
+
+use std::io::{ self, Write };
+
+use vm::bytecode::{ Function, Opcode, Program, Scalar };
+
+/// Apply `$integer`, a method of every integer type, or the operator
+/// `$float` to two `Scalar`s of the same type, giving one of that type; or
+/// `None` if they differ.
+///
+macro_rules! arithmetic {
+    ($lhs:expr, $rhs:expr, $integer:ident, $float:tt) => {
+        match ($lhs, $rhs) {
+            (Scalar::I8(a), Scalar::I8(b)) => Some(Scalar::I8(a.$integer(b))),
+            (Scalar::I16(a), Scalar::I16(b)) => Some(Scalar::I16(a.$integer(b))),
+            (Scalar::I32(a), Scalar::I32(b)) => Some(Scalar::I32(a.$integer(b))),
+            (Scalar::I64(a), Scalar::I64(b)) => Some(Scalar::I64(a.$integer(b))),
+            (Scalar::U8(a), Scalar::U8(b)) => Some(Scalar::U8(a.$integer(b))),
+            (Scalar::U16(a), Scalar::U16(b)) => Some(Scalar::U16(a.$integer(b))),
+            (Scalar::U32(a), Scalar::U32(b)) => Some(Scalar::U32(a.$integer(b))),
+            (Scalar::U64(a), Scalar::U64(b)) => Some(Scalar::U64(a.$integer(b))),
+            (Scalar::F32(a), Scalar::F32(b)) => Some(Scalar::F32(a $float b)),
+            (Scalar::F64(a), Scalar::F64(b)) => Some(Scalar::F64(a $float b)),
+            _ => None,
+        }
+    };
+}
+
+/// Apply `$method`, a method of every integer type giving an `Option`, to two
+/// `Scalar`s of the same integer type: `Some` of its result; or `None` if they
+/// differ or aren't integers.
+///
+macro_rules! checked {
+    ($lhs:expr, $rhs:expr, $method:ident) => {
+        match ($lhs, $rhs) {
+            (Scalar::I8(a), Scalar::I8(b)) => Some(a.$method(b).map(Scalar::I8)),
+            (Scalar::I16(a), Scalar::I16(b)) => Some(a.$method(b).map(Scalar::I16)),
+            (Scalar::I32(a), Scalar::I32(b)) => Some(a.$method(b).map(Scalar::I32)),
+            (Scalar::I64(a), Scalar::I64(b)) => Some(a.$method(b).map(Scalar::I64)),
+            (Scalar::U8(a), Scalar::U8(b)) => Some(a.$method(b).map(Scalar::U8)),
+            (Scalar::U16(a), Scalar::U16(b)) => Some(a.$method(b).map(Scalar::U16)),
+            (Scalar::U32(a), Scalar::U32(b)) => Some(a.$method(b).map(Scalar::U32)),
+            (Scalar::U64(a), Scalar::U64(b)) => Some(a.$method(b).map(Scalar::U64)),
+            _ => None,
+        }
+    };
+}
+
+/// Apply `$method`, a method of every integer type, to two `Scalar`s of the
+/// same integer type; or `None` if they differ or aren't integers.
+///
+macro_rules! integer {
+    ($lhs:expr, $rhs:expr, $method:ident) => {
+        match ($lhs, $rhs) {
+            (Scalar::I8(a), Scalar::I8(b)) => Some(Scalar::I8(a.$method(b))),
+            (Scalar::I16(a), Scalar::I16(b)) => Some(Scalar::I16(a.$method(b))),
+            (Scalar::I32(a), Scalar::I32(b)) => Some(Scalar::I32(a.$method(b))),
+            (Scalar::I64(a), Scalar::I64(b)) => Some(Scalar::I64(a.$method(b))),
+            (Scalar::U8(a), Scalar::U8(b)) => Some(Scalar::U8(a.$method(b))),
+            (Scalar::U16(a), Scalar::U16(b)) => Some(Scalar::U16(a.$method(b))),
+            (Scalar::U32(a), Scalar::U32(b)) => Some(Scalar::U32(a.$method(b))),
+            (Scalar::U64(a), Scalar::U64(b)) => Some(Scalar::U64(a.$method(b))),
+            _ => None,
+        }
+    };
+}
+
+/// Compare two `Scalar`s of the same type with `$op`; or `None` if they
+/// differ.
+///
+macro_rules! comparison {
+    ($lhs:expr, $rhs:expr, $op:tt) => {
+        match ($lhs, $rhs) {
+            (Scalar::I8(a), Scalar::I8(b)) => Some(a $op b),
+            (Scalar::I16(a), Scalar::I16(b)) => Some(a $op b),
+            (Scalar::I32(a), Scalar::I32(b)) => Some(a $op b),
+            (Scalar::I64(a), Scalar::I64(b)) => Some(a $op b),
+            (Scalar::U8(a), Scalar::U8(b)) => Some(a $op b),
+            (Scalar::U16(a), Scalar::U16(b)) => Some(a $op b),
+            (Scalar::U32(a), Scalar::U32(b)) => Some(a $op b),
+            (Scalar::U64(a), Scalar::U64(b)) => Some(a $op b),
+            (Scalar::F32(a), Scalar::F32(b)) => Some(a $op b),
+            (Scalar::F64(a), Scalar::F64(b)) => Some(a $op b),
+            (Scalar::Char(a), Scalar::Char(b)) => Some(a $op b),
+            _ => None,
+        }
+    };
+}
+
+/// Convert a `Scalar` to the Rust type `$ty` with `as`, whose rules are the
+/// IR's; a `char` converts as its `u32` scalar value.
+///
+macro_rules! convert {
+    ($scalar:expr, $ty:ty) => {
+        match $scalar {
+            Scalar::I8(value) => value as $ty,
+            Scalar::I16(value) => value as $ty,
+            Scalar::I32(value) => value as $ty,
+            Scalar::I64(value) => value as $ty,
+            Scalar::U8(value) => value as $ty,
+            Scalar::U16(value) => value as $ty,
+            Scalar::U32(value) => value as $ty,
+            Scalar::U64(value) => value as $ty,
+            Scalar::F32(value) => value as $ty,
+            Scalar::F64(value) => value as $ty,
+            Scalar::Char(value) => value as u32 as $ty,
+            Scalar::Ptr(value) => value as $ty,
+        }
+    };
+}
+
+/// A function activation.
+///
+struct Frame {
+    function: usize,
+    /// Offset of the next instruction in the function's code.
+    ip: usize,
+    /// Index in the stack of the first local.
+    base: usize,
+    /// Address of the frame's memory.
+    memory: usize,
+}
+
+pub struct Machine<'a> {
+    program: &'a Program,
+
+    /// Locals and operands of every frame, with each frame's locals below its
+    /// operands.
+    stack: Vec<Scalar>,
+    frames: Vec<Frame>,
+    /// The memory of every frame, by address; `None` where nothing has been
+    /// stored.
+    memory: Vec<Option<Scalar>>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Machine {
+            program,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+
+    /// Run the function `name` with `arguments` to completion; return its
+    /// result.
+    ///
+    pub fn run(
+        &mut self,
+        name: &str,
+        arguments: &[Scalar],
+    ) -> Result<Scalar, String> {
+        let function = self.program.function(name).ok_or_else(
+            || format!("no function `{}` to run", name)
+        )?;
+
+        self.stack.clear();
+        self.frames.clear();
+        self.memory.clear();
+
+        self.stack.extend_from_slice(arguments);
+        self.call(function)?;
+
+        loop {
+            let (function, ip) = {
+                let frame = self.frames.last().unwrap();
+                (&self.program.functions[frame.function], frame.ip)
+            };
+
+            let (opcode, operand, next) = function.decode(ip).ok_or_else(
+                || format!("invalid instruction in `{}` at {}", function.name, ip)
+            )?;
+            self.frames.last_mut().unwrap().ip = next;
+
+            match opcode {
+                Opcode::Const => {
+                    let value = *function.constants.get(operand as usize)
+                        .ok_or("constant index out of range")?;
+                    self.stack.push(value);
+                },
+                Opcode::Str => self.stack.push(Scalar::I32(operand as i32)),
+                Opcode::Load => {
+                    let value = self.stack[self.local(operand)?];
+                    self.stack.push(value);
+                },
+                Opcode::Store => {
+                    let value = self.pop()?;
+                    let local = self.local(operand)?;
+                    self.stack[local] = value;
+                },
+                Opcode::Pop => {
+                    self.pop()?;
+                },
+
+                // Integer arithmetic wraps, as it does in the other back-ends,
+                // unless it's checked or saturating.
+                Opcode::Add => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(arithmetic!(lhs, rhs, wrapping_add, +))?;
+                },
+                Opcode::Sub => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(arithmetic!(lhs, rhs, wrapping_sub, -))?;
+                },
+                Opcode::Mul => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(arithmetic!(lhs, rhs, wrapping_mul, *))?;
+                },
+                Opcode::CheckedAdd => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_checked(checked!(lhs, rhs, checked_add), function)?;
+                },
+                Opcode::CheckedSub => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_checked(checked!(lhs, rhs, checked_sub), function)?;
+                },
+                Opcode::CheckedMul => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_checked(checked!(lhs, rhs, checked_mul), function)?;
+                },
+                Opcode::SaturatingAdd => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(integer!(lhs, rhs, saturating_add))?;
+                },
+                Opcode::SaturatingSub => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(integer!(lhs, rhs, saturating_sub))?;
+                },
+                Opcode::SaturatingMul => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push(integer!(lhs, rhs, saturating_mul))?;
+                },
+
+                Opcode::Eq => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, ==))?;
+                },
+                Opcode::Ne => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, !=))?;
+                },
+                Opcode::Lt => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, <))?;
+                },
+                Opcode::Le => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, <=))?;
+                },
+                Opcode::Gt => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, >))?;
+                },
+                Opcode::Ge => {
+                    let (lhs, rhs) = self.pop_pair()?;
+                    self.push_bool(comparison!(lhs, rhs, >=))?;
+                },
+
+                Opcode::ToI8 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::I8(convert!(value, i8)));
+                },
+                Opcode::ToI16 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::I16(convert!(value, i16)));
+                },
+                Opcode::ToI32 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::I32(convert!(value, i32)));
+                },
+                Opcode::ToI64 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::I64(convert!(value, i64)));
+                },
+                Opcode::ToU8 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::U8(convert!(value, u8)));
+                },
+                Opcode::ToU16 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::U16(convert!(value, u16)));
+                },
+                Opcode::ToU32 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::U32(convert!(value, u32)));
+                },
+                Opcode::ToU64 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::U64(convert!(value, u64)));
+                },
+                Opcode::ToF32 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::F32(convert!(value, f32)));
+                },
+                Opcode::ToF64 => {
+                    let value = self.pop()?;
+                    self.stack.push(Scalar::F64(convert!(value, f64)));
+                },
+                Opcode::ToChar => {
+                    let value = convert!(self.pop()?, i128);
+                    let scalar = match (0..=0x10FFFF).contains(&value) {
+                        true  => std::char::from_u32(value as u32),
+                        false => None,
+                    };
+                    self.stack.push(Scalar::Char(scalar.unwrap_or('\u{FFFD}')));
+                },
+
+                Opcode::Call => self.call(operand as usize)?,
+                Opcode::Return => {
+                    let mut values = Vec::new();
+                    for _ in 0..function.results {
+                        values.push(self.pop()?);
+                    }
+                    values.reverse();
+                    let frame = self.frames.pop().unwrap();
+
+                    self.stack.truncate(frame.base);
+                    self.memory.truncate(frame.memory);
+                    if self.frames.is_empty() {
+                        return values.first().cloned().ok_or_else(
+                            || format!("`{}` returns nothing", function.name)
+                        );
+                    }
+                    self.stack.extend(values);
+                },
+
+                Opcode::Jump => {
+                    self.frames.last_mut().unwrap().ip = operand as usize;
+                },
+                Opcode::JumpIfZero => {
+                    if self.pop_i32()? == 0 {
+                        self.frames.last_mut().unwrap().ip = operand as usize;
+                    }
+                },
+
+                Opcode::PrintNumber => {
+                    let text = match self.pop()? {
+                        Scalar::F32(value) => format_float(value as f64),
+                        Scalar::F64(value) => format_float(value),
+                        Scalar::Char(value) => value.to_string(),
+                        integer => convert!(integer, i128).to_string(),
+                    };
+                    print(&text)?;
+                },
+                Opcode::PrintStr => {
+                    let index = self.pop_i32()?;
+                    let string = function.strings.get(index as usize)
+                        .ok_or("string index out of range")?;
+                    print(string)?;
+                },
+
+                Opcode::Slot => {
+                    let address = self.frames.last().unwrap().memory + operand as usize;
+                    self.stack.push(Scalar::Ptr(address as u32));
+                },
+                Opcode::ReadMemory => {
+                    let address = self.address(operand)?;
+                    let value = self.memory[address].ok_or_else(
+                        || format!("read of uninitialised memory in `{}`", function.name)
+                    )?;
+                    self.stack.push(value);
+                },
+                Opcode::WriteMemory => {
+                    let value = self.pop()?;
+                    let address = self.address(operand)?;
+                    self.memory[address] = Some(value);
+                },
+                Opcode::Offset => {
+                    let address = self.address(operand)?;
+                    self.stack.push(Scalar::Ptr(address as u32));
+                },
+                Opcode::CopyMemory => {
+                    let from = self.address(0)?;
+                    let to = self.address(0)?;
+                    let size = operand as usize;
+                    if from.max(to) + size > self.memory.len() {
+                        return Err("memory address out of range".to_string());
+                    }
+                    self.memory.copy_within(from..from + size, to);
+                },
+                Opcode::Switch => {
+                    let table = function.tables.get(operand as usize)
+                        .ok_or("jump table index out of range")?;
+                    let value = match self.pop()? {
+                        Scalar::F32(_) | Scalar::F64(_) | Scalar::Ptr(_) => {
+                            return Err("expected an integer or char on the stack".to_string())
+                        },
+                        value => convert!(value, i128),
+                    };
+                    self.frames.last_mut().unwrap().ip = table.target(value) as usize;
+                },
+                Opcode::Element => {
+                    let index = match self.pop()? {
+                        Scalar::U64(index) => index,
+                        _ => return Err("expected a u64 on the stack".to_string()),
+                    };
+                    let address = self.address(0)? as u64;
+                    let address = index.checked_mul(operand as u64)
+                        .and_then(|offset| offset.checked_add(address))
+                        .filter(|&address| address < self.memory.len() as u64)
+                        .ok_or("memory address out of range")?;
+                    self.stack.push(Scalar::Ptr(address as u32));
+                },
+                Opcode::Trap => return Err(format!("trap in `{}`", function.name)),
+            }
+        }
+    }
+
+    /// Enter `function`, whose arguments are on top of the stack.
+    ///
+    fn call(&mut self, function: usize) -> Result<(), String> {
+        let function_index = function;
+        let function = self.program.functions.get(function_index)
+            .ok_or("function index out of range")?;
+
+        let arity = function.arity as usize;
+        if self.stack.len() < arity {
+            return Err(format!("too few arguments to `{}`", function.name));
+        }
+        let base = self.stack.len() - arity;
+
+        // The non-parameter locals start at 0.
+        for _ in function.arity..function.locals {
+            self.stack.push(Scalar::I32(0));
+        }
+
+        let memory = self.memory.len();
+        self.memory.resize(memory + function.memory as usize, None);
+
+        self.frames.push(Frame {
+            function: function_index,
+            ip: 0,
+            base,
+            memory,
+        });
+        Ok(())
+    }
+
+    /// Get the stack index of local `slot` in the current frame.
+    ///
+    fn local(&self, slot: u32) -> Result<usize, String> {
+        let frame = self.frames.last().unwrap();
+        let function = &self.program.functions[frame.function];
+
+        if slot >= function.locals as u32 {
+            return Err(format!("local index out of range in `{}`", function.name));
+        }
+        Ok(frame.base + slot as usize)
+    }
+
+    /// Pop an address, and get it plus `offset`, checking it's in memory.
+    ///
+    fn address(&mut self, offset: u32) -> Result<usize, String> {
+        let address = match self.pop()? {
+            Scalar::Ptr(address) => address as usize + offset as usize,
+            _ => return Err("expected an address on the stack".to_string()),
+        };
+        match address < self.memory.len() {
+            true  => Ok(address),
+            false => Err("memory address out of range".to_string()),
+        }
+    }
+
+    fn pop(&mut self) -> Result<Scalar, String> {
+        // Operands never reach down into the locals of a correctly-compiled
+        // frame, so only the stack as a whole is checked.
+        self.stack.pop().ok_or_else(|| "stack underflow".to_string())
+    }
+
+    fn pop_i32(&mut self) -> Result<i32, String> {
+        match self.pop()? {
+            Scalar::I32(value) => Ok(value),
+            _ => Err("expected an i32 on the stack".to_string()),
+        }
+    }
+
+    /// Pop the operands of a binary operation: the right-hand one is on top.
+    ///
+    fn pop_pair(&mut self) -> Result<(Scalar, Scalar), String> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        Ok((lhs, rhs))
+    }
+
+    /// Push the result of `arithmetic!` or `integer!`.
+    ///
+    fn push(&mut self, result: Option<Scalar>) -> Result<(), String> {
+        let result = result.ok_or("mismatched types on the stack")?;
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// Push the result of `checked!`, done in `function`; stop if it
+    /// overflowed.
+    ///
+    fn push_checked(
+        &mut self,
+        result: Option<Option<Scalar>>,
+        function: &Function,
+    ) -> Result<(), String> {
+        let result = result.ok_or("integers of mismatched types on the stack")?;
+        let result = result.ok_or_else(
+            || format!("arithmetic overflow in `{}`", function.name)
+        )?;
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// Push the result of `comparison!`, as an `i32` of 1 or 0.
+    ///
+    fn push_bool(&mut self, result: Option<bool>) -> Result<(), String> {
+        let result = result.ok_or("mismatched types on the stack")?;
+        self.stack.push(Scalar::I32(result as i32));
+        Ok(())
+    }
+}
+
+fn print(string: &str) -> Result<(), String> {
+    io::stdout().write_all(string.as_bytes())
+        .map_err(|error| format!("can't print: {}", error))
+}
+
+/// Format a float as C's `printf("%g")` does, as the native back-ends print
+/// them: to 6 significant figures, in exponent form if the exponent is below
+/// -4 or above 5, and without trailing zeros. A NaN is `nan` whatever its
+/// sign, as they make sure too.
+///
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+
+    // Round to 6 figures first, since that may carry into the exponent.
+    let exponent_form = format!("{:.5e}", value);
+    let (mantissa, exponent) = exponent_form.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    if (-4..6).contains(&exponent) {
+        let precision = (5 - exponent).max(0) as usize;
+        trim_zeros(&format!("{:.*}", precision, value)).to_string()
+    }
+    else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim_zeros(mantissa), sign, exponent.abs())
+    }
+}
+
+/// Trim trailing zeros after a decimal point, and the point if that's all.
+///
+fn trim_zeros(number: &str) -> &str {
+    match number.contains('.') {
+        true  => number.trim_end_matches('0').trim_end_matches('.'),
+        false => number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ format_float, Machine };
+    use ir::{ self, Overflow };
+    use lex::Lexer;
+    use opt::{ self, Level };
+    use parse::Parser;
+    use vm::bytecode::Scalar;
+    use vm::compile::compile;
+
+    /// Run `function` of `source` with `arguments`, with overflow handled as
+    /// `overflow`. It's run as lowered and as optimised at `-O2`, which must
+    /// agree.
+    ///
+    fn run(
+        source: &str,
+        overflow: Overflow,
+        function: &str,
+        arguments: &[Scalar],
+    ) -> Result<Scalar, String> {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let tree = Parser::new().parse(tokens).unwrap();
+        let module = ir::lower::lower(&tree, overflow).unwrap();
+        let mut optimised = module.clone();
+        opt::optimise(&mut optimised, Level::O2).unwrap();
+
+        let results: Vec<Result<Scalar, String>> = [module, optimised].iter().map(|module| {
+            let program = compile(module).unwrap();
+            Machine::new(&program).run(function, arguments)
+        }).collect();
+        assert_eq!(results[0], results[1], "as lowered and optimised");
+        results[0].clone()
+    }
+
+    #[test]
+    fn runs_programs_to_their_results() {
+        let cases: &[(&str, &[Scalar], Scalar)] = &[
+            ("f(a: i32, b: i32) -> i32 { return a * 3 + b - 1; }",
+             &[Scalar::I32(4), Scalar::I32(5)], Scalar::I32(16)),
+            ("f(n: i64) -> i64 { if n < 2 { return n; } return f(n - 1) + f(n - 2); }",
+             &[Scalar::I64(20)], Scalar::I64(6765)),
+            ("f(x: u8) -> u64 { return x as u64 * 1000000000000; }",
+             &[Scalar::U8(200)], Scalar::U64(200000000000000)),
+            ("f(x: f64) -> f64 { return x * 0.5 + 1.0; }",
+             &[Scalar::F64(3.0)], Scalar::F64(2.5)),
+            ("f(x: i32) -> i32 { \
+                  match x { 0 => { return 10; }, 2 => { return 20; }, _ => { return 30; } } \
+              }",
+             &[Scalar::I32(2)], Scalar::I32(20)),
+            ("f(i: u64) -> i32 { a = [1, 2, 3, 4]; a[1] = 10; return a[i] + a[1]; }",
+             &[Scalar::U64(3)], Scalar::I32(14)),
+            ("struct P { x: i32, ys: [u8; 2] } \
+              bump(p: &mut P) { p.x = p.x + 1; p.ys[1] = 9; } \
+              f(x: i32) -> i32 { p = P { x: x, ys: [1, 2] }; bump(&mut p); \
+                                 return p.x + p.ys[1] as i32; }",
+             &[Scalar::I32(5)], Scalar::I32(15)),
+            ("pair(x: i32) -> (i32, char) { return (x + 1, 'z'); } \
+              f(x: i32) -> char { (n, c) := pair(x); if n > 0 { return c; } return 'a'; }",
+             &[Scalar::I32(0)], Scalar::Char('z')),
+        ];
+        for &(source, arguments, ref expected) in cases {
+            assert_eq!(
+                run(source, Overflow::Trap, "f", arguments).as_ref(), Ok(expected), "{}", source,
+            );
+        }
+    }
+
+    #[test]
+    fn overflow_wraps_saturates_or_traps() {
+        let source = "f(x: i8) -> i8 { return x + 100; }";
+        let cases = [
+            (Overflow::Wrap, Ok(Scalar::I8(-56))),
+            (Overflow::Saturate, Ok(Scalar::I8(127))),
+            (Overflow::Trap, Err("arithmetic overflow in `f`".to_string())),
+        ];
+        for (overflow, expected) in cases {
+            assert_eq!(run(source, overflow, "f", &[Scalar::I8(100)]), expected);
+        }
+        assert_eq!(run(source, Overflow::Trap, "f", &[Scalar::I8(27)]), Ok(Scalar::I8(127)));
+    }
+
+    #[test]
+    fn out_of_bounds_indices_trap() {
+        let sources = [
+            "f(i: u64) -> i32 { a = [1, 2, 3, 4]; return a[i]; }",
+            "f(i: u64) -> i32 { a = [1, 2, 3, 4]; s: &[i32] = &a; return s[i]; }",
+        ];
+        for source in sources {
+            assert_eq!(run(source, Overflow::Trap, "f", &[Scalar::U64(3)]), Ok(Scalar::I32(4)));
+            for index in [4, u64::MAX] {
+                assert_eq!(
+                    run(source, Overflow::Trap, "f", &[Scalar::U64(index)]),
+                    Err("trap in `f`".to_string()),
+                    "{}", source,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn running_needs_the_function_and_its_arguments() {
+        let source = "f(x: i32) -> i32 { return x; }";
+        assert_eq!(
+            run(source, Overflow::Trap, "g", &[]),
+            Err("no function `g` to run".to_string()),
+        );
+        assert_eq!(
+            run(source, Overflow::Trap, "f", &[]),
+            Err("too few arguments to `f`".to_string()),
+        );
+    }
+
+    #[test]
+    fn floats_print_as_printf_g_does() {
+        let cases = [
+            (1.25, "1.25"),
+            (0.1 + 0.2, "0.3"),
+            (100000.0, "100000"),
+            (1234567.0, "1.23457e+06"),
+            (999999.5, "1e+06"),
+            (0.0001, "0.0001"),
+            (0.00001234, "1.234e-05"),
+            (-2.5e-300, "-2.5e-300"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+            // Whatever the sign, as the native back-ends make sure.
+            (f64::NAN, "nan"),
+            (-f64::NAN, "nan"),
+        ];
+        for (value, printed) in cases {
+            assert_eq!(format_float(value), printed, "{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! A compact bytecode, a compiler to it from IR, and a stack machine to
+//! execute it.
+//!
+//! The bytecode format is documented in `bytecode`.
+
+pub mod bytecode;
+pub mod compile;
+pub mod machine;